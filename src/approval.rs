@@ -0,0 +1,181 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{message_component::MessageComponentInteractionData, Interaction},
+    channel::{
+        message::component::{ActionRow, Button, ButtonStyle},
+        Message,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+    user::User,
+};
+
+use crate::{
+    confirmation, do_pin, ephemeral, events::PinEvent, gate, jump_link, label, lock, metrics,
+    pinlog, time, Context,
+};
+
+/// Prefix of the approval button custom id, followed by the message, requester and expiry
+pub const BUTTON_PREFIX: &str = "unpin-approval";
+/// Told to everyone who tries to unpin in a way that can't wait for an approval
+pub const REQUIRED: &str =
+    "Unpins need the approval of a second moderator in this server, unpin one message at a time.";
+
+/// Ask another moderator to approve the unpin, in public so they can see it
+pub async fn request(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+    requester: &User,
+) -> Result<()> {
+    let requester_id = requester.id;
+    let expires_at = time::now().saturating_add(ctx.config.unpin_approval_secs);
+    let button = Button {
+        style: ButtonStyle::Danger,
+        url: None,
+        custom_id: Some(format!(
+            "{BUTTON_PREFIX}:{}:{requester_id}:{expires_at}",
+            message.id
+        )),
+        disabled: false,
+        label: Some("Approve unpin".to_owned()),
+        emoji: None,
+    };
+    let link = jump_link(guild_id, message.channel_id, message.id);
+    let response = InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some(format!(
+                "<@{requester_id}> wants to unpin [this message]({link}). Another moderator has \
+                 to approve it until <t:{expires_at}:t>."
+            )),
+            components: Some(row!(button).into()),
+            allowed_mentions: Some(confirmation::NO_PINGS),
+            ..Default::default()
+        }),
+    };
+
+    log::info!(
+        "[{}] {} asked for approval to unpin {}",
+        message.channel_id,
+        requester.name,
+        message.id
+    );
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Unpin the message once a different moderator clicks the approval button
+pub async fn approve(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let (Some(guild_id), Some(approver), Some(channel_id)) = (
+        event.guild_id,
+        event.author(),
+        event.channel.as_ref().map(|channel| channel.id),
+    ) else {
+        return Ok(());
+    };
+    let mut parts = data.custom_id.split(':').skip(1);
+    let (Some(message_id), Some(requester_id), Some(expires_at)) = (
+        parts.next().and_then(|it| it.parse().ok()),
+        parts
+            .next()
+            .and_then(|it| it.parse::<Id<UserMarker>>().ok()),
+        parts.next().and_then(|it| it.parse::<u64>().ok()),
+    ) else {
+        return Ok(());
+    };
+    let link = jump_link(guild_id, channel_id, message_id);
+
+    if time::now() > expires_at {
+        let content =
+            format!("The request to unpin [this message]({link}) expired without an approval.");
+        client
+            .create_response(event.id, &event.token, &update(content))
+            .await?;
+        return Ok(());
+    }
+
+    // Settings could have changed since the request was posted
+    let rejection = if approver.id == requester_id {
+        Some("Someone other than you has to approve this.".to_owned())
+    } else if !label::can_manage_messages(event) {
+        Some("You need the **Manage Messages** permission to approve this.".to_owned())
+    } else if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, false) {
+        Some(content)
+    } else if lock::prevents_unpin(ctx, event, message_id) {
+        Some(lock::LOCKED.to_owned())
+    } else {
+        None
+    };
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{} approved an unpin in {channel_name}", approver.name);
+    if let Err(e) = do_pin(&ctx.http, channel_id, message_id, false, &reason).await {
+        metrics::observe_pin_error(&e, "delete_pin");
+        log::error!("Failed to process pin due to error: {}", e);
+        let response = ephemeral(ctx.config.pin_error_message(e.code()));
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    if ctx.store.remove_timed_unpin(message_id).await? {
+        ctx.scheduler.wake();
+    }
+    pinlog::record_with_reason(
+        ctx,
+        PinEvent::new(false, guild_id, channel_id, message_id, requester_id),
+        Some(format!("approved by {}", approver.name)),
+    )
+    .await;
+
+    log::info!(
+        "[{}] {} approved the unpin of {} by {}",
+        channel_id,
+        approver.name,
+        message_id,
+        requester_id
+    );
+    let content = format!(
+        "Unpinned [this message]({link}), requested by <@{requester_id}> and approved by <@{}>.",
+        approver.id
+    );
+    client
+        .create_response(event.id, &event.token, &update(content))
+        .await?;
+    Ok(())
+}
+
+/// Replace the request, without the button
+fn update(content: String) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            content: Some(content),
+            components: Some(Vec::new()),
+            allowed_mentions: Some(confirmation::NO_PINGS),
+            ..Default::default()
+        }),
+    }
+}
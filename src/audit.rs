@@ -0,0 +1,80 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::Message,
+    guild::{audit_log::AuditLogEventType, Permissions},
+    id::{marker::GuildMarker, Id},
+};
+
+use crate::{confirmation, ephemeral, error, time, Context, DEFER_EPHEMERAL};
+
+const MISSING_PERMISSION: &str = "I need the **View Audit Log** permission to look this up.";
+
+/// Handle "Who Pinned", which looks up the pin of the message in the audit log
+pub async fn who_pinned(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let permitted = event
+        .app_permissions
+        .is_none_or(|perms| perms.contains(Permissions::VIEW_AUDIT_LOG));
+    if !permitted {
+        client
+            .create_response(event.id, &event.token, &ephemeral(MISSING_PERMISSION))
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let request = ctx
+        .http
+        .audit_log(guild_id)
+        .action_type(AuditLogEventType::MessagePin)
+        .limit(100)?;
+
+    let content = match request.await {
+        Ok(response) => {
+            let audit_log = response.model().await?;
+            let entry = audit_log.entries.iter().find(|entry| {
+                entry
+                    .options
+                    .as_ref()
+                    .is_some_and(|it| it.message_id == Some(message.id))
+            });
+
+            match entry {
+                Some(entry) => {
+                    let timestamp = time::snowflake_timestamp(entry.id.get());
+                    let mut content = match entry.user_id {
+                        Some(user_id) => format!("Pinned by <@{user_id}> <t:{timestamp}:R>."),
+                        None => format!("Pinned <t:{timestamp}:R>."),
+                    };
+                    // Pins done through the bot carry the actual user in the reason
+                    if let Some(ref reason) = entry.reason {
+                        content.push_str(&format!("\nReason: {reason}"));
+                    }
+                    content
+                }
+                None => "I couldn't find this pin in the recent audit log.".to_owned(),
+            }
+        }
+        Err(e) if error::api_code(&e) == Some(error::MISSING_PERMISSIONS) => {
+            MISSING_PERMISSION.to_owned()
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    client
+        .create_followup(&event.token)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
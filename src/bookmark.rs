@@ -0,0 +1,99 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::{
+        message::{
+            component::{ActionRow, Button, ButtonStyle},
+            MessageFlags,
+        },
+        Message,
+    },
+    id::{
+        marker::{GuildMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{confirmation, error, jump_link, metrics, Context, DEFER_EPHEMERAL};
+
+/// Longest excerpt of the message sent in the bookmark, the rest is behind the link
+const EXCERPT_LENGTH: usize = 1500;
+
+/// Handle "Bookmark", which sends the message to the DMs of the user without pinning it
+pub async fn bookmark(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let content = match send(ctx, user.id, guild_id, message).await {
+        Ok(()) => "Sent you the message in your DMs.",
+        Err(e) if error::error_code(&e) == Some(error::CANNOT_DM) => {
+            "I couldn't DM you. Allow direct messages from server members and try again."
+        }
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "bookmark");
+            }
+            log::error!("Failed to send bookmark: {e}");
+            "I couldn't DM you the message, try again later."
+        }
+    };
+    client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .content(content)?
+        .await?;
+    Ok(())
+}
+
+async fn send(
+    ctx: &Context,
+    user_id: Id<UserMarker>,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+) -> Result<()> {
+    let channel = ctx
+        .http
+        .create_private_channel(user_id)
+        .await?
+        .model()
+        .await?;
+
+    let mut content = format!(
+        "\u{1F516} Bookmarked from <#{}>, by <@{}>:",
+        message.channel_id, message.author.id
+    );
+    if message.content.is_empty() {
+        content.push_str("\n*Open the message to see it.*");
+    } else {
+        let mut excerpt: String = message.content.chars().take(EXCERPT_LENGTH).collect();
+        if excerpt.len() < message.content.len() {
+            excerpt.push('\u{2026}');
+        }
+        content.push_str("\n>>> ");
+        content.push_str(&excerpt);
+    }
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, message.channel_id, message.id)
+    ));
+
+    ctx.http
+        .create_message(channel.id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::Message,
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{
+    confirmation, defer, do_pin, ephemeral, error, events::PinEvent, gate, link, locale, metrics,
+    pinlog, pins::PinApi, reaction, Context,
+};
+
+/// Discord doesn't allow more pins in a single channel
+pub const MAX_PINS: usize = 50;
+/// Most links handled in one command, so the response arrives in reasonable time
+const MAX_LINKS: usize = 25;
+/// Messages of the history looked at by `/pin-reacted`, unless specified otherwise
+const DEFAULT_SCAN: usize = 100;
+const MAX_SCAN: usize = 500;
+/// Pause between two pins, to stay well clear of the ratelimit
+pub const PIN_SPACING: Duration = Duration::from_millis(750);
+
+/// Why a single message of the batch was not pinned
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    InvalidLink,
+    AlreadyPinned,
+    LimitReached,
+    Error,
+}
+
+impl Failure {
+    const fn describe(self) -> &'static str {
+        match self {
+            Self::InvalidLink => "not a message link of this channel",
+            Self::AlreadyPinned => "already pinned",
+            Self::LimitReached => "limit reached",
+            Self::Error => "request failed",
+        }
+    }
+}
+
+/// Handle `/bulk-pin`, which pins every linked message of this channel in order
+pub async fn bulk_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let input = data
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value.as_str()),
+            _ => None,
+        })
+        .unwrap_or("");
+
+    let links: Vec<&str> = input.split_whitespace().collect();
+    if let Some(content) = bulk_rejection(ctx, event, guild_id, channel_id, &links) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    // Links to other channels are rejected, since the user might not be able to see them
+    let targets = links
+        .iter()
+        .map(|input| {
+            link::parse_message_ref(input)
+                .filter(|it| it.is_in(guild_id, channel_id))
+                .map(|it| it.message_id)
+        })
+        .collect();
+    pin_all(ctx, event, guild_id, channel_id, targets).await
+}
+
+/// Handle `/pin-reacted`, which pins the recent messages of this channel with enough reactions
+pub async fn pin_reacted(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut emoji = None;
+    let mut min_count = 1;
+    let mut limit = DEFAULT_SCAN;
+    for option in &data.options {
+        match (option.name.as_str(), &option.value) {
+            ("emoji", CommandOptionValue::String(value)) => emoji = Some(reaction::emoji_id(value)),
+            ("count", CommandOptionValue::Integer(value)) => min_count = (*value).max(1) as u64,
+            ("messages", CommandOptionValue::Integer(value)) => {
+                limit = (*value).clamp(1, MAX_SCAN as i64) as usize;
+            }
+            _ => {}
+        }
+    }
+
+    let rejection = gate::rejection(ctx, event, guild_id, channel_id, true).or_else(|| {
+        emoji
+            .is_none()
+            .then(|| "Pick an emoji to look for.".to_owned())
+    });
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let Some(emoji) = emoji else {
+        return Ok(());
+    };
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    // History comes newest first, in pages of at most 100 messages
+    let mut messages = Vec::new();
+    while messages.len() < limit {
+        let page_size = (limit - messages.len()).min(100) as u16;
+        let request = ctx.http.channel_messages(channel_id);
+        let page = match messages.last().map(|it: &Message| it.id) {
+            Some(before) => request.before(before).limit(page_size)?.await,
+            None => request.limit(page_size)?.await,
+        };
+        let page = match page {
+            Ok(response) => response.models().await?,
+            Err(e) => {
+                metrics::observe_http_error(&e, "channel_messages");
+                return Err(e.into());
+            }
+        };
+        let done = page.len() < usize::from(page_size);
+        messages.extend(page);
+        if done {
+            break;
+        }
+    }
+
+    // Oldest first, so the newest message ends up at the top of the pins
+    let targets = messages
+        .iter()
+        .rev()
+        .filter(|message| {
+            message
+                .reactions
+                .iter()
+                .any(|it| reaction::emoji_matches(&emoji, &it.emoji) && it.count >= min_count)
+        })
+        .map(|message| Some(message.id))
+        .collect::<Vec<_>>();
+
+    if targets.is_empty() {
+        client
+            .create_followup(&event.token)
+            .flags(ctx.config.response_visibility.flags())
+            .content(&format!(
+                "None of the last {} have enough reactions.",
+                confirmation::count(
+                    messages.len(),
+                    "message",
+                    "messages",
+                    locale(&ctx.config, event)
+                )
+            ))?
+            .await?;
+        return Ok(());
+    }
+    pin_all(ctx, event, guild_id, channel_id, targets).await
+}
+
+/// Why the user can't pin the linked messages here, if they can't
+fn bulk_rejection(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    links: &[&str],
+) -> Option<String> {
+    gate::rejection(ctx, event, guild_id, channel_id, true).or_else(|| {
+        (links.is_empty() || links.len() > MAX_LINKS)
+            .then(|| format!("Give me between 1 and {MAX_LINKS} message links."))
+    })
+}
+
+/// Pin the messages in order and follow up with a summary, `None` marks an invalid target
+async fn pin_all(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    targets: Vec<Option<Id<MessageMarker>>>,
+) -> Result<()> {
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+
+    let client = ctx.http.interaction(event.application_id);
+    let mut pinned: Vec<Id<MessageMarker>> = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins.iter().map(|message| message.id).collect(),
+        Err(e) => {
+            // The response is deferred already, so the user would be left waiting
+            log::error!("[{}] Failed to load the pins: {}", channel_id, e);
+            client
+                .create_followup(&event.token)
+                .flags(ctx.config.response_visibility.flags())
+                .content(&ctx.config.pin_error_message(error::error_code(&e)))?
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{username} bulk pinned messages in {channel_name}");
+    let mut failures = Vec::new();
+    let mut requested = false;
+
+    for target in &targets {
+        let Some(message_id) = *target else {
+            failures.push(Failure::InvalidLink);
+            continue;
+        };
+        if pinned.contains(&message_id) {
+            failures.push(Failure::AlreadyPinned);
+            continue;
+        }
+        if pinned.len() >= MAX_PINS {
+            failures.push(Failure::LimitReached);
+            continue;
+        }
+
+        if requested {
+            tokio::time::sleep(PIN_SPACING).await;
+        }
+        requested = true;
+
+        match do_pin(&ctx.http, channel_id, message_id, true, &reason).await {
+            Ok(()) => {
+                pinned.push(message_id);
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(true, guild_id, channel_id, message_id, user.id),
+                )
+                .await;
+                // Pinning it by hand overrides any previous expiry
+                if ctx.store.remove_timed_unpin(message_id).await? {
+                    ctx.scheduler.wake();
+                }
+            }
+            Err(e) if e.code() == Some(error::MAX_PINS) => {
+                failures.push(Failure::LimitReached);
+            }
+            Err(e) => {
+                metrics::observe_pin_error(&e, "create_pin");
+                log::error!("Failed to process pin due to error: {}", e);
+                failures.push(Failure::Error);
+            }
+        }
+    }
+
+    let mut content = format!(
+        "\u{1F4CC} **{username}** pinned {} of {}.",
+        targets.len() - failures.len(),
+        confirmation::count(
+            targets.len(),
+            "message",
+            "messages",
+            locale(&ctx.config, event)
+        )
+    );
+    for failure in [
+        Failure::InvalidLink,
+        Failure::AlreadyPinned,
+        Failure::LimitReached,
+        Failure::Error,
+    ] {
+        let count = failures.iter().filter(|it| **it == failure).count();
+        if count > 0 {
+            content.push_str(&format!("\n{count} failed: {}", failure.describe()));
+        }
+    }
+
+    log::info!("[{}] {}", channel_id, content);
+    client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags())
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{command, context, created_at},
+        time,
+    };
+    use twilight_model::guild::Permissions;
+
+    #[tokio::test]
+    async fn new_accounts_cant_bulk_pin() {
+        let ctx = context(serde_json::json!({ "token": "", "min_account_age_days": 7 })).await;
+        let all = Permissions::VIEW_CHANNEL | Permissions::MANAGE_MESSAGES;
+        let links = ["https://discord.com/channels/4/5/6"];
+
+        let event = command(created_at(time::now() - 60 * 60), all);
+        let content = bulk_rejection(&ctx, &event, Id::new(4), Id::new(5), &links).unwrap();
+        assert!(content.contains("too new"));
+
+        let event = command(created_at(time::now() - 30 * 24 * 60 * 60), all);
+        assert_eq!(
+            bulk_rejection(&ctx, &event, Id::new(4), Id::new(5), &links),
+            None
+        );
+        assert!(bulk_rejection(&ctx, &event, Id::new(4), Id::new(5), &[]).is_some());
+    }
+}
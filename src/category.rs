@@ -0,0 +1,153 @@
+use anyhow::{bail, Result};
+use twilight_model::{
+    application::interaction::{message_component::MessageComponentInteractionData, Interaction},
+    channel::{
+        message::component::{ActionRow, SelectMenu, SelectMenuOption},
+        Message,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+};
+
+use crate::{ephemeral, label::can_manage_messages, Context};
+
+/// Prefix of the select menu custom id, followed by the message id
+pub const MENU_PREFIX: &str = "categorize-pin";
+/// Value of the menu option and `/pins` choice for pins without a category
+pub const UNCATEGORIZED: &str = "uncategorized";
+/// Select menus and command choices hold at most 25 entries, one of them is for uncategorized pins
+const MAX_CATEGORIES: usize = 24;
+/// Longest name Discord accepts for an option or a choice
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Check the configured categories once at startup
+pub fn validate(categories: &[String]) -> Result<()> {
+    if categories.len() > MAX_CATEGORIES {
+        bail!("pin_categories can have at most {MAX_CATEGORIES} categories");
+    }
+    for (index, name) in categories.iter().enumerate() {
+        if name.trim().is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            bail!("pin_categories[{index}] must have between 1 and {MAX_NAME_LENGTH} characters");
+        }
+        if name.eq_ignore_ascii_case(UNCATEGORIZED) {
+            bail!("pin_categories[{index}] can't be called {name:?}, that name is reserved");
+        }
+        if categories[..index]
+            .iter()
+            .any(|other| other.eq_ignore_ascii_case(name))
+        {
+            bail!("pin_categories has {name:?} more than once");
+        }
+    }
+    Ok(())
+}
+
+/// The configured category with this name, ignoring case
+pub fn find<'a>(ctx: &'a Context, name: &str) -> Option<&'a str> {
+    ctx.config
+        .pin_categories
+        .iter()
+        .find(|category| category.eq_ignore_ascii_case(name.trim()))
+        .map(String::as_str)
+}
+
+/// Handle "Categorize Pin", which offers the configured categories for a pinned message
+pub async fn prompt(ctx: &Context, event: &Interaction, message: &Message) -> Result<()> {
+    let response = if !can_manage_messages(event) {
+        ephemeral("You need the **Manage Messages** permission to do this.")
+    } else if !message.pinned {
+        ephemeral("This message isn't pinned.")
+    } else if ctx.config.pin_categories.is_empty() {
+        ephemeral("There are no categories configured.")
+    } else {
+        let current = ctx.store.category(message.id);
+        let mut options: Vec<SelectMenuOption> = ctx
+            .config
+            .pin_categories
+            .iter()
+            .map(|name| SelectMenuOption {
+                default: current.as_ref() == Some(name),
+                description: None,
+                emoji: None,
+                label: name.clone(),
+                value: name.clone(),
+            })
+            .collect();
+        options.push(SelectMenuOption {
+            default: false,
+            description: Some("Remove the category of the pin".to_owned()),
+            emoji: None,
+            label: "Uncategorized".to_owned(),
+            value: UNCATEGORIZED.to_owned(),
+        });
+
+        let menu = SelectMenu {
+            custom_id: format!("{MENU_PREFIX}:{}", message.id),
+            disabled: false,
+            max_values: Some(1),
+            min_values: Some(1),
+            options,
+            placeholder: Some("Pick a category".to_owned()),
+        };
+        let mut response = ephemeral("Which category does this pin belong to?");
+        if let Some(ref mut data) = response.data {
+            data.components = Some(row!(menu).into());
+        }
+        response
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Store the category once it is picked from the menu
+pub async fn select(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let (Some(message_id), Some(value)) = (
+        data.custom_id
+            .split(':')
+            .nth(1)
+            .and_then(|it| it.parse().ok()),
+        data.values.first(),
+    ) else {
+        return Ok(());
+    };
+
+    // The config could have changed since the menu was sent
+    let category = (value != UNCATEGORIZED).then(|| find(ctx, value));
+    let content = if !can_manage_messages(event) {
+        "You need the **Manage Messages** permission to do this.".to_owned()
+    } else if let Some(None) = category {
+        format!("The category **{value}** doesn't exist anymore.")
+    } else {
+        let category = category.flatten().map(ToOwned::to_owned);
+        let mut content = match category {
+            Some(ref name) => format!("Put the pin in **{name}**."),
+            None => "Removed the category of the pin.".to_owned(),
+        };
+        ctx.store.set_category(message_id, category).await?;
+        if !ctx.store.is_persistent() {
+            content.push_str("\nNo database is configured, so this resets when the bot restarts.");
+        }
+        content
+    };
+
+    let response = InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            content: Some(content),
+            components: Some(Vec::new()),
+            ..Default::default()
+        }),
+    };
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+        Interaction,
+    },
+    channel::{
+        message::{
+            component::{ActionRow, Button, ButtonStyle},
+            MessageFlags,
+        },
+        Message,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    approval, bulk, confirmation, do_pin, ephemeral, error, events::PinEvent, gate, locale, lock,
+    metrics, pinlog, pins::PinApi, time, Context, DEFER_EPHEMERAL,
+};
+
+/// Prefix of the confirmation button custom id, followed by the cutoff timestamp
+pub const BUTTON_PREFIX: &str = "unpin-older";
+
+const DEFERRED_UPDATE: InteractionResponse = InteractionResponse {
+    kind: InteractionResponseType::DeferredUpdateMessage,
+    data: None,
+};
+
+/// Handle `/unpin-older-than`, which asks for confirmation before unpinning the old pins
+pub async fn unpin_older_than(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let input = data
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value.as_str()),
+            _ => None,
+        })
+        .unwrap_or("");
+
+    let age = time::parse_duration(input);
+    let rejection = rejection(ctx, event, guild_id, channel_id).or_else(|| {
+        age.is_none()
+            .then(|| "I couldn't understand that age. Try something like `30d` or `1w`.".to_owned())
+    });
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let Some(age) = age else {
+        return Ok(());
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let cutoff = time::now().saturating_sub(age.as_secs());
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+    let targets = match old_pins(ctx, event, channel_id, cutoff).await {
+        Ok(targets) => targets,
+        Err(e) => {
+            log::error!("[{}] Failed to load the pins: {e}", channel_id);
+            let content = ctx.config.pin_error_message(error::error_code(&e));
+            request.content(&content)?.await?;
+            return Ok(());
+        }
+    };
+    if targets.is_empty() {
+        request
+            .content(&format!("No pins in this channel are older than {input}."))?
+            .await?;
+        return Ok(());
+    }
+
+    // The button carries the cutoff, so the pins are looked up again once it is clicked
+    let button = Button {
+        style: ButtonStyle::Danger,
+        url: None,
+        custom_id: Some(format!("{BUTTON_PREFIX}:{cutoff}")),
+        disabled: false,
+        label: Some(format!("Unpin {}", targets.len())),
+        emoji: None,
+    };
+    request
+        .components(&row!(button))?
+        .content(&format!(
+            "Unpin {} older than {input} from this channel?",
+            confirmation::count(
+                targets.len(),
+                "message",
+                "messages",
+                locale(&ctx.config, event)
+            )
+        ))?
+        .await?;
+    Ok(())
+}
+
+/// Unpin the old pins once the confirmation button is clicked
+pub async fn confirm(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let (Some(guild_id), Some(user), Some(channel_id), Some(cutoff)) = (
+        event.guild_id,
+        event.author(),
+        event.channel.as_ref().map(|channel| channel.id),
+        data.custom_id
+            .split(':')
+            .nth(1)
+            .and_then(|it| it.parse::<u64>().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    // Settings could have changed since the button was sent
+    if let Some(content) = rejection(ctx, event, guild_id, channel_id) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    // Unpinning takes a while, so the button is acknowledged first
+    client
+        .create_response(event.id, &event.token, &DEFERRED_UPDATE)
+        .await?;
+
+    let targets = match old_pins(ctx, event, channel_id, cutoff).await {
+        Ok(targets) => targets,
+        Err(e) => {
+            // The button would stay around without any sign that it failed
+            log::error!("[{}] Failed to load the pins: {e}", channel_id);
+            let content = ctx.config.pin_error_message(error::error_code(&e));
+            client
+                .update_response(&event.token)
+                .components(Some(&[]))?
+                .content(Some(&content))?
+                .await?;
+            return Ok(());
+        }
+    };
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{} unpinned old pins in {channel_name}", user.name);
+    let mut unpinned = 0;
+    for (index, message) in targets.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(bulk::PIN_SPACING).await;
+        }
+        match do_pin(&ctx.http, channel_id, message.id, false, &reason).await {
+            Ok(()) => {
+                unpinned += 1;
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(false, guild_id, channel_id, message.id, user.id)
+                        .author(message.author.id),
+                )
+                .await;
+                if ctx.store.remove_timed_unpin(message.id).await? {
+                    ctx.scheduler.wake();
+                }
+            }
+            // Someone else was faster
+            Err(e) if e.code() == Some(error::UNKNOWN_MESSAGE) => {}
+            Err(e) => {
+                metrics::observe_pin_error(&e, "delete_pin");
+                log::error!("Failed to process pin due to error: {}", e);
+            }
+        }
+    }
+
+    let content = if targets.is_empty() {
+        "There are no old pins left to remove.".to_owned()
+    } else {
+        format!("Unpinned {unpinned} of {} old messages.", targets.len())
+    };
+    log::info!("[{}] {} {}", channel_id, user.name, content);
+    client
+        .update_response(&event.token)
+        .components(Some(&[]))?
+        .content(Some(&content))?
+        .await?;
+    Ok(())
+}
+
+/// Why the user can't unpin several messages here, if they can't
+fn rejection(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Option<String> {
+    gate::rejection(ctx, event, guild_id, channel_id, false).or_else(|| {
+        ctx.store
+            .guild(guild_id)
+            .unpin_approval
+            .then(|| approval::REQUIRED.to_owned())
+    })
+}
+
+/// The pins of the channel sent before the cutoff, leaving out those the user can't unpin
+async fn old_pins(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+    cutoff: u64,
+) -> Result<Vec<Message>> {
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            return Err(e);
+        }
+    };
+
+    Ok(pins
+        .into_iter()
+        .filter(|message| time::snowflake_timestamp(message.id.get()) < cutoff)
+        .filter(|message| !lock::prevents_unpin(ctx, event, message.id))
+        .collect())
+}
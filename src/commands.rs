@@ -0,0 +1,750 @@
+use anyhow::Result;
+use twilight_http::Client;
+use twilight_model::channel::ChannelType;
+use twilight_model::{
+    application::command::{Command, CommandOption, CommandType},
+    guild::Permissions,
+    id::{marker::ApplicationMarker, Id},
+};
+use twilight_util::builder::command::{
+    AttachmentBuilder, BooleanBuilder, ChannelBuilder, CommandBuilder, IntegerBuilder, RoleBuilder,
+    StringBuilder, UserBuilder,
+};
+
+use crate::{
+    category,
+    config::{CommandSurface, Config},
+};
+
+pub const PIN: &str = "Pin Message";
+pub const UNPIN: &str = "Unpin Message";
+pub const TEMP_PIN: &str = "Pin (Temporary)";
+pub const WHO_PINNED: &str = "Who Pinned";
+pub const SHOW_PIN: &str = "Show Pin";
+pub const LOCK_PIN: &str = "Lock Pin";
+pub const UNLOCK_PIN: &str = "Unlock Pin";
+pub const LABEL_PIN: &str = "Label Pin";
+pub const CATEGORIZE_PIN: &str = "Categorize Pin";
+pub const PREVIEW_PIN: &str = "Preview Pin";
+pub const BOOKMARK: &str = "Bookmark";
+pub const SLASH_PIN: &str = "pin";
+pub const SLASH_UNPIN: &str = "unpin";
+pub const SET_ROLE: &str = "pin-set-role";
+pub const UNPIN_APPROVAL: &str = "pin-unpin-approval";
+pub const COPY_PIN: &str = "pin-copy";
+pub const PIN_TRANSFER: &str = "pin-transfer";
+pub const HELP: &str = "pin-help";
+pub const BULK_PIN: &str = "bulk-pin";
+pub const PIN_SELECT: &str = "pin-select";
+pub const PIN_REACTED: &str = "pin-reacted";
+pub const SCHEDULE_PIN: &str = "schedule-pin";
+pub const RANDOM_PIN: &str = "pin-random";
+pub const OLDEST_PIN: &str = "oldest-pin";
+pub const UNPIN_OLDER_THAN: &str = "unpin-older-than";
+pub const PIN_LOG: &str = "pin-log";
+pub const LIST_PINS: &str = "pins";
+pub const PIN_COUNT: &str = "pin-count";
+pub const PIN_FREEZE: &str = "pin-freeze";
+pub const PIN_UNFREEZE: &str = "pin-unfreeze";
+pub const MY_PINS: &str = "my-pins";
+pub const PIN_LOG_PURGE: &str = "pin-log-purge";
+pub const PIN_TRENDS: &str = "pin-trends";
+pub const PIN_SNAPSHOT: &str = "pin-snapshot";
+pub const PIN_DIFF: &str = "pin-diff";
+pub const SELF_TEST: &str = "selftest";
+pub const EFFECTIVE_CONFIG: &str = "pin-effective-config";
+pub const GRANT_PIN: &str = "grant-pin";
+pub const REVOKE_PIN: &str = "revoke-pin";
+pub const EXPORT_PINS: &str = "export-pins";
+pub const IMPORT_PINS: &str = "import-pins";
+
+/// Channels which can hold pinned messages, voice and stage channels have a text chat too
+const MESSAGE_CHANNEL_TYPES: [ChannelType; 6] = [
+    ChannelType::GuildText,
+    ChannelType::GuildAnnouncement,
+    ChannelType::GuildVoice,
+    ChannelType::GuildStageVoice,
+    ChannelType::PublicThread,
+    ChannelType::PrivateThread,
+];
+
+/// The canonical set of commands this bot handles
+pub fn definitions(config: &Config) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if config.has_surface(CommandSurface::MessageContext) {
+        commands.extend(message_commands(config));
+    }
+
+    if config.has_surface(CommandSurface::Slash) {
+        let slash = [
+            (
+                SLASH_PIN,
+                "Pin a message of this channel",
+                config.enable_pin,
+            ),
+            (
+                SLASH_UNPIN,
+                "Unpin a message of this channel",
+                config.enable_unpin,
+            ),
+        ];
+        for (name, description, enabled) in slash {
+            if !enabled {
+                continue;
+            }
+            commands.push(
+                CommandBuilder::new(name, description, CommandType::ChatInput)
+                    .dm_permission(false)
+                    .option(StringBuilder::new("message", "Link to the message").required(true))
+                    .build(),
+            );
+        }
+    }
+
+    commands.push(
+        CommandBuilder::new(
+            SET_ROLE,
+            "Set the role required to pin messages in this server",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(RoleBuilder::new(
+            "role",
+            "The required role, leave empty to allow everyone",
+        ))
+        .option(
+            StringBuilder::new("for", "What the role is needed for, both by default").choices([
+                ("Pinning and unpinning", "both"),
+                ("Only pinning", "pin"),
+                ("Only unpinning", "unpin"),
+            ]),
+        )
+        .build(),
+    );
+
+    if config.enable_pin {
+        commands.push(
+            CommandBuilder::new(
+                GRANT_PIN,
+                "Let a member pin without the required role for a while",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .option(UserBuilder::new("user", "The member who can pin").required(true))
+            .option(StringBuilder::new("duration", "Like 30m, 2h or 1d").required(true))
+            .build(),
+        );
+
+        commands.push(
+            CommandBuilder::new(
+                REVOKE_PIN,
+                "End the pin grant of a member early",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .option(UserBuilder::new("user", "The member with the grant").required(true))
+            .build(),
+        );
+    }
+
+    if config.enable_unpin {
+        commands.push(
+            CommandBuilder::new(
+                UNPIN_APPROVAL,
+                "Require a second moderator to approve every unpin in this server",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .option(
+                BooleanBuilder::new("enabled", "Whether unpins need an approval").required(true),
+            )
+            .build(),
+        );
+    }
+
+    if config.enable_pin {
+        commands.push(
+            CommandBuilder::new(
+                COPY_PIN,
+                "Copy a pinned message of this channel to another channel",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .option(StringBuilder::new("message", "Link to the pinned message").required(true))
+            .option(
+                ChannelBuilder::new("channel", "The channel to copy the message to")
+                    .channel_types(MESSAGE_CHANNEL_TYPES)
+                    .required(true),
+            )
+            .option(BooleanBuilder::new(
+                "pin",
+                "Pin the copy in the other channel, enabled by default",
+            ))
+            .option(BooleanBuilder::new(
+                "move",
+                "Unpin the original message afterwards",
+            ))
+            .build(),
+        );
+    }
+
+    if config.enable_pin {
+        commands.push(
+            CommandBuilder::new(
+                PIN_TRANSFER,
+                "Repost and pin every pinned message of this channel in another channel",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .option(
+                ChannelBuilder::new("channel", "The channel to transfer the pins to")
+                    .channel_types(MESSAGE_CHANNEL_TYPES)
+                    .required(true),
+            )
+            .build(),
+        );
+    }
+
+    if config.enable_pin {
+        commands.push(
+            CommandBuilder::new(
+                PIN_SELECT,
+                "Pick one of the recent messages of this channel to pin",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .build(),
+        );
+        commands.push(
+            CommandBuilder::new(
+                BULK_PIN,
+                "Pin several messages of this channel at once",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .option(
+                StringBuilder::new("messages", "Links to the messages, separated by spaces")
+                    .required(true),
+            )
+            .build(),
+        );
+        commands.push(
+            CommandBuilder::new(
+                SCHEDULE_PIN,
+                "Pin a message of this channel at a later time",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .option(StringBuilder::new("message", "Link to the message").required(true))
+            .option(
+                StringBuilder::new(
+                    "time",
+                    "When to pin it, like 2h, 1d 12h or 2024-05-01 18:30 (UTC)",
+                )
+                .required(true),
+            )
+            .build(),
+        );
+        commands.push(
+            CommandBuilder::new(
+                PIN_REACTED,
+                "Pin the recent messages of this channel with a reaction",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .option(StringBuilder::new("emoji", "The reaction to look for").required(true))
+            .option(
+                IntegerBuilder::new("count", "How many reactions a message needs, 1 by default")
+                    .min_value(1),
+            )
+            .option(
+                IntegerBuilder::new(
+                    "messages",
+                    "How many recent messages to look at, 100 by default",
+                )
+                .min_value(1)
+                .max_value(500),
+            )
+            .build(),
+        );
+    }
+
+    commands.push(
+        CommandBuilder::new(
+            RANDOM_PIN,
+            "Show a random pinned message of this channel",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .option(BooleanBuilder::new(
+            "public",
+            "Show the message to everyone in the channel",
+        ))
+        .build(),
+    );
+
+    let mut list_pins = CommandBuilder::new(
+        LIST_PINS,
+        "List the pinned messages of this channel",
+        CommandType::ChatInput,
+    )
+    .dm_permission(false);
+    if !config.pin_categories.is_empty() {
+        let choices = config
+            .pin_categories
+            .iter()
+            .map(|name| (name.clone(), name.clone()))
+            .chain([(
+                "Uncategorized".to_owned(),
+                category::UNCATEGORIZED.to_owned(),
+            )]);
+        list_pins = list_pins.option(
+            StringBuilder::new("category", "Only list the pins in this category").choices(choices),
+        );
+    }
+    commands.push(list_pins.build());
+
+    if config.enable_unpin {
+        commands.push(
+            CommandBuilder::new(
+                UNPIN_OLDER_THAN,
+                "Unpin every message of this channel older than the given age",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .option(StringBuilder::new("age", "Like 30d or 1w").required(true))
+            .build(),
+        );
+    }
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_COUNT,
+            "Show how many messages are pinned in this channel",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_SNAPSHOT,
+            "Save the current pins of this channel, to compare them later",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .option(
+            StringBuilder::new(
+                "name",
+                "Name of the snapshot, replaces an older one with it",
+            )
+            .max_length(32),
+        )
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_DIFF,
+            "Show which pins of this channel were added or removed since a snapshot",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .option(StringBuilder::new("name", "Name of the snapshot").max_length(32))
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            EXPORT_PINS,
+            "Save the pins of this channel to a file, which /import-pins can restore",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .build(),
+    );
+
+    if config.enable_pin {
+        commands.push(
+            CommandBuilder::new(
+                IMPORT_PINS,
+                "Restore the pins of a file made by /export-pins in this channel",
+                CommandType::ChatInput,
+            )
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .option(AttachmentBuilder::new("file", "The exported pins").required(true))
+            .build(),
+        );
+    }
+
+    commands.push(
+        CommandBuilder::new(
+            MY_PINS,
+            "List your pinned messages in this server",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            OLDEST_PIN,
+            "Show the oldest pinned message of this channel, to make room for new pins",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_FREEZE,
+            "Stop every pin and unpin in this channel for a while",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .option(StringBuilder::new("duration", "Like 30m, 2h or 1d").required(true))
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_UNFREEZE,
+            "Allow pins in this channel again before the freeze ends",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_MESSAGES)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_LOG,
+            "Show the recent pins and unpins in this server",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_LOG_PURGE,
+            "Remove old entries from the pin log of this server",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(
+            IntegerBuilder::new(
+                "days",
+                "Remove entries older than this, the configured retention by default",
+            )
+            .min_value(1),
+        )
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            PIN_TRENDS,
+            "Chart the pins and unpins in this server over time",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(
+            StringBuilder::new("per", "Count per day or per week, per day by default").choices([
+                ("Day, the last 7 days", "day"),
+                ("Week, the last 8 weeks", "week"),
+            ]),
+        )
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            SELF_TEST,
+            "Check that I can pin messages in this channel",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            EFFECTIVE_CONFIG,
+            "Show the pin settings that apply in this channel",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .build(),
+    );
+
+    commands.push(
+        CommandBuilder::new(
+            HELP,
+            "List the commands of this bot and what they need",
+            CommandType::ChatInput,
+        )
+        .dm_permission(false)
+        .build(),
+    );
+
+    commands
+}
+
+/// The commands found under **Apps** when right-clicking a message
+fn message_commands(config: &Config) -> Vec<Command> {
+    let mut commands: Vec<Command> = [
+        (PIN, config.enable_pin),
+        (TEMP_PIN, config.enable_pin),
+        (UNPIN, config.enable_unpin),
+        (WHO_PINNED, true),
+        (SHOW_PIN, true),
+        (BOOKMARK, true),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| {
+        CommandBuilder::new(name, "", CommandType::Message)
+            .dm_permission(false)
+            .build()
+    })
+    .collect();
+
+    // Moderators only, server admins can still change who sees them
+    let preview = config.enable_pin.then_some(PREVIEW_PIN);
+    for name in [LOCK_PIN, UNLOCK_PIN].into_iter().chain(preview) {
+        commands.push(
+            CommandBuilder::new(name, "", CommandType::Message)
+                .dm_permission(false)
+                .default_member_permissions(Permissions::MANAGE_GUILD)
+                .build(),
+        );
+    }
+
+    commands.push(
+        CommandBuilder::new(LABEL_PIN, "", CommandType::Message)
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_MESSAGES)
+            .build(),
+    );
+
+    if !config.pin_categories.is_empty() {
+        commands.push(
+            CommandBuilder::new(CATEGORIZE_PIN, "", CommandType::Message)
+                .dm_permission(false)
+                .default_member_permissions(Permissions::MANAGE_MESSAGES)
+                .build(),
+        );
+    }
+
+    commands
+}
+
+/// Overwrite the global commands of the application with our definitions
+pub async fn register_commands(
+    http: &Client,
+    application_id: Id<ApplicationMarker>,
+    config: &Config,
+) -> Result<Vec<Command>> {
+    let commands = http
+        .interaction(application_id)
+        .set_global_commands(&definitions(config))
+        .await?
+        .models()
+        .await?;
+    Ok(commands)
+}
+
+/// How the registered global commands differ from the definitions, by command
+#[derive(Default)]
+pub struct Drift {
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the registered global commands with the definitions, and overwrite them on any drift
+pub async fn sync_commands(
+    http: &Client,
+    application_id: Id<ApplicationMarker>,
+    config: &Config,
+) -> Result<Drift> {
+    let registered = http
+        .interaction(application_id)
+        .global_commands()
+        .await?
+        .models()
+        .await?;
+    let drift = drift(&registered, &definitions(config));
+    if !drift.is_empty() {
+        register_commands(http, application_id, config).await?;
+    }
+    Ok(drift)
+}
+
+fn drift(registered: &[Command], expected: &[Command]) -> Drift {
+    let find = |commands: &[Command], command: &Command| {
+        commands
+            .iter()
+            .find(|it| it.kind == command.kind && it.name == command.name)
+            .map(normalized)
+    };
+
+    let mut drift = Drift::default();
+    for command in expected {
+        match find(registered, command) {
+            None => drift.missing.push(label(command)),
+            Some(current) if current != normalized(command) => {
+                drift.changed.push(label(command));
+            }
+            Some(_) => {}
+        }
+    }
+    drift.extra = registered
+        .iter()
+        .filter(|command| find(expected, command).is_none())
+        .map(label)
+        .collect();
+    drift
+}
+
+fn label(command: &Command) -> String {
+    match command.kind {
+        CommandType::ChatInput => format!("/{}", command.name),
+        _ => command.name.clone(),
+    }
+}
+
+/// The command without the fields Discord assigns, and with the defaults Discord fills in
+fn normalized(command: &Command) -> Command {
+    Command {
+        application_id: None,
+        guild_id: None,
+        id: None,
+        version: Id::new(1),
+        dm_permission: Some(command.dm_permission.unwrap_or(true)),
+        nsfw: Some(command.nsfw.unwrap_or(false)),
+        name_localizations: command
+            .name_localizations
+            .clone()
+            .filter(|it| !it.is_empty()),
+        description_localizations: command
+            .description_localizations
+            .clone()
+            .filter(|it| !it.is_empty()),
+        options: command.options.iter().map(normalized_option).collect(),
+        ..command.clone()
+    }
+}
+
+fn normalized_option(option: &CommandOption) -> CommandOption {
+    CommandOption {
+        autocomplete: option.autocomplete.filter(|it| *it),
+        required: option.required.filter(|it| *it),
+        name_localizations: option
+            .name_localizations
+            .clone()
+            .filter(|it| !it.is_empty()),
+        description_localizations: option
+            .description_localizations
+            .clone()
+            .filter(|it| !it.is_empty()),
+        options: option
+            .options
+            .as_ref()
+            .map(|options| options.iter().map(normalized_option).collect::<Vec<_>>())
+            .filter(|it| !it.is_empty()),
+        ..option.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slash(name: &str, description: &str) -> Command {
+        CommandBuilder::new(name, description, CommandType::ChatInput)
+            .option(StringBuilder::new("message", "Link to the message").required(true))
+            .build()
+    }
+
+    /// The command as Discord returns it, with its ids and the defaults filled in
+    fn registered(command: &Command) -> Command {
+        Command {
+            application_id: Some(Id::new(1)),
+            id: Some(Id::new(2)),
+            version: Id::new(3),
+            dm_permission: Some(command.dm_permission.unwrap_or(true)),
+            nsfw: Some(command.nsfw.unwrap_or(false)),
+            ..command.clone()
+        }
+    }
+
+    #[test]
+    fn registered_commands_are_in_sync() {
+        let expected = [
+            slash("pin", "Pin a message"),
+            slash("unpin", "Unpin a message"),
+        ];
+        let current: Vec<_> = expected.iter().map(registered).collect();
+        assert!(drift(&current, &expected).is_empty());
+    }
+
+    #[test]
+    fn reports_every_kind_of_drift() {
+        let expected = [
+            slash("pin", "Pin a message"),
+            slash("unpin", "Unpin a message"),
+        ];
+        let current = [
+            registered(&slash("pin", "Pin something")),
+            registered(&slash("old", "Removed command")),
+            registered(&CommandBuilder::new("unpin", "", CommandType::Message).build()),
+        ];
+
+        let drift = drift(&current, &expected);
+        assert_eq!(drift.missing, ["/unpin"]);
+        assert_eq!(drift.extra, ["/old", "unpin"]);
+        assert_eq!(drift.changed, ["/pin"]);
+    }
+
+    #[test]
+    fn every_defined_command_is_in_sync_with_itself() {
+        let config: Config = serde_json::from_str(r#"{"token": "x"}"#).unwrap();
+        let commands = definitions(&config);
+        assert!(!commands.is_empty());
+        let current: Vec<_> = commands.iter().map(registered).collect();
+        let drift = drift(&current, &commands);
+        assert!(drift.is_empty(), "{:?}", drift.changed);
+    }
+}
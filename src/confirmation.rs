@@ -0,0 +1,296 @@
+use twilight_model::{
+    channel::message::{AllowedMentions, Embed, Message},
+    id::{marker::UserMarker, Id},
+    user::User,
+};
+use twilight_util::builder::embed::{EmbedAuthorBuilder, EmbedBuilder, ImageSource};
+
+use crate::config::Config;
+
+const DEFAULT_TEMPLATE: &str = "\u{1F4CC} **{user}** {action} {message} in this channel.";
+
+/// Colors of the embed confirmation, blurple for pins and grey for unpins
+const PIN_COLOR: u32 = 0x5865F2;
+const UNPIN_COLOR: u32 = 0x99AAB5;
+/// Longest excerpt of the message shown in the embed confirmation
+const EXCERPT_LENGTH: usize = 300;
+
+/// Confirmations mention the author of the message, which should not ping them
+pub const NO_PINGS: AllowedMentions = AllowedMentions {
+    parse: Vec::new(),
+    replied_user: false,
+    roles: Vec::new(),
+    users: Vec::new(),
+};
+
+/// Build the text shown after a message was pinned or unpinned, between the configured prefix
+/// and suffix.
+///
+/// The template supports these placeholders:
+/// - `{user}` name of the user who pinned the message
+/// - `{author}` mention of the author of the pinned message
+/// - `{action}` either "pinned" or "unpinned"
+/// - `{message}` "a message by {author}", or "their own message" for self-pins
+pub fn content(config: &Config, user: &User, author_id: Id<UserMarker>, pin: bool) -> String {
+    let template = config
+        .confirmation_template
+        .as_deref()
+        .unwrap_or(DEFAULT_TEMPLATE);
+
+    let content = render(template, |key| match key {
+        "user" => Some(user.name.clone()),
+        "author" => Some(format!("<@{author_id}>")),
+        "action" => Some(if pin { "pinned" } else { "unpinned" }.to_owned()),
+        "message" if author_id == user.id => Some("their own message".to_owned()),
+        "message" => Some(format!("a message by <@{author_id}>")),
+        _ => None,
+    });
+
+    format!(
+        "{}{content}{}",
+        config.confirmation_prefix, config.confirmation_suffix
+    )
+}
+
+/// Text shown when a message was pinned or unpinned through its reactions
+pub fn community(config: &Config, author_id: Id<UserMarker>, pin: bool) -> String {
+    let action = if pin { "pinned" } else { "unpinned" };
+    format!(
+        "{}\u{1F4CC} The community {action} a message by <@{author_id}> in this channel.{}",
+        config.confirmation_prefix, config.confirmation_suffix
+    )
+}
+
+/// Text which replaces the system message of a pin, see [`SystemMessageHandling::Replace`]
+///
+/// [`SystemMessageHandling::Replace`]: crate::config::SystemMessageHandling::Replace
+pub fn notice(config: &Config) -> String {
+    format!(
+        "{}\u{1F4CC} A message was pinned in this channel.{}",
+        config.confirmation_prefix, config.confirmation_suffix
+    )
+}
+
+/// Text shown when the confirmation of an existing pin is requested again, with the pinner if
+/// the pin log knows them
+pub fn reminder(
+    config: &Config,
+    author_id: Id<UserMarker>,
+    pinner_id: Option<Id<UserMarker>>,
+) -> String {
+    let pinned_by = pinner_id.map_or_else(String::new, |id| format!(" It was pinned by <@{id}>."));
+    format!(
+        "{}\u{1F4CC} A message by <@{author_id}> is pinned in this channel.{pinned_by}{}",
+        config.confirmation_prefix, config.confirmation_suffix
+    )
+}
+
+/// Where the message ended up among the pins of the channel, counting from the newest
+pub fn position(index: usize, total: usize) -> String {
+    format!("Pin #{index} of {total}")
+}
+
+/// Build the embed confirmation, with the title in the language of the user
+pub fn embed(user: &User, message: &Message, pin: bool, locale: &str) -> Embed {
+    let mut author = EmbedAuthorBuilder::new(user.name.clone());
+    if let Some(hash) = user.avatar {
+        let url = format!("https://cdn.discordapp.com/avatars/{}/{hash}.png", user.id);
+        if let Ok(icon) = ImageSource::url(url) {
+            author = author.icon_url(icon);
+        }
+    }
+
+    let mut description = format!("Message by <@{}>", message.author.id);
+    push_excerpt(&mut description, message);
+
+    EmbedBuilder::new()
+        .title(title(locale, pin))
+        .color(if pin { PIN_COLOR } else { UNPIN_COLOR })
+        .author(author)
+        .description(description)
+        .build()
+}
+
+/// Build the embed of [`reminder`], which has no author since it's not known who pinned it
+pub fn reminder_embed(message: &Message, pinner_id: Option<Id<UserMarker>>, locale: &str) -> Embed {
+    let mut description = format!("Message by <@{}>", message.author.id);
+    if let Some(pinner_id) = pinner_id {
+        description.push_str(&format!(", pinned by <@{pinner_id}>"));
+    }
+    push_excerpt(&mut description, message);
+
+    EmbedBuilder::new()
+        .title(title(locale, true))
+        .color(PIN_COLOR)
+        .description(description)
+        .build()
+}
+
+/// Add the start of the message content to the embed description, if it has any
+fn push_excerpt(description: &mut String, message: &Message) {
+    if !message.content.is_empty() {
+        let mut excerpt: String = message.content.chars().take(EXCERPT_LENGTH).collect();
+        if excerpt.len() < message.content.len() {
+            excerpt.push('\u{2026}');
+        }
+        description.push_str("\n\n");
+        description.push_str(&excerpt);
+    }
+}
+
+/// Languages with a translation of the embed confirmation
+pub const LANGUAGES: [&str; 6] = ["en", "de", "es", "fr", "nl", "pt"];
+
+/// Whether there is a translation for the locale, like `de` or `pt-BR`
+pub fn is_translated(locale: &str) -> bool {
+    LANGUAGES.contains(&language(locale))
+}
+
+fn language(locale: &str) -> &str {
+    locale.split('-').next().unwrap_or(locale)
+}
+
+/// The number with the thousands separator of the locale, like `1,234` or `1.234`.
+///
+/// Dates don't need this, `<t:timestamp:R>` is formatted by the client of each user.
+pub fn number(value: usize, locale: &str) -> String {
+    let separator = match language(locale) {
+        "de" | "es" | "nl" | "pt" => '.',
+        "fr" => '\u{202F}',
+        _ => ',',
+    };
+    let digits = value.to_string();
+    let mut output = String::with_capacity(digits.len() * 2);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            output.push(separator);
+        }
+        output.push(digit);
+    }
+    output
+}
+
+/// Plural categories of the CLDR rules, only the ones of the supported languages
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Plural {
+    One,
+    Many,
+    Other,
+}
+
+/// The plural category of a count in the language of the locale, English for the rest.
+///
+/// French and Brazilian Portuguese treat zero like one, and the Romance languages use a separate
+/// form for whole millions, like `1 000 000 de messages`.
+pub fn plural(value: usize, locale: &str) -> Plural {
+    match language(locale) {
+        "fr" | "pt" if value <= 1 => Plural::One,
+        "es" | "fr" | "pt" if value != 0 && value.is_multiple_of(1_000_000) => Plural::Many,
+        _ if value == 1 => Plural::One,
+        _ => Plural::Other,
+    }
+}
+
+/// The number with the English noun that agrees with it, like `1 pin` or `1,234 pins`.
+///
+/// The text around it is English, so the English rules apply whatever the locale, which only
+/// picks the thousands separator.
+pub fn count(value: usize, one: &str, other: &str, locale: &str) -> String {
+    let noun = match plural(value, "en") {
+        Plural::One => one,
+        Plural::Many | Plural::Other => other,
+    };
+    format!("{} {noun}", number(value, locale))
+}
+
+/// The answer of `/pin-count`, in the language of the user
+pub fn pin_count(pins: usize, max: usize, locale: &str) -> String {
+    let category = plural(pins, locale);
+    let (pins, max) = (number(pins, locale), number(max, locale));
+    match (language(locale), category) {
+        ("de", Plural::One) => {
+            format!("Dieser Kanal hat {pins} angeheftete Nachricht von {max} erlaubten.")
+        }
+        ("de", _) => {
+            format!("Dieser Kanal hat {pins} angeheftete Nachrichten von {max} erlaubten.")
+        }
+        ("es", Plural::One) => {
+            format!("Este canal tiene {pins} mensaje fijado de {max} permitidos.")
+        }
+        ("es", Plural::Many) => {
+            format!("Este canal tiene {pins} de mensajes fijados de {max} permitidos.")
+        }
+        ("es", Plural::Other) => {
+            format!("Este canal tiene {pins} mensajes fijados de {max} permitidos.")
+        }
+        ("fr", Plural::One) => format!("Ce salon a {pins} message épinglé sur {max} autorisés."),
+        ("fr", Plural::Many) => {
+            format!("Ce salon a {pins} de messages épinglés sur {max} autorisés.")
+        }
+        ("fr", Plural::Other) => {
+            format!("Ce salon a {pins} messages épinglés sur {max} autorisés.")
+        }
+        ("nl", Plural::One) => {
+            format!("Dit kanaal heeft {pins} vastgezet bericht van de {max} toegestane.")
+        }
+        ("nl", _) => {
+            format!("Dit kanaal heeft {pins} vastgezette berichten van de {max} toegestane.")
+        }
+        ("pt", Plural::One) => {
+            format!("Este canal tem {pins} mensagem fixada de {max} permitidas.")
+        }
+        ("pt", Plural::Many) => {
+            format!("Este canal tem {pins} de mensagens fixadas de {max} permitidas.")
+        }
+        ("pt", Plural::Other) => {
+            format!("Este canal tem {pins} mensagens fixadas de {max} permitidas.")
+        }
+        (_, Plural::One) => format!("This channel has {pins} pin, out of {max} allowed."),
+        (_, _) => format!("This channel has {pins} pins, out of {max} allowed."),
+    }
+}
+
+/// The embed title, in English for every locale without a translation
+fn title(locale: &str, pin: bool) -> &'static str {
+    match (language(locale), pin) {
+        ("de", true) => "Nachricht angeheftet",
+        ("de", false) => "Nachricht losgelöst",
+        ("es", true) => "Mensaje fijado",
+        ("es", false) => "Mensaje desfijado",
+        ("fr", true) => "Message épinglé",
+        ("fr", false) => "Message désépinglé",
+        ("nl", true) => "Bericht vastgezet",
+        ("nl", false) => "Bericht losgemaakt",
+        ("pt", true) => "Mensagem fixada",
+        ("pt", false) => "Mensagem desafixada",
+        (_, true) => "Message pinned",
+        (_, false) => "Message unpinned",
+    }
+}
+
+/// Replace every `{key}` in the template, unknown keys are kept as they are
+pub fn render(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some((before, after)) = rest.split_once('{') {
+        output.push_str(before);
+        let replaced = after
+            .split_once('}')
+            .and_then(|(key, tail)| Some((value(key)?, tail)));
+
+        match replaced {
+            Some((text, tail)) => {
+                output.push_str(&text);
+                rest = tail;
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
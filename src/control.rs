@@ -0,0 +1,693 @@
+//! Optional gRPC API for external tooling to manage pins without going through Discord.
+//!
+//! Needs the `control` feature, and is served on `control_address` next to the gateway. The
+//! service is defined in `proto/control.proto`:
+//!
+//! - `ListPins`: the pinned messages of a channel, newest first
+//! - `Pin` and `Unpin`: change the pin of a message
+//! - `GetStats`: counters of the bot since it started
+//!
+//! Every call has to carry the metadata `authorization: Bearer <control_token>`. The server runs
+//! on the HTTP/2 of hyper and encodes the few messages itself instead of using tonic and prost,
+//! so any client generated from the proto file can call it. It only supports unary calls
+//! without compression, which is all the service has.
+//!
+//! Pins and unpins go through the same checks as automatic pins: the guild has to be allowed
+//! and the channel must not be frozen. They are recorded with the bot as the one who pinned.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use tracing as log;
+
+use crate::Context;
+
+/// Start serving the control API if an address is configured
+pub fn spawn(ctx: Arc<Context>) -> Result<()> {
+    let Some(address) = ctx.config.control_address else {
+        return Ok(());
+    };
+
+    #[cfg(feature = "control")]
+    {
+        let (address, server) = server::bind(ctx, address)?;
+        log::info!("Serving the control API on {address}");
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                log::error!("Control API stopped: {e}");
+            }
+        });
+    }
+
+    #[cfg(not(feature = "control"))]
+    {
+        drop(ctx);
+        log::warn!(
+            "Ignoring control_address {address}, the bot was built without the control feature"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "control")]
+mod server {
+    use std::{
+        convert::Infallible,
+        future::Future,
+        net::SocketAddr,
+        sync::{atomic::Ordering::Relaxed, Arc},
+    };
+
+    use anyhow::Result;
+    use hyper::{
+        header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+        service::{make_service_fn, service_fn},
+        Body, Method, Request, Response, Server, StatusCode,
+    };
+    use percent_encoding::{AsciiSet, CONTROLS};
+    use tracing as log;
+    use twilight_model::id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker},
+        Id,
+    };
+
+    use super::grpc::{self, Code, Fields, Message};
+    use crate::{
+        do_pin,
+        error::{self, PinbotError},
+        events::PinEvent,
+        freeze, metrics, pinlog,
+        pins::PinApi,
+        Context,
+    };
+
+    /// Path prefix of the methods, the package and name of the service in the proto file
+    const SERVICE: &str = "/pinbot.control.v1.Control/";
+    const GRPC_STATUS: HeaderName = HeaderName::from_static("grpc-status");
+    const GRPC_MESSAGE: HeaderName = HeaderName::from_static("grpc-message");
+    /// Characters which the status message has to escape, besides everything outside of ASCII
+    const MESSAGE_ESCAPES: &AsciiSet = &CONTROLS.add(b'%');
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub(super) enum Call {
+        ListPins(Id<ChannelMarker>),
+        Pin(Id<ChannelMarker>, Id<MessageMarker>),
+        Unpin(Id<ChannelMarker>, Id<MessageMarker>),
+        GetStats,
+    }
+
+    type Failure = (Code, String);
+
+    /// Which call the request is for, from the path and the fields of the request message
+    pub(super) fn call(path: &str, message: &[u8]) -> Result<Call, Failure> {
+        let Some(method) = path.strip_prefix(SERVICE) else {
+            return Err((Code::Unimplemented, format!("Unknown service of {path}")));
+        };
+        let Some(fields) = grpc::decode(message) else {
+            return Err((
+                Code::InvalidArgument,
+                "Malformed request message".to_owned(),
+            ));
+        };
+        match method {
+            "ListPins" => Ok(Call::ListPins(id(&fields, 1, "channel_id")?)),
+            "Pin" => Ok(Call::Pin(
+                id(&fields, 1, "channel_id")?,
+                id(&fields, 2, "message_id")?,
+            )),
+            "Unpin" => Ok(Call::Unpin(
+                id(&fields, 1, "channel_id")?,
+                id(&fields, 2, "message_id")?,
+            )),
+            "GetStats" => Ok(Call::GetStats),
+            _ => Err((Code::Unimplemented, format!("Unknown method {method}"))),
+        }
+    }
+
+    /// An id field of the request, which proto3 leaves out if it's 0
+    fn id<T>(fields: &Fields, number: u32, name: &str) -> Result<Id<T>, Failure> {
+        fields
+            .get(&number)
+            .copied()
+            .and_then(Id::new_checked)
+            .ok_or_else(|| (Code::InvalidArgument, format!("{name} is missing")))
+    }
+
+    /// Whether the authorization metadata carries the token, compared in constant time
+    pub(super) fn authorized(header: Option<&str>, token: &str) -> bool {
+        let Some(given) = header.and_then(|it| it.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        let (given, token) = (given.as_bytes(), token.as_bytes());
+        !token.is_empty()
+            && given.len() == token.len()
+            && given
+                .iter()
+                .zip(token)
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Serve the API over HTTP/2 without TLS, returning the address it's bound to
+    pub(super) fn bind(
+        ctx: Arc<Context>,
+        address: SocketAddr,
+    ) -> Result<(SocketAddr, impl Future<Output = hyper::Result<()>>)> {
+        let make_service = make_service_fn(move |_| {
+            let ctx = ctx.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let ctx = ctx.clone();
+                    async move { Ok::<_, Infallible>(handle(&ctx, request).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&address)?
+            .http2_only(true)
+            .serve(make_service);
+        Ok((server.local_addr(), server))
+    }
+
+    pub(super) async fn handle(ctx: &Context, request: Request<Body>) -> Response<Body> {
+        let grpc = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|it| it.to_str().ok())
+            .is_some_and(|it| it.starts_with("application/grpc"));
+        if request.method() != Method::POST || !grpc {
+            // Not a gRPC client, so it can't read a status in the trailers
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+            return response;
+        }
+
+        let token = ctx
+            .config
+            .control_token
+            .as_ref()
+            .map_or("", |token| token.expose());
+        let header = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|it| it.to_str().ok());
+        if !authorized(header, token) {
+            return failure(Code::Unauthenticated, "Missing or wrong token");
+        }
+
+        let (parts, body) = request.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return failure(Code::InvalidArgument, &e.to_string()),
+        };
+        let call = match grpc::unframe(&body).and_then(|message| call(parts.uri.path(), message)) {
+            Ok(call) => call,
+            Err((code, message)) => return failure(code, &message),
+        };
+        let result = match call {
+            Call::GetStats => Ok(stats(ctx)),
+            Call::ListPins(channel_id) => list_pins(ctx, channel_id).await,
+            Call::Pin(channel_id, message_id) => {
+                set_pinned(ctx, channel_id, message_id, true).await
+            }
+            Call::Unpin(channel_id, message_id) => {
+                set_pinned(ctx, channel_id, message_id, false).await
+            }
+        };
+        match result {
+            Ok(message) => respond(&message).await,
+            Err((code, message)) => failure(code, &message),
+        }
+    }
+
+    type Outcome = Result<Message, Failure>;
+
+    fn stats(ctx: &Context) -> Message {
+        let count = |len: usize| u64::try_from(len).unwrap_or(u64::MAX);
+        Message::default()
+            .uint64(1, metrics::PINS_TOTAL.load(Relaxed))
+            .uint64(2, metrics::RATELIMITED_TOTAL.load(Relaxed))
+            .optional_uint64(3, metrics::COMMAND_LATENCY.quantile(0.5))
+            .optional_uint64(4, metrics::COMMAND_LATENCY.quantile(0.99))
+            .uint64(5, count(ctx.store.timed_unpins().len()))
+            .uint64(6, count(ctx.store.scheduled_pins().len()))
+    }
+
+    async fn list_pins(ctx: &Context, channel_id: Id<ChannelMarker>) -> Outcome {
+        guild_of(ctx, channel_id).await?;
+        let pins = ctx.http.pinned_messages(channel_id).await.map_err(|e| {
+            log::warn!("[{}] Control API failed to load the pins: {e}", channel_id);
+            status_of(error::error_code(&e), &e.to_string())
+        })?;
+        let response = pins.iter().fold(Message::default(), |response, message| {
+            let pin = Message::default()
+                .uint64(1, message.id.get())
+                .uint64(2, message.author.id.get())
+                .string(3, &message.content)
+                .int64(4, message.timestamp.as_secs());
+            response.message(1, &pin)
+        });
+        Ok(response)
+    }
+
+    async fn set_pinned(
+        ctx: &Context,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        pin: bool,
+    ) -> Outcome {
+        let guild_id = guild_of(ctx, channel_id).await?;
+        let enabled = if pin {
+            ctx.config.enable_pin
+        } else {
+            ctx.config.enable_unpin
+        };
+        if !enabled {
+            return Err((Code::PermissionDenied, "This action is disabled".to_owned()));
+        }
+        if let Some(until) = freeze::frozen_until(ctx, channel_id) {
+            return Err((
+                Code::FailedPrecondition,
+                format!("Pins of the channel are frozen until {until}"),
+            ));
+        }
+        if !pin && ctx.store.is_locked(message_id) {
+            return Err((Code::FailedPrecondition, "The pin is locked".to_owned()));
+        }
+
+        let reason = if pin {
+            "Pinned through the control API"
+        } else {
+            "Unpinned through the control API"
+        };
+        if let Err(e) = do_pin(&ctx.http, channel_id, message_id, pin, reason).await {
+            metrics::observe_pin_error(&e, if pin { "create_pin" } else { "delete_pin" });
+            log::warn!("[{}] Control API failed to change a pin: {e}", channel_id);
+            return Err(pin_failure(&e));
+        }
+
+        log::info!(
+            "[{}] {} message {} through the control API",
+            channel_id,
+            if pin { "Pinned" } else { "Unpinned" },
+            message_id
+        );
+        if ctx
+            .store
+            .remove_timed_unpin(message_id)
+            .await
+            .unwrap_or(false)
+        {
+            ctx.scheduler.wake();
+        }
+        if let Some(bot_id) = ctx.store.bot_user_id() {
+            let event = PinEvent::new(pin, guild_id, channel_id, message_id, bot_id);
+            pinlog::record_with_reason(ctx, event, Some(reason.to_owned())).await;
+        }
+        Ok(Message::default().bool(1, pin))
+    }
+
+    /// The guild of the channel, which has to be on the allowlist
+    async fn guild_of(
+        ctx: &Context,
+        channel_id: Id<ChannelMarker>,
+    ) -> Result<Id<GuildMarker>, Failure> {
+        let channel = match ctx.http.channel(channel_id).await {
+            Ok(response) => response
+                .model()
+                .await
+                .map_err(|e| (Code::Unavailable, format!("Invalid channel data: {e}")))?,
+            Err(e) => {
+                metrics::observe_http_error(&e, "channel");
+                return Err(status_of(error::api_code(&e), &e.to_string()));
+            }
+        };
+        match channel.guild_id {
+            Some(guild_id) if ctx.config.is_guild_allowed(guild_id) => Ok(guild_id),
+            Some(_) => Err((
+                Code::PermissionDenied,
+                "The guild is not on the allowlist".to_owned(),
+            )),
+            None => Err((
+                Code::InvalidArgument,
+                "Only channels of guilds are supported".to_owned(),
+            )),
+        }
+    }
+
+    fn pin_failure(error: &PinbotError) -> Failure {
+        status_of(error.code(), &error.to_string())
+    }
+
+    /// The status for an error of Discord, with its own message
+    fn status_of(code: Option<u64>, message: &str) -> Failure {
+        let code = match code {
+            Some(error::MISSING_ACCESS | error::MISSING_PERMISSIONS) => Code::PermissionDenied,
+            Some(error::UNKNOWN_CHANNEL | error::UNKNOWN_MESSAGE) => Code::NotFound,
+            Some(error::MAX_PINS) => Code::FailedPrecondition,
+            _ => Code::Unavailable,
+        };
+        (code, message.to_owned())
+    }
+
+    /// A response with only headers, which gRPC reads the status of a failed call from
+    pub(super) fn failure(code: Code, message: &str) -> Response<Body> {
+        let mut response = Response::new(Body::empty());
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        headers.insert(GRPC_STATUS, HeaderValue::from(code as u16));
+        let message = percent_encoding::utf8_percent_encode(message, MESSAGE_ESCAPES).to_string();
+        if let Ok(value) = HeaderValue::from_str(&message) {
+            headers.insert(GRPC_MESSAGE, value);
+        }
+        response
+    }
+
+    /// The response message, followed by the OK status in the trailers
+    async fn respond(message: &Message) -> Response<Body> {
+        let (mut sender, body) = Body::channel();
+        // A new channel has room for one chunk, and the trailers have their own
+        if sender.try_send_data(grpc::frame(message)).is_ok() {
+            let mut trailers = HeaderMap::new();
+            trailers.insert(GRPC_STATUS, HeaderValue::from(Code::Ok as u16));
+            if let Err(e) = sender.send_trailers(trailers).await {
+                log::warn!("Control API failed to send the status of a call: {e}");
+            }
+        }
+        let mut response = Response::new(body);
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        response
+    }
+}
+
+/// The protobuf encoding and the gRPC framing of the messages in `proto/control.proto`
+#[cfg(feature = "control")]
+mod grpc {
+    use std::collections::HashMap;
+
+    use hyper::body::Bytes;
+
+    const VARINT: u64 = 0;
+    const FIXED64: u64 = 1;
+    const LENGTH_DELIMITED: u64 = 2;
+    const FIXED32: u64 = 5;
+
+    /// Status codes of gRPC, the subset the service responds with
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Code {
+        Ok = 0,
+        InvalidArgument = 3,
+        NotFound = 5,
+        PermissionDenied = 7,
+        FailedPrecondition = 9,
+        Unimplemented = 12,
+        Unavailable = 14,
+        Unauthenticated = 16,
+    }
+
+    /// The varint fields of a request message by number
+    pub type Fields = HashMap<u32, u64>;
+
+    /// A message being encoded, which leaves out fields at their default value like proto3
+    #[derive(Default, Debug, PartialEq, Eq)]
+    pub struct Message(pub(super) Vec<u8>);
+
+    impl Message {
+        pub fn uint64(self, field: u32, value: u64) -> Self {
+            self.optional_uint64(field, Some(value).filter(|it| *it != 0))
+        }
+
+        /// A field with presence, which is written even if it's 0
+        pub fn optional_uint64(mut self, field: u32, value: Option<u64>) -> Self {
+            if let Some(value) = value {
+                self.key(field, VARINT);
+                varint(&mut self.0, value);
+            }
+            self
+        }
+
+        /// Negative values take all ten bytes of a varint, protobuf doesn't zigzag int64
+        pub fn int64(self, field: u32, value: i64) -> Self {
+            self.uint64(field, value as u64)
+        }
+
+        pub fn bool(self, field: u32, value: bool) -> Self {
+            self.uint64(field, u64::from(value))
+        }
+
+        pub fn string(self, field: u32, value: &str) -> Self {
+            if value.is_empty() {
+                self
+            } else {
+                self.length_delimited(field, value.as_bytes())
+            }
+        }
+
+        /// An embedded message, or one entry of a repeated field
+        pub fn message(self, field: u32, value: &Self) -> Self {
+            self.length_delimited(field, &value.0)
+        }
+
+        fn length_delimited(mut self, field: u32, value: &[u8]) -> Self {
+            self.key(field, LENGTH_DELIMITED);
+            varint(&mut self.0, value.len() as u64);
+            self.0.extend(value);
+            self
+        }
+
+        fn key(&mut self, field: u32, wire_type: u64) {
+            varint(&mut self.0, u64::from(field) << 3 | wire_type);
+        }
+    }
+
+    fn varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn read_varint(input: &mut &[u8]) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = input.split_first()?;
+            *input = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    /// The varint fields of the message, skipping all others, `None` if it's malformed
+    pub fn decode(mut input: &[u8]) -> Option<Fields> {
+        let mut fields = Fields::new();
+        while !input.is_empty() {
+            let key = read_varint(&mut input)?;
+            let number = u32::try_from(key >> 3).ok()?;
+            match key & 7 {
+                // A field which occurs more than once takes the last value, like protobuf does
+                VARINT => {
+                    fields.insert(number, read_varint(&mut input)?);
+                }
+                FIXED64 => input = input.get(8..)?,
+                LENGTH_DELIMITED => {
+                    let length = usize::try_from(read_varint(&mut input)?).ok()?;
+                    input = input.get(length..)?;
+                }
+                FIXED32 => input = input.get(4..)?,
+                _ => return None,
+            }
+        }
+        Some(fields)
+    }
+
+    /// The message with the prefix of gRPC, which says it's uncompressed and how long it is
+    pub fn frame(message: &Message) -> Bytes {
+        let mut framed = Vec::with_capacity(message.0.len() + 5);
+        framed.push(0);
+        framed.extend((message.0.len() as u32).to_be_bytes());
+        framed.extend(&message.0);
+        framed.into()
+    }
+
+    /// The message of a unary call from the body of the request
+    pub fn unframe(body: &[u8]) -> Result<&[u8], (Code, String)> {
+        let Some((&[compressed, ref length @ ..], message)) = body.split_first_chunk::<5>() else {
+            return Err((
+                Code::InvalidArgument,
+                "The request has no message".to_owned(),
+            ));
+        };
+        if compressed != 0 {
+            let content = "Compressed messages are not supported";
+            return Err((Code::Unimplemented, content.to_owned()));
+        }
+        if u32::from_be_bytes(*length) as usize != message.len() {
+            let content = "Expected exactly one request message";
+            return Err((Code::InvalidArgument, content.to_owned()));
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(all(test, feature = "control"))]
+mod tests {
+    use hyper::{body::HttpBody, Body, Request, StatusCode};
+    use twilight_model::id::Id;
+
+    use super::{
+        grpc::{self, Code, Message},
+        server::{self, authorized, call, handle, Call},
+    };
+
+    fn request(token: &str, method: &str, message: &Message) -> Request<Body> {
+        Request::post(format!("/pinbot.control.v1.Control/{method}"))
+            .header("content-type", "application/grpc")
+            .header("authorization", format!("Bearer {token}"))
+            .body(Body::from(grpc::frame(message)))
+            .unwrap()
+    }
+
+    fn status(headers: &hyper::HeaderMap) -> Option<&str> {
+        headers.get("grpc-status").and_then(|it| it.to_str().ok())
+    }
+
+    #[test]
+    fn messages_use_the_protobuf_encoding() {
+        // The examples of the protobuf documentation
+        assert_eq!(
+            Message::default().uint64(1, 150),
+            Message(vec![0x08, 0x96, 0x01])
+        );
+        let string = Message::default().string(2, "testing");
+        assert_eq!(string.0, b"\x12\x07testing");
+        let embedded = Message::default().message(3, &Message::default().uint64(1, 150));
+        assert_eq!(embedded.0, [0x1a, 0x03, 0x08, 0x96, 0x01]);
+
+        // Defaults are left out, unless the field has presence
+        assert!(Message::default().uint64(1, 0).bool(2, false).0.is_empty());
+        assert_eq!(Message::default().optional_uint64(1, Some(0)).0, [0x08, 0]);
+        let negative = Message::default().int64(1, -1);
+        assert_eq!(negative.0.len(), 11);
+    }
+
+    #[test]
+    fn requests_are_decoded_skipping_unknown_fields() {
+        let mut message = vec![0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i', 0x10, 0x05];
+        let fields = grpc::decode(&message).unwrap();
+        assert_eq!(fields.get(&1), Some(&150));
+        assert_eq!(fields.get(&2), Some(&5));
+
+        message.truncate(5);
+        assert!(grpc::decode(&message).is_none());
+
+        let framed = grpc::frame(&Message::default().uint64(1, 7));
+        assert_eq!(&framed[..5], [0, 0, 0, 0, 2]);
+        assert_eq!(grpc::unframe(&framed).unwrap(), [0x08, 7]);
+        assert_eq!(
+            grpc::unframe(&[1, 0, 0, 0, 0]).unwrap_err().0,
+            Code::Unimplemented
+        );
+        assert_eq!(
+            grpc::unframe(&[0, 0, 0, 0, 3, 1]).unwrap_err().0,
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn calls_are_matched_with_their_ids() {
+        let ids = grpc::frame(&Message::default().uint64(1, 1).uint64(2, 2));
+        let ids = grpc::unframe(&ids).unwrap();
+        let path = |method: &str| format!("/pinbot.control.v1.Control/{method}");
+        assert_eq!(call(&path("ListPins"), ids), Ok(Call::ListPins(Id::new(1))));
+        assert_eq!(
+            call(&path("Pin"), ids),
+            Ok(Call::Pin(Id::new(1), Id::new(2)))
+        );
+        assert_eq!(
+            call(&path("Unpin"), ids),
+            Ok(Call::Unpin(Id::new(1), Id::new(2)))
+        );
+        assert_eq!(call(&path("GetStats"), &[]), Ok(Call::GetStats));
+
+        assert_eq!(
+            call(&path("Pin"), &[0x08, 1]).unwrap_err().0,
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            call(&path("Delete"), ids).unwrap_err().0,
+            Code::Unimplemented
+        );
+        assert_eq!(
+            call("/other.Service/Pin", ids).unwrap_err().0,
+            Code::Unimplemented
+        );
+    }
+
+    #[test]
+    fn only_the_bearer_token_is_authorized() {
+        assert!(authorized(Some("Bearer secret"), "secret"));
+        assert!(!authorized(Some("Bearer secre"), "secret"));
+        assert!(!authorized(Some("Bearer secret!"), "secret"));
+        assert!(!authorized(Some("secret"), "secret"));
+        assert!(!authorized(None, "secret"));
+        assert!(!authorized(Some("Bearer "), ""));
+    }
+
+    #[tokio::test]
+    async fn calls_need_the_token() {
+        let ctx = crate::tests::context(serde_json::json!({
+            "token": "",
+            "control_token": "secret",
+        }))
+        .await;
+        let empty = Message::default();
+
+        let response = handle(&ctx, request("wrong", "GetStats", &empty)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(status(response.headers()), Some("16"));
+        let response = handle(&ctx, request("secret", "Unknown", &empty)).await;
+        assert_eq!(status(response.headers()), Some("12"));
+
+        let mut response = handle(&ctx, request("secret", "GetStats", &empty)).await;
+        assert_eq!(status(response.headers()), None);
+        let body = response.data().await.unwrap().unwrap();
+        // Nothing was counted yet, so every field is at its default and left out
+        assert_eq!(grpc::unframe(&body), Ok(&[][..]));
+        let trailers = response.trailers().await.unwrap().unwrap();
+        assert_eq!(status(&trailers), Some("0"));
+
+        let plain = Request::get("/stats").body(Body::empty()).unwrap();
+        let response = handle(&ctx, plain).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn the_server_speaks_http2() {
+        let ctx = crate::tests::context(serde_json::json!({
+            "token": "",
+            "control_token": "secret",
+        }))
+        .await;
+        let (address, server) =
+            server::bind(std::sync::Arc::new(ctx), ([127, 0, 0, 1], 0).into()).unwrap();
+        tokio::spawn(server);
+
+        let client = hyper::Client::builder()
+            .http2_only(true)
+            .build_http::<Body>();
+        let mut request = request("secret", "GetStats", &Message::default());
+        *request.uri_mut() = format!("http://{address}{}", request.uri())
+            .parse()
+            .unwrap();
+        let mut response = client.request(request).await.unwrap();
+        assert_eq!(response.version(), hyper::Version::HTTP_2);
+        while response.data().await.is_some() {}
+        let trailers = response.trailers().await.unwrap().unwrap();
+        assert_eq!(status(&trailers), Some("0"));
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::{
+        message::component::{ActionRow, Button, ButtonStyle},
+        Message,
+    },
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, GuildMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    confirmation, defer, do_pin, ephemeral,
+    error::{self, PinbotError},
+    events::PinEvent,
+    gate, jump_link, link, lock, metrics, pinlog, Context,
+};
+
+/// Discord rejects messages with more characters than this
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// Handle `/pin-copy`, which reposts a pinned message in another channel, optionally moving the pin
+pub async fn copy_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut link = None;
+    let mut target_id = None;
+    let mut pin = true;
+    let mut unpin = false;
+    for option in &data.options {
+        match (option.name.as_str(), &option.value) {
+            ("message", CommandOptionValue::String(value)) => link = link::parse_message_ref(value),
+            ("channel", CommandOptionValue::Channel(id)) => target_id = Some(*id),
+            ("pin", CommandOptionValue::Boolean(value)) => pin = *value,
+            ("move", CommandOptionValue::Boolean(value)) => unpin = *value,
+            _ => {}
+        }
+    }
+
+    // The copy is pinned in the other channel, and moving the pin unpins the original here
+    let rejection = gate::rejection(ctx, event, guild_id, target_id.unwrap_or(channel_id), true)
+        .or_else(|| {
+            unpin
+                .then(|| gate::rejection(ctx, event, guild_id, channel_id, false))
+                .flatten()
+        });
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    // Only messages the user can already see in this channel may be copied elsewhere
+    let Some(link) = link.filter(|it| it.is_in(guild_id, channel_id)) else {
+        let response =
+            ephemeral("Use this command in the channel of the pinned message, with a link to it.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    };
+    if unpin && lock::prevents_unpin(ctx, event, link.message_id) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(lock::LOCKED))
+            .await?;
+        return Ok(());
+    }
+
+    // Discord computes the permissions of the user in every channel passed as an option
+    let target = target_id.and_then(|id| data.resolved.as_ref()?.channels.get(&id));
+    let Some(target) = target.filter(|it| it.id != channel_id) else {
+        let response = ephemeral("Pick a different channel to copy the pin to.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    };
+    let target_id = target.id;
+    if !target
+        .permissions
+        .contains(Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES)
+    {
+        let response = ephemeral(format!("You can't send messages in <#{target_id}>."));
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags());
+
+    let message = match ctx.http.message(channel_id, link.message_id).await {
+        Ok(response) => Some(response.model().await?),
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_MESSAGE) => None,
+        Err(e) => {
+            metrics::observe_http_error(&e, "message");
+            return Err(e.into());
+        }
+    };
+    let Some(message) = message.filter(|it| it.pinned) else {
+        request.content("That message isn't pinned.")?.await?;
+        return Ok(());
+    };
+
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+    let username = &user.name;
+    let original = jump_link(guild_id, channel_id, message.id);
+    let button = row!(link!("Original", original.clone()));
+
+    let copy = ctx
+        .http
+        .create_message(target_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&copy_content(&message, channel_id))?
+        .await;
+    let copy = match copy {
+        Ok(response) => response.model().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "create_message");
+            log::error!("Failed to copy pin due to error: {e}");
+            let content = match error::api_code(&e) {
+                Some(error::MISSING_ACCESS) => format!("I can't see <#{target_id}>."),
+                Some(error::MISSING_PERMISSIONS) => {
+                    format!("I don't have permission to send messages in <#{target_id}>.")
+                }
+                _ => format!("I couldn't post the copy in <#{target_id}>. Try again?"),
+            };
+            request.content(&content)?.await?;
+            return Ok(());
+        }
+    };
+
+    // Every step after the copy can fail on its own, so report each of them
+    let mut report = vec![format!(
+        "\u{1F4CC} **{username}** copied a pinned message to <#{target_id}>."
+    )];
+
+    if pin {
+        let channel_name = ctx.config.channel_name(event.channel.as_ref());
+        let reason = format!("{username} copied a pin from {channel_name}");
+        match do_pin(&ctx.http, target_id, copy.id, true, &reason).await {
+            Ok(()) => {
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(true, guild_id, target_id, copy.id, user.id)
+                        .author(copy.author.id),
+                )
+                .await
+            }
+            Err(e) => {
+                metrics::observe_pin_error(&e, "create_pin");
+                log::error!("Failed to pin copy due to error: {e}");
+                report.push(failure("pin the copy", &e));
+            }
+        }
+    }
+
+    if unpin {
+        let reason = format!("{username} moved a pin to #{}", target.name);
+        match do_pin(&ctx.http, channel_id, message.id, false, &reason).await {
+            Ok(()) => {
+                report.push("The original message was unpinned.".to_owned());
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(false, guild_id, channel_id, message.id, user.id)
+                        .author(message.author.id),
+                )
+                .await;
+                // The pin is gone, so there is nothing left to expire
+                if ctx.store.remove_timed_unpin(message.id).await? {
+                    ctx.scheduler.wake();
+                }
+            }
+            Err(e) => {
+                metrics::observe_pin_error(&e, "delete_pin");
+                log::error!("Failed to unpin original due to error: {e}");
+                report.push(failure("unpin the original", &e));
+            }
+        }
+    }
+
+    let content = report.join("\n");
+    let button = row!(
+        link!("Original", original),
+        link!("Copy", jump_link(guild_id, target_id, copy.id))
+    );
+
+    log::info!("[{}] {}", channel_id, content);
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Explain why one step of the copy failed
+fn failure(step: &str, error: &PinbotError) -> String {
+    match error {
+        PinbotError::Permission { .. } => {
+            format!("I couldn't {step}, since I'm missing permissions there.")
+        }
+        _ => format!("I couldn't {step}, sorry about that..."),
+    }
+}
+
+/// The text of the copy, which points to the original for anything that can't be copied
+pub fn copy_content(message: &Message, channel_id: Id<ChannelMarker>) -> String {
+    let extras = !message.attachments.is_empty()
+        || !message.embeds.is_empty()
+        || !message.sticker_items.is_empty();
+    quoted_copy(message.author.id, &message.content, extras, channel_id)
+}
+
+/// The copy of a message which is only known by its author and text, like in a pin export
+pub fn quoted_copy(
+    author_id: Id<UserMarker>,
+    content: &str,
+    extras: bool,
+    channel_id: Id<ChannelMarker>,
+) -> String {
+    let header = format!("Pinned message by <@{author_id}> in <#{channel_id}>:\n");
+    let mut footer = String::new();
+    if extras {
+        footer.push_str("\n*The original has attachments or embeds, open it to see them.*");
+    }
+
+    let available = MAX_CONTENT_LENGTH - header.chars().count() - footer.chars().count();
+    let mut body: String = content.chars().take(available).collect();
+    if body.len() < content.len() {
+        // Make room for the ellipsis
+        body.pop();
+        body.push('\u{2026}');
+    }
+
+    header + &body + &footer
+}
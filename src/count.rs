@@ -0,0 +1,34 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::Interaction,
+    id::{marker::ChannelMarker, Id},
+};
+
+use crate::{bulk, confirmation, ephemeral, error, locale, metrics, pins::PinApi, Context};
+
+/// Handle `/pin-count`, which tells how many of the allowed pins this channel uses
+pub async fn pin_count(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let content = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => confirmation::pin_count(pins.len(), bulk::MAX_PINS, locale(&ctx.config, event)),
+        Err(e) => {
+            let hint = e.downcast_ref().and_then(|e| {
+                metrics::observe_http_error(e, "pins");
+                error::permission_hint(error::api_code(e))
+            });
+            log::warn!("[{}] Failed to count the pins: {}", channel_id, e);
+            hint.unwrap_or("I couldn't load the pins of this channel, try again later.")
+                .to_owned()
+        }
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &ephemeral(content))
+        .await?;
+    Ok(())
+}
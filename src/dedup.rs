@@ -0,0 +1,178 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use twilight_model::id::{
+    marker::{InteractionMarker, MessageMarker},
+    Id,
+};
+
+/// Bounded map of the most recently handled interactions and their outcome.
+///
+/// Discord can deliver the same interaction again after a reconnect, which would pin twice.
+/// This is best-effort, since the ids are only kept in memory of this process.
+pub struct RecentInteractions {
+    capacity: usize,
+    seen: Mutex<Seen>,
+}
+
+#[derive(Default)]
+struct Seen {
+    /// The outcome of each interaction, none while it is still being handled
+    outcomes: HashMap<Id<InteractionMarker>, Option<String>>,
+    order: VecDeque<Id<InteractionMarker>>,
+}
+
+/// Whether an interaction was delivered before
+#[derive(Debug, PartialEq, Eq)]
+pub enum Delivery {
+    /// Seen for the first time, so it has to be handled
+    First,
+    /// The first delivery is still being handled
+    Running,
+    /// The first delivery was handled, with the outcome told to the user
+    Handled(String),
+}
+
+impl RecentInteractions {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: Mutex::default(),
+        }
+    }
+
+    /// Remember the interaction, and tell whether it was already delivered before
+    pub fn insert(&self, id: Id<InteractionMarker>) -> Delivery {
+        if self.capacity == 0 {
+            return Delivery::First;
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+        if let Some(outcome) = seen.outcomes.get(&id) {
+            return outcome.clone().map_or(Delivery::Running, Delivery::Handled);
+        }
+
+        seen.outcomes.insert(id, None);
+        seen.order.push_back(id);
+        if seen.order.len() > self.capacity {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.outcomes.remove(&oldest);
+            }
+        }
+        Delivery::First
+    }
+
+    /// Remember what the user was told, unless an outcome was already recorded
+    pub fn complete(&self, id: Id<InteractionMarker>, outcome: impl Into<String>) {
+        let mut seen = self.seen.lock().unwrap();
+        if let Some(previous @ None) = seen.outcomes.get_mut(&id) {
+            *previous = Some(outcome.into());
+        }
+    }
+}
+
+/// Bounded set of the messages pinned with a public confirmation, whose system message needs
+/// no notice in its place.
+///
+/// They are added before pinning, since the system message can arrive before the response.
+pub struct RecentPins {
+    capacity: usize,
+    messages: Mutex<VecDeque<Id<MessageMarker>>>,
+}
+
+impl RecentPins {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::default(),
+        }
+    }
+
+    pub fn insert(&self, id: Id<MessageMarker>) {
+        let mut messages = self.messages.lock().unwrap();
+        messages.push_back(id);
+        if messages.len() > self.capacity {
+            messages.pop_front();
+        }
+    }
+
+    /// Forget the message, and tell whether it was pinned with a public confirmation
+    pub fn take(&self, id: Id<MessageMarker>) -> bool {
+        let mut messages = self.messages.lock().unwrap();
+        let Some(index) = messages.iter().position(|it| *it == id) else {
+            return false;
+        };
+        messages.remove(index);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_interactions_get_the_outcome_of_the_first() {
+        let interactions = RecentInteractions::new(10);
+        let id = Id::new(1);
+
+        assert_eq!(interactions.insert(id), Delivery::First);
+        assert_eq!(interactions.insert(id), Delivery::Running);
+
+        // The specific outcome of the pin is recorded before the generic one
+        interactions.complete(id, "Pinned a message.");
+        interactions.complete(id, "Done.");
+        assert_eq!(
+            interactions.insert(id),
+            Delivery::Handled("Pinned a message.".to_owned())
+        );
+        assert_eq!(interactions.insert(Id::new(2)), Delivery::First);
+    }
+
+    #[test]
+    fn oldest_interactions_are_forgotten() {
+        let interactions = RecentInteractions::new(2);
+        for id in 1..=3 {
+            assert_eq!(interactions.insert(Id::new(id)), Delivery::First);
+        }
+        assert_eq!(interactions.insert(Id::new(1)), Delivery::First);
+        assert_eq!(interactions.insert(Id::new(3)), Delivery::Running);
+    }
+
+    #[test]
+    fn disabled_cache_handles_everything() {
+        let interactions = RecentInteractions::new(0);
+        assert_eq!(interactions.insert(Id::new(1)), Delivery::First);
+        interactions.complete(Id::new(1), "Done.");
+        assert_eq!(interactions.insert(Id::new(1)), Delivery::First);
+    }
+
+    #[test]
+    fn completing_unknown_interactions_does_nothing() {
+        let interactions = RecentInteractions::new(1);
+        interactions.complete(Id::new(1), "Done.");
+        assert_eq!(interactions.insert(Id::new(1)), Delivery::First);
+    }
+
+    #[test]
+    fn recent_pins_are_taken_once() {
+        let pins = RecentPins::new(2);
+        pins.insert(Id::new(1));
+        assert!(pins.take(Id::new(1)));
+        assert!(!pins.take(Id::new(1)));
+        assert!(!pins.take(Id::new(2)));
+    }
+
+    #[test]
+    fn oldest_pins_are_forgotten() {
+        let pins = RecentPins::new(2);
+        for id in 1..=3 {
+            pins.insert(Id::new(id));
+        }
+        assert!(!pins.take(Id::new(1)));
+        assert!(pins.take(Id::new(2)));
+        assert!(pins.take(Id::new(3)));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing as log;
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker},
+    Id,
+};
+
+use crate::{confirmation, error, jump_link, metrics, time, Context};
+
+const DAY: u64 = 24 * 60 * 60;
+/// How often the task looks for a digest that is due, they go out within this time
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// A recurring summary of the new pins of a guild, posted to one channel
+#[derive(Deserialize)]
+pub struct PinDigest {
+    pub channel: Id<ChannelMarker>,
+    #[serde(default)]
+    pub period: DigestPeriod,
+    /// Don't post anything for periods without new pins
+    #[serde(default)]
+    pub skip_empty: bool,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestPeriod {
+    /// Every day at midnight UTC
+    Daily,
+    /// Every Monday at midnight UTC
+    #[default]
+    Weekly,
+}
+
+impl DigestPeriod {
+    const fn length(self) -> u64 {
+        match self {
+            Self::Daily => DAY,
+            Self::Weekly => 7 * DAY,
+        }
+    }
+
+    /// The start of the current period, the Unix epoch was a Thursday
+    const fn start(self, now: u64) -> u64 {
+        match self {
+            Self::Daily => now / DAY * DAY,
+            Self::Weekly => (now / DAY - (now / DAY + 3) % 7) * DAY,
+        }
+    }
+
+    const fn empty(self) -> &'static str {
+        match self {
+            Self::Daily => "No new pins today.",
+            Self::Weekly => "No new pins this week.",
+        }
+    }
+}
+
+/// Run the background task, which posts every digest once its period is over
+pub fn spawn(ctx: Arc<Context>) {
+    if ctx.config.pin_digests.is_empty() {
+        return;
+    }
+    if !ctx.store.is_persistent() {
+        log::warn!("pin_digests need a database, no digests will be posted");
+        return;
+    }
+    // The startup summary is at the field limit of tracing, so the digests get their own line
+    log::info!(
+        "Posting pin digests in {} guilds",
+        ctx.config.pin_digests.len()
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            for (&guild_id, digest) in &ctx.config.pin_digests {
+                if let Err(e) = check(&ctx, guild_id, digest).await {
+                    log::error!("[{guild_id}] Failed to post the pin digest: {e}");
+                }
+            }
+        }
+    });
+}
+
+/// Post the digest of the guild if a period ended since the last one
+async fn check(ctx: &Context, guild_id: Id<GuildMarker>, digest: &PinDigest) -> Result<()> {
+    let end = digest.period.start(time::now());
+    let Some(sent_until) = ctx.store.digest_sent(guild_id) else {
+        // The first period is incomplete, the pins from before the config changed don't count
+        ctx.store.set_digest_sent(guild_id, end).await?;
+        return Ok(());
+    };
+    if sent_until >= end {
+        return Ok(());
+    }
+
+    // Periods missed while offline are summarized together, up to one period back
+    let start = sent_until.max(end - digest.period.length());
+    let content = render(ctx, guild_id, digest, start, end);
+    if let Some(ref content) = content {
+        let result = ctx
+            .http
+            .create_message(digest.channel)
+            .allowed_mentions(Some(&confirmation::NO_PINGS))
+            .content(content)?
+            .await;
+        if let Err(e) = result {
+            metrics::observe_http_error(&e, "digest");
+            if let Some(error::MISSING_ACCESS | error::MISSING_PERMISSIONS) = error::api_code(&e) {
+                log::warn!(
+                    "[{guild_id}] Missing permissions to post the pin digest in {}",
+                    digest.channel
+                );
+            }
+            return Err(e.into());
+        }
+    }
+
+    log::info!(
+        "[{guild_id}] {} the pin digest until {end}",
+        if content.is_some() {
+            "Posted"
+        } else {
+            "Skipped"
+        }
+    );
+    ctx.store.set_digest_sent(guild_id, end).await
+}
+
+/// The digest of the pins between the timestamps grouped by channel, or none to skip the post
+fn render(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    digest: &PinDigest,
+    start: u64,
+    end: u64,
+) -> Option<String> {
+    // Pinned again after an unpin or undo, the message still only counts once
+    let mut seen = HashSet::new();
+    let mut pins: Vec<_> = ctx
+        .store
+        .pin_actions(guild_id)
+        .into_iter()
+        .filter(|action| action.pin && (start..end).contains(&action.timestamp))
+        .filter(|action| seen.insert(action.message_id))
+        .collect();
+    if pins.is_empty() {
+        return (!digest.skip_empty).then(|| digest.period.empty().to_owned());
+    }
+    // Stable, so the pins of each channel stay in the order they happened
+    pins.sort_by_key(|action| action.channel_id);
+
+    let mut content = format!(
+        "\u{1F4CC} **Pin digest** from <t:{start}:d> to <t:{end}:d>, {}",
+        confirmation::count(pins.len(), "new pin", "new pins", "en")
+    );
+    let mut channel_id = None;
+    for (index, action) in pins.iter().enumerate() {
+        let mut lines = String::new();
+        if channel_id != Some(action.channel_id) {
+            channel_id = Some(action.channel_id);
+            lines.push_str(&format!("\n\n<#{}>", action.channel_id));
+        }
+        lines.push_str(&format!(
+            "\n- [Message]({}) pinned by <@{}>",
+            jump_link(guild_id, action.channel_id, action.message_id),
+            action.user_id
+        ));
+        // Leave room for the note about the remaining pins
+        if content.chars().count() + lines.chars().count() > MAX_CONTENT_LENGTH - 30 {
+            content.push_str(&format!("\n*and {} more*", pins.len() - index));
+            break;
+        }
+        content.push_str(&lines);
+    }
+    Some(content)
+}
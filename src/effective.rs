@@ -0,0 +1,148 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::Interaction,
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    config::{ConfirmationPlacement, ConfirmationStyle, Visibility},
+    ephemeral, freeze, lock, Context,
+};
+
+/// Handle `/pin-effective-config`, which shows the settings that apply in this channel after
+/// merging the config file, the settings of the server and the state of the channel
+pub async fn effective_config(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    if !lock::can_manage_guild(event) {
+        let response = ephemeral("You need the **Manage Server** permission to do this.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &ephemeral(describe(ctx, event, guild_id, channel_id)),
+        )
+        .await?;
+    Ok(())
+}
+
+fn describe(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> String {
+    let config = &ctx.config;
+    let settings = ctx.store.guild(guild_id);
+    let allowed = config.is_guild_allowed(guild_id);
+    let frozen = freeze::frozen_until(ctx, channel_id);
+
+    let state = |enabled: bool| {
+        if !enabled {
+            "disabled in the config".to_owned()
+        } else if !allowed {
+            "disabled, this server isn't on the allowlist".to_owned()
+        } else if let Some(until) = frozen {
+            format!("frozen until <t:{until}:f>")
+        } else {
+            "enabled".to_owned()
+        }
+    };
+    let role = |pin: bool| {
+        settings
+            .role_for(pin)
+            .map_or_else(|| "anyone".to_owned(), |role_id| format!("<@&{role_id}>"))
+    };
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    let permissions = match event.app_permissions {
+        Some(perms) if perms.contains(Permissions::MANAGE_MESSAGES) => {
+            "**Manage Messages** granted"
+        }
+        Some(_) => "**Manage Messages** missing",
+        None => "unknown",
+    };
+    let visibility = match config.response_visibility {
+        Visibility::Public => "visible to everyone",
+        Visibility::Ephemeral => "visible to the user",
+    };
+    let style = match config.confirmation_style {
+        ConfirmationStyle::Text => "text",
+        ConfirmationStyle::Embed => "embed",
+    };
+    let placement = match config.confirmation_placement {
+        ConfirmationPlacement::Interaction => "in the response",
+        ConfirmationPlacement::Reply => "as a reply",
+        ConfirmationPlacement::Thread => "in the pin thread",
+    };
+    let text_trigger = config
+        .text_trigger
+        .as_deref()
+        .map_or_else(|| "off".to_owned(), |trigger| format!("`{trigger}`"));
+    let reaction_pin = config.reaction_pin.as_ref().map_or_else(
+        || "off".to_owned(),
+        |reaction| {
+            let unpin = if reaction.unpin_below {
+                ", unpinned below it"
+            } else {
+                ""
+            };
+            format!(
+                "{} reactions of `{}`{unpin}",
+                reaction.threshold, reaction.emoji
+            )
+        },
+    );
+    let highlights = config
+        .highlights_channels
+        .get(&guild_id)
+        .map_or_else(|| "off".to_owned(), |target_id| format!("<#{target_id}>"));
+    let pin_thread = ctx.store.pin_thread(channel_id).map_or_else(
+        || "none yet".to_owned(),
+        |thread_id| format!("<#{thread_id}>"),
+    );
+    let account_age = config
+        .min_account_age_days
+        .map_or_else(|| "none".to_owned(), |days| format!("{days} days"));
+    let undo = match config.undo_secs {
+        0 => "off".to_owned(),
+        secs => format!("{secs} seconds"),
+    };
+
+    let lines = [
+        format!("**Effective configuration in <#{channel_id}>**"),
+        format!("- Pinning: {}", state(config.enable_pin)),
+        format!("- Unpinning: {}", state(config.enable_unpin)),
+        format!("- Who can pin: {}", role(true)),
+        format!("- Who can unpin: {}", role(false)),
+        format!("- My permissions: {permissions}"),
+        format!("- Reason required: {}", yes_no(config.require_pin_reason)),
+        format!("- Minimum account age: {account_age}"),
+        format!("- Confirmations: {style} {placement}, {visibility}"),
+        format!(
+            "- Quiet for bot messages: {}",
+            yes_no(config.quiet_for_bot_messages)
+        ),
+        format!("- Pin thread: {pin_thread}"),
+        format!("- Undo: {undo}"),
+        format!("- Text trigger: {text_trigger}"),
+        format!("- Reaction pins: {reaction_pin}"),
+        format!("- Highlights: {highlights}"),
+    ];
+    lines.join("\n")
+}
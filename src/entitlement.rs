@@ -0,0 +1,256 @@
+//! Premium commands, which need an entitlement to one of the SKUs of the application.
+//!
+//! Set `premium_commands` to the SKU each command needs, and build with the `entitlements`
+//! feature:
+//!
+//! ```json
+//! { "premium_commands": { "pin-trends": "1088510058284990888", "bulk-pin": "1088510058284990888" } }
+//! ```
+//!
+//! Discord sends the entitlements of the user with every interaction, but twilight-model 0.15
+//! doesn't deserialize them. With the feature, the shards read the raw payloads and remember
+//! the SKUs of every interaction before twilight parses them. Users without the SKU get an
+//! upsell with a link to its store page instead of the command.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use twilight_model::{
+    application::interaction::Interaction,
+    http::interaction::InteractionResponse,
+    id::{
+        marker::{InteractionMarker, OauthSkuMarker},
+        Id,
+    },
+};
+
+use crate::{ephemeral, Context};
+
+/// Number of interactions whose SKUs are remembered, far more than are handled at once
+const CAPACITY: usize = 1000;
+
+/// Bounded map of the SKUs the user of each recent interaction is entitled to
+#[derive(Default)]
+pub struct Entitlements {
+    granted: Mutex<Granted>,
+}
+
+#[derive(Default)]
+struct Granted {
+    skus: HashMap<Id<InteractionMarker>, Vec<Id<OauthSkuMarker>>>,
+    order: VecDeque<Id<InteractionMarker>>,
+}
+
+impl Entitlements {
+    /// Remember the SKUs of the interaction, the oldest one is forgotten once full
+    #[cfg_attr(not(feature = "entitlements"), allow(dead_code))]
+    pub fn record(&self, id: Id<InteractionMarker>, skus: Vec<Id<OauthSkuMarker>>) {
+        let mut granted = self.granted.lock().unwrap();
+        if granted.skus.insert(id, skus).is_none() {
+            granted.order.push_back(id);
+        }
+        if granted.order.len() > CAPACITY {
+            if let Some(oldest) = granted.order.pop_front() {
+                granted.skus.remove(&oldest);
+            }
+        }
+    }
+
+    /// Whether the user of the interaction is entitled to the SKU
+    pub fn granted(&self, id: Id<InteractionMarker>, sku_id: Id<OauthSkuMarker>) -> bool {
+        let granted = self.granted.lock().unwrap();
+        granted
+            .skus
+            .get(&id)
+            .is_some_and(|skus| skus.contains(&sku_id))
+    }
+}
+
+/// The response to a premium command which the user has no entitlement for
+pub fn upsell(ctx: &Context, event: &Interaction, command: &str) -> Option<InteractionResponse> {
+    // Without the feature the entitlements are never read, so nobody would have any
+    if !cfg!(feature = "entitlements") {
+        return None;
+    }
+    let sku_id = *ctx.config.premium_commands.get(command)?;
+    if ctx.entitlements.granted(event.id, sku_id) {
+        return None;
+    }
+    let store = format!(
+        "https://discord.com/application-directory/{}/store/{sku_id}",
+        event.application_id
+    );
+    Some(ephemeral(format!(
+        "**/{command}** is a premium feature, [upgrade]({store}) to use it."
+    )))
+}
+
+#[cfg(feature = "entitlements")]
+pub use self::gateway::next_event;
+
+#[cfg(feature = "entitlements")]
+mod gateway {
+    use serde::Deserialize;
+    use tracing as log;
+    use twilight_gateway::{error::ReceiveMessageError, EventTypeFlags, Message, Shard};
+    use twilight_model::{
+        gateway::{
+            event::{Event, GatewayEventDeserializer},
+            OpCode,
+        },
+        id::{
+            marker::{InteractionMarker, OauthSkuMarker},
+            Id,
+        },
+        util::Timestamp,
+    };
+
+    use super::Entitlements;
+    use crate::time;
+
+    #[derive(Deserialize)]
+    struct Dispatch {
+        d: RawInteraction,
+    }
+
+    /// The fields of an interaction which twilight-model leaves out
+    #[derive(Deserialize)]
+    struct RawInteraction {
+        id: Id<InteractionMarker>,
+        #[serde(default)]
+        entitlements: Vec<Entitlement>,
+    }
+
+    #[derive(Deserialize)]
+    struct Entitlement {
+        sku_id: Id<OauthSkuMarker>,
+        #[serde(default)]
+        deleted: bool,
+        /// Subscriptions end, one-time purchases don't
+        #[serde(default)]
+        ends_at: Option<Timestamp>,
+    }
+
+    /// Like [`Shard::next_event`], remembering the entitlements of every interaction on the way
+    pub async fn next_event(
+        shard: &mut Shard,
+        entitlements: &Entitlements,
+    ) -> Result<Event, ReceiveMessageError> {
+        loop {
+            let text = match shard.next_message().await? {
+                Message::Close(frame) => return Ok(Event::GatewayClose(frame)),
+                Message::Text(text) => text,
+            };
+            if let Some((id, skus)) = active_skus(&text) {
+                entitlements.record(id, skus);
+            }
+            // twilight skips events it doesn't know, since Discord sends undocumented ones
+            if !known(&text) {
+                log::debug!("Skipped an unknown event");
+                continue;
+            }
+            if let Some(event) = twilight_gateway::parse(text, shard.config().event_types())? {
+                return Ok(event.into());
+            }
+        }
+    }
+
+    /// Whether twilight can parse the payload, the same check it does before parsing
+    fn known(text: &str) -> bool {
+        GatewayEventDeserializer::from_json(text).is_some_and(|event| {
+            OpCode::from(event.op())
+                .is_some_and(|op| EventTypeFlags::try_from((op, event.event_type())).is_ok())
+        })
+    }
+
+    /// The SKUs the user of the interaction is entitled to, if the payload is an interaction
+    pub(super) fn active_skus(
+        text: &str,
+    ) -> Option<(Id<InteractionMarker>, Vec<Id<OauthSkuMarker>>)> {
+        let event = GatewayEventDeserializer::from_json(text)?;
+        if event.op() != OpCode::Dispatch as u8 || event.event_type() != Some("INTERACTION_CREATE")
+        {
+            return None;
+        }
+        let interaction = match serde_json::from_str::<Dispatch>(text) {
+            Ok(dispatch) => dispatch.d,
+            Err(e) => {
+                log::warn!("Failed to read the entitlements of an interaction: {e}");
+                return None;
+            }
+        };
+        let now = i64::try_from(time::now()).unwrap_or(i64::MAX);
+        let skus = interaction
+            .entitlements
+            .iter()
+            .filter(|it| !it.deleted && it.ends_at.is_none_or(|end| end.as_secs() > now))
+            .map(|it| it.sku_id)
+            .collect();
+        Some((interaction.id, skus))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{context, interaction};
+
+    #[tokio::test]
+    async fn premium_commands_need_their_sku() {
+        let ctx = context(serde_json::json!({
+            "token": "",
+            "premium_commands": { "pin-trends": "10" },
+        }))
+        .await;
+        let event = interaction(serde_json::json!({}));
+
+        assert!(upsell(&ctx, &event, "pin").is_none());
+        let response = upsell(&ctx, &event, "pin-trends");
+        assert_eq!(response.is_some(), cfg!(feature = "entitlements"));
+
+        ctx.entitlements.record(event.id, vec![Id::new(10)]);
+        assert!(upsell(&ctx, &event, "pin-trends").is_none());
+        ctx.entitlements.record(event.id, vec![Id::new(11)]);
+        assert_eq!(
+            upsell(&ctx, &event, "pin-trends").is_some(),
+            cfg!(feature = "entitlements")
+        );
+    }
+
+    #[test]
+    fn old_interactions_are_forgotten() {
+        let entitlements = Entitlements::default();
+        for id in 1..=CAPACITY as u64 + 1 {
+            entitlements.record(Id::new(id), vec![Id::new(10)]);
+        }
+        assert!(!entitlements.granted(Id::new(1), Id::new(10)));
+        assert!(entitlements.granted(Id::new(2), Id::new(10)));
+    }
+
+    #[cfg(feature = "entitlements")]
+    #[test]
+    fn only_active_entitlements_are_read_from_interactions() {
+        let payload = serde_json::json!({
+            "op": 0,
+            "s": 1,
+            "t": "INTERACTION_CREATE",
+            "d": {
+                "id": "2",
+                "entitlements": [
+                    { "sku_id": "10" },
+                    { "sku_id": "11", "deleted": true },
+                    { "sku_id": "12", "ends_at": "2020-01-01T00:00:00.000000+00:00" },
+                    { "sku_id": "13", "ends_at": "2999-01-01T00:00:00.000000+00:00" },
+                ],
+            },
+        });
+        let (id, skus) = gateway::active_skus(&payload.to_string()).unwrap();
+        assert_eq!(id, Id::new(2));
+        assert_eq!(skus, [Id::new(10), Id::new(13)]);
+
+        let message = serde_json::json!({ "op": 0, "s": 2, "t": "MESSAGE_CREATE", "d": {} });
+        assert!(gateway::active_skus(&message.to_string()).is_none());
+    }
+}
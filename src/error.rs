@@ -0,0 +1,113 @@
+use thiserror::Error;
+use twilight_http::{api_error::ApiError, error::ErrorType};
+
+pub const UNKNOWN_CHANNEL: u64 = 10003;
+pub const UNKNOWN_MESSAGE: u64 = 10008;
+/// The interaction wasn't acknowledged within 3 seconds, so its token can't be used anymore
+pub const UNKNOWN_INTERACTION: u64 = 10062;
+pub const MAX_PINS: u64 = 30003;
+pub const MISSING_ACCESS: u64 = 50001;
+/// The user has closed their DMs, or blocked us
+pub const CANNOT_DM: u64 = 50007;
+pub const MISSING_PERMISSIONS: u64 = 50013;
+
+/// The ways the bot fails which callers tell apart, everything else is [`PinbotError::Other`].
+///
+/// Errors of Discord keep the request error as their source and display it unchanged, so they
+/// are logged the same as before.
+#[derive(Debug, Error)]
+pub enum PinbotError {
+    /// The config file is missing, malformed or inconsistent
+    #[error(transparent)]
+    Config(anyhow::Error),
+    /// We can't see the channel, or lack a permission in it
+    #[error("{source}")]
+    Permission {
+        code: u64,
+        source: twilight_http::Error,
+    },
+    /// The channel or message was deleted
+    #[error("{source}")]
+    NotFound {
+        code: u64,
+        source: twilight_http::Error,
+    },
+    /// Any other failed request, with the JSON error code if Discord sent one
+    #[error("{source}")]
+    Api {
+        code: Option<u64>,
+        source: twilight_http::Error,
+    },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl PinbotError {
+    /// The JSON error code returned by Discord, if the request failed with one
+    pub fn code(&self) -> Option<u64> {
+        match *self {
+            Self::Permission { code, .. } | Self::NotFound { code, .. } => Some(code),
+            Self::Api { code, .. } => code,
+            Self::Config(_) => None,
+            Self::Other(ref error) => error_code(error),
+        }
+    }
+
+    /// The failed request, for the metrics
+    pub fn http(&self) -> Option<&twilight_http::Error> {
+        match self {
+            Self::Permission { source, .. }
+            | Self::NotFound { source, .. }
+            | Self::Api { source, .. } => Some(source),
+            Self::Config(error) | Self::Other(error) => error.downcast_ref(),
+        }
+    }
+}
+
+impl From<twilight_http::Error> for PinbotError {
+    fn from(source: twilight_http::Error) -> Self {
+        match api_code(&source) {
+            Some(code @ (MISSING_ACCESS | MISSING_PERMISSIONS)) => {
+                Self::Permission { code, source }
+            }
+            Some(code @ (UNKNOWN_CHANNEL | UNKNOWN_MESSAGE)) => Self::NotFound { code, source },
+            code => Self::Api { code, source },
+        }
+    }
+}
+
+/// The JSON error code returned by Discord, if the request failed with one
+pub fn error_code(error: &anyhow::Error) -> Option<u64> {
+    if let Some(error) = error.downcast_ref::<PinbotError>() {
+        return error.code();
+    }
+    api_code(error.downcast_ref()?)
+}
+
+/// Same as [`error_code`], for errors directly returned by the http client
+pub fn api_code(error: &twilight_http::Error) -> Option<u64> {
+    match error.kind() {
+        ErrorType::Response {
+            error: ApiError::General(error),
+            ..
+        } => Some(error.code),
+        _ => None,
+    }
+}
+
+/// What to tell the user if a pin failed because of our permissions in the channel.
+///
+/// Discord answers with Missing Access if we can't see the channel at all, and with Missing
+/// Permissions if we can see it but lack Manage Messages.
+pub fn permission_hint(code: Option<u64>) -> Option<&'static str> {
+    match code? {
+        MISSING_ACCESS => Some("I can't see this channel \u{2014} check my permissions."),
+        MISSING_PERMISSIONS => Some("I need the **Manage Messages** permission in this channel."),
+        _ => None,
+    }
+}
+
+/// Whether the request failed because the channel or message was deleted
+pub fn is_unknown_target(error: &anyhow::Error) -> bool {
+    matches!(error_code(error), Some(UNKNOWN_CHANNEL | UNKNOWN_MESSAGE))
+}
@@ -0,0 +1,176 @@
+//! Pin actions published as JSON to a Redis channel, for integration with other systems.
+//!
+//! Every message has this shape:
+//!
+//! ```json
+//! {
+//!   "action": "pin",
+//!   "guild_id": "81384788765712384",
+//!   "channel_id": "381889648827301889",
+//!   "message_id": "1027999262437863454",
+//!   "user_id": "86699011792191488",
+//!   "author_id": "86699011792191488",
+//!   "timestamp": 1665678890
+//! }
+//! ```
+//!
+//! `action` is either `"pin"` or `"unpin"`, `user_id` is who used the command and `author_id`
+//! who wrote the message. `author_id` is `null` for pins done without loading the message, like
+//! scheduled pins or expired timed pins, and `user_id` is the bot itself for the latter.
+//! `timestamp` is the unix time in seconds.
+//!
+//! Every successful pin and unpin done through the bot is published, see [`crate::pinlog::record`].
+
+use serde::Serialize;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing as log;
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
+    Id,
+};
+
+use crate::{config::Config, time};
+
+/// Events waiting to be published, anything beyond this is dropped
+#[cfg(feature = "redis")]
+const BUFFER_SIZE: usize = 256;
+
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Pin,
+    Unpin,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PinEvent {
+    pub action: Action,
+    pub guild_id: Id<GuildMarker>,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Id<MessageMarker>,
+    pub user_id: Id<UserMarker>,
+    pub author_id: Option<Id<UserMarker>>,
+    pub timestamp: u64,
+}
+
+impl PinEvent {
+    /// The pin or unpin of the message by the user, which happened just now
+    pub fn new(
+        pin: bool,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        user_id: Id<UserMarker>,
+    ) -> Self {
+        Self {
+            action: if pin { Action::Pin } else { Action::Unpin },
+            guild_id,
+            channel_id,
+            message_id,
+            user_id,
+            author_id: None,
+            timestamp: time::now(),
+        }
+    }
+
+    /// Add who wrote the message, if it was loaded anyway
+    pub const fn author(mut self, author_id: Id<UserMarker>) -> Self {
+        self.author_id = Some(author_id);
+        self
+    }
+
+    pub const fn is_pin(&self) -> bool {
+        matches!(self.action, Action::Pin)
+    }
+}
+
+/// Hands events to a background task, so publishing never delays a command
+pub struct Publisher {
+    sender: Option<mpsc::Sender<PinEvent>>,
+}
+
+impl Publisher {
+    /// Start publishing if a Redis url is configured
+    pub fn spawn(config: &Config) -> Self {
+        let Some(ref url) = config.redis_url else {
+            return Self { sender: None };
+        };
+
+        #[cfg(feature = "redis")]
+        {
+            let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+            tokio::spawn(run(url.clone(), config.redis_channel.clone(), receiver));
+            Self {
+                sender: Some(sender),
+            }
+        }
+
+        #[cfg(not(feature = "redis"))]
+        {
+            log::warn!("Ignoring redis_url {url:?}, the bot was built without the redis feature");
+            Self { sender: None }
+        }
+    }
+
+    pub fn publish(&self, event: PinEvent) {
+        let Some(ref sender) = self.sender else {
+            return;
+        };
+        match sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(event)) => {
+                log::warn!(
+                    "Dropping pin event of message {}, too many pending",
+                    event.message_id
+                );
+            }
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+async fn run(url: String, channel: String, mut receiver: mpsc::Receiver<PinEvent>) {
+    let client = match redis::Client::open(url) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Invalid redis_url, pin events are not published: {e}");
+            return;
+        }
+    };
+
+    // Connect lazily and again after every failure, so an outage only drops the events during it
+    let mut connection = None;
+    while let Some(event) = receiver.recv().await {
+        let json = match serde_json::to_string(&event) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to serialize pin event: {e}");
+                continue;
+            }
+        };
+
+        if connection.is_none() {
+            match client.get_multiplexed_async_connection().await {
+                Ok(conn) => connection = Some(conn),
+                Err(e) => {
+                    log::warn!("Failed to connect to redis, dropping pin event: {e}");
+                    continue;
+                }
+            }
+        }
+        let Some(ref mut conn) = connection else {
+            continue;
+        };
+
+        let result: redis::RedisResult<()> = redis::cmd("PUBLISH")
+            .arg(&channel)
+            .arg(json)
+            .query_async(conn)
+            .await;
+        if let Err(e) = result {
+            log::warn!("Failed to publish pin event: {e}");
+            connection = None;
+        }
+    }
+}
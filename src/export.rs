@@ -0,0 +1,564 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::{
+        message::{
+            component::{ActionRow, Button, ButtonStyle},
+            MessageFlags,
+        },
+        Message,
+    },
+    http::attachment::Attachment,
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    bulk, confirmation, copy, defer, do_pin, ephemeral, error, events::PinEvent, gate, jump_link,
+    locale, metrics, pinlog, pins::PinApi, proxy, time, Context, DEFER_EPHEMERAL,
+};
+
+/// Raised whenever a field of the export changes its meaning
+const FORMAT_VERSION: u32 = 1;
+/// Largest file `/import-pins` downloads, an export of 50 long pins is far smaller
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+/// Most failed pins linked in the summary, so it fits into one message
+const MAX_FAILED_LINKS: usize = 10;
+
+/// The pins of a channel as written by `/export-pins` and read by `/import-pins`
+#[derive(Serialize, Deserialize)]
+struct Export {
+    version: u32,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    /// Unix timestamp in seconds
+    exported_at: u64,
+    /// Newest pin first, like Discord lists them
+    pins: Vec<ExportedPin>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedPin {
+    message_id: Id<MessageMarker>,
+    author_id: Id<UserMarker>,
+    content: String,
+    /// Whether the message has attachments, embeds or stickers, which aren't exported
+    #[serde(default)]
+    extras: bool,
+    /// When the message was sent, as a unix timestamp in seconds
+    timestamp: u64,
+    /// Only for people reading the file, the ids are used on import
+    #[serde(default, skip_deserializing)]
+    link: String,
+}
+
+impl Export {
+    fn new(guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>, pins: &[Message]) -> Self {
+        let pins = pins
+            .iter()
+            .map(|message| ExportedPin {
+                message_id: message.id,
+                author_id: message.author.id,
+                content: message.content.clone(),
+                extras: !message.attachments.is_empty()
+                    || !message.embeds.is_empty()
+                    || !message.sticker_items.is_empty(),
+                timestamp: message.timestamp.as_secs().max(0) as u64,
+                link: jump_link(guild_id, channel_id, message.id),
+            })
+            .collect();
+        Self {
+            version: FORMAT_VERSION,
+            guild_id,
+            channel_id,
+            exported_at: time::now(),
+            pins,
+        }
+    }
+
+    /// Read the file, with an error that tells the user what is wrong with it
+    fn parse(file: &[u8]) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+
+        // Checked first, so a newer export doesn't fail on a field this version doesn't know
+        let Version { version } = serde_json::from_slice(file)
+            .context("The file is not a pin export, it has no format version")?;
+        if version != FORMAT_VERSION {
+            bail!("The export has format version {version}, but I can only read version {FORMAT_VERSION}");
+        }
+        let export: Self = serde_json::from_slice(file).context("The export is damaged")?;
+
+        if export.pins.is_empty() {
+            bail!("The export has no pins");
+        }
+        if export.pins.len() > bulk::MAX_PINS {
+            bail!(
+                "The export has {} pins, but a channel can only hold {}",
+                export.pins.len(),
+                bulk::MAX_PINS
+            );
+        }
+        let mut seen = HashSet::new();
+        if let Some(pin) = export.pins.iter().find(|pin| !seen.insert(pin.message_id)) {
+            bail!("The export lists message {} twice", pin.message_id);
+        }
+        Ok(export)
+    }
+}
+
+/// Handle `/export-pins`, which attaches a file with every pin of this channel
+pub async fn export_pins(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            return Err(e);
+        }
+    };
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+    if pins.is_empty() {
+        request
+            .content("There are no pinned messages in this channel.")?
+            .await?;
+        return Ok(());
+    }
+
+    let export = Export::new(guild_id, channel_id, &pins);
+    let file = Attachment::from_bytes(
+        format!("pins-{channel_id}.json"),
+        serde_json::to_vec_pretty(&export)?,
+        1,
+    );
+    log::info!(
+        "[{}] {} exported {} pins",
+        channel_id,
+        user.name,
+        pins.len()
+    );
+    request
+        .content(&format!(
+            "Exported {} of this channel. Use `/import-pins` with this file to restore them.",
+            confirmation::count(pins.len(), "pin", "pins", locale(&ctx.config, event))
+        ))?
+        .attachments(&[file])?
+        .await?;
+    Ok(())
+}
+
+/// The pins of the export which still need to be restored, and why the others don't
+struct Plan<'a> {
+    /// Oldest first, so the newest pin ends up at the top again
+    pending: Vec<&'a ExportedPin>,
+    already_pinned: usize,
+    /// The oldest pins which don't fit into the channel anymore
+    skipped: usize,
+}
+
+impl<'a> Plan<'a> {
+    fn new(
+        export: &'a Export,
+        channel_id: Id<ChannelMarker>,
+        pinned: &[Id<MessageMarker>],
+    ) -> Self {
+        // Only the messages of this channel can be pinned here, the others get a new reference
+        let (already, pending): (Vec<_>, Vec<_>) = export
+            .pins
+            .iter()
+            .partition(|pin| export.channel_id == channel_id && pinned.contains(&pin.message_id));
+        let room = bulk::MAX_PINS.saturating_sub(pinned.len());
+        let skipped = pending.len().saturating_sub(room);
+        Self {
+            pending: pending.into_iter().take(room).rev().collect(),
+            already_pinned: already.len(),
+            skipped,
+        }
+    }
+}
+
+/// What became of the pins which were restored
+#[derive(Default)]
+struct Restored<'a> {
+    /// Messages of this channel, pinned again
+    repinned: usize,
+    /// References to messages of another channel
+    foreign: usize,
+    /// References to messages of this channel which were deleted
+    deleted: usize,
+    failed: Vec<&'a ExportedPin>,
+}
+
+impl Restored<'_> {
+    fn summary(&self, export: &Export, plan: &Plan, username: &str, locale: &str) -> String {
+        let mut content = format!(
+            "\u{1F4CC} **{username}** restored {} of {} from the export.",
+            self.repinned + self.foreign + self.deleted,
+            confirmation::count(export.pins.len(), "pin", "pins", locale)
+        );
+        if self.foreign > 0 {
+            content.push_str(&format!(
+                "\nPosted a reference to {} of <#{}>, they can't be pinned in this channel.",
+                confirmation::count(self.foreign, "message", "messages", locale),
+                export.channel_id
+            ));
+        }
+        if self.deleted > 0 {
+            content.push_str(&format!(
+                "\nPosted a reference to {} that couldn't be re-pinned here, since they were deleted.",
+                confirmation::count(self.deleted, "message", "messages", locale)
+            ));
+        }
+        if plan.already_pinned > 0 {
+            content.push_str(&format!("\n{} were already pinned.", plan.already_pinned));
+        }
+        if plan.skipped > 0 {
+            content.push_str(&format!(
+                "\nSkipped {}, this channel has no room for them.",
+                confirmation::count(plan.skipped, "older pin", "older pins", locale)
+            ));
+        }
+        if !self.failed.is_empty() {
+            let links: Vec<_> = self
+                .failed
+                .iter()
+                .take(MAX_FAILED_LINKS)
+                .map(|pin| jump_link(export.guild_id, export.channel_id, pin.message_id))
+                .collect();
+            content.push_str(&format!(
+                "\n{} failed: {}",
+                self.failed.len(),
+                links.join(" ")
+            ));
+        }
+        content
+    }
+}
+
+/// Handle `/import-pins`, which restores the pins of an export in this channel
+pub async fn import_pins(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let file = data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::Attachment(id) => data.resolved.as_ref()?.attachments.get(&id),
+        _ => None,
+    });
+    let rejection =
+        gate::rejection(ctx, event, guild_id, channel_id, true).or_else(|| match file {
+            None => Some("Attach a file made by `/export-pins`.".to_owned()),
+            Some(file) if file.size > MAX_FILE_SIZE => {
+                Some("That file is too large to be a pin export.".to_owned())
+            }
+            Some(_) => None,
+        });
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let (Some(file), Some(user)) = (file, event.author()) else {
+        return Ok(());
+    };
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+    let request = client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags());
+
+    let export = match download(ctx, &file.url).await {
+        Ok(bytes) => Export::parse(&bytes),
+        Err(e) => {
+            log::warn!("[{}] Failed to download {}: {}", channel_id, file.url, e);
+            request
+                .content("I couldn't download the file, please try again.")?
+                .await?;
+            return Ok(());
+        }
+    };
+    let export = match export {
+        Ok(export) => export,
+        Err(e) => {
+            request.content(&format!("{e:#}."))?.await?;
+            return Ok(());
+        }
+    };
+
+    let pinned: Vec<Id<MessageMarker>> = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins.iter().map(|message| message.id).collect(),
+        Err(e) => {
+            log::error!("[{}] Failed to load the pins: {}", channel_id, e);
+            request
+                .content(&ctx.config.pin_error_message(error::error_code(&e)))?
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let plan = Plan::new(&export, channel_id, &pinned);
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{username} imported pins in {channel_name}");
+    let mut restored = Restored::default();
+    for (index, pin) in plan.pending.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(bulk::PIN_SPACING).await;
+        }
+
+        // The original is pinned again if it still exists, otherwise its reference is
+        if export.channel_id == channel_id {
+            match do_pin(&ctx.http, channel_id, pin.message_id, true, &reason).await {
+                Ok(()) => {
+                    restored.repinned += 1;
+                    pinlog::record(
+                        ctx,
+                        PinEvent::new(true, guild_id, channel_id, pin.message_id, user.id)
+                            .author(pin.author_id),
+                    )
+                    .await;
+                    continue;
+                }
+                Err(e) if e.code() == Some(error::UNKNOWN_MESSAGE) => {}
+                Err(e) => {
+                    metrics::observe_pin_error(&e, "create_pin");
+                    log::error!("Failed to restore pin due to error: {e}");
+                    restored.failed.push(pin);
+                    continue;
+                }
+            }
+        }
+
+        match reference(ctx, &export, channel_id, pin, &reason).await {
+            Ok(reference_id) => {
+                if export.channel_id == channel_id {
+                    restored.deleted += 1;
+                } else {
+                    restored.foreign += 1;
+                }
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(true, guild_id, channel_id, reference_id, user.id),
+                )
+                .await;
+            }
+            Err(e) => {
+                log::error!("Failed to post the reference of an imported pin: {e}");
+                restored.failed.push(pin);
+            }
+        }
+    }
+
+    let content = restored.summary(&export, &plan, username, locale(&ctx.config, event));
+    log::info!("[{}] {}", channel_id, content);
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Post and pin a reference to the exported pin, for a message which can't be pinned here
+async fn reference(
+    ctx: &Context,
+    export: &Export,
+    channel_id: Id<ChannelMarker>,
+    pin: &ExportedPin,
+    reason: &str,
+) -> Result<Id<MessageMarker>> {
+    let button = row!(link!(
+        "Original",
+        jump_link(export.guild_id, export.channel_id, pin.message_id)
+    ));
+    let content = copy::quoted_copy(pin.author_id, &pin.content, pin.extras, export.channel_id);
+    let reference = match ctx
+        .http
+        .create_message(channel_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await
+    {
+        Ok(response) => response.model().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "create_message");
+            return Err(e.into());
+        }
+    };
+    if let Err(e) = do_pin(&ctx.http, channel_id, reference.id, true, reason).await {
+        metrics::observe_pin_error(&e, "create_pin");
+        return Err(e.into());
+    }
+    Ok(reference.id)
+}
+
+/// Fetch the attached file from the Discord CDN
+async fn download(ctx: &Context, url: &str) -> Result<Vec<u8>> {
+    let client = proxy::client(ctx.config.proxy()?);
+    let response = client.get(url.parse()?).await?;
+    if !response.status().is_success() {
+        bail!("The CDN responded with {}", response.status());
+    }
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(body.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pins;
+
+    fn export(channel_id: u64, message_ids: &[u64]) -> Export {
+        let pins: Vec<_> = message_ids
+            .iter()
+            .map(|id| pins::message(channel_id, *id))
+            .collect();
+        Export::new(Id::new(1), Id::new(channel_id), &pins)
+    }
+
+    #[test]
+    fn exports_list_the_pins_newest_first() {
+        let pins = [pins::message(2, 20), pins::message(2, 10)];
+        let export = serde_json::to_value(Export::new(Id::new(1), Id::new(2), &pins)).unwrap();
+        assert_eq!(export["version"], FORMAT_VERSION);
+        assert_eq!(export["channel_id"], "2");
+        let ids: Vec<_> = export["pins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|pin| pin["message_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["20", "10"]);
+        assert_eq!(
+            export["pins"][0]["link"],
+            "https://discord.com/channels/1/2/20"
+        );
+    }
+
+    #[test]
+    fn exports_can_be_imported() {
+        let file = serde_json::to_vec(&export(2, &[20, 10])).unwrap();
+        let export = Export::parse(&file).unwrap();
+        assert_eq!(export.channel_id, Id::new(2));
+        let ids: Vec<_> = export.pins.iter().map(|pin| pin.message_id.get()).collect();
+        assert_eq!(ids, [20, 10]);
+        assert_eq!(export.pins[0].author_id, Id::new(1));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let error = |file: &str| match Export::parse(file.as_bytes()) {
+            Ok(_) => panic!("{file} was accepted"),
+            Err(e) => format!("{e:#}"),
+        };
+
+        assert!(error("not json").contains("not a pin export"));
+        assert!(error(r#"{"pins": []}"#).contains("not a pin export"));
+        assert!(error(r#"{"version": 2}"#).contains("version 2"));
+        assert!(error(r#"{"version": 1, "pins": []}"#).contains("damaged"));
+
+        let mut value = serde_json::to_value(export(2, &[10])).unwrap();
+        value["pins"] = serde_json::json!([]);
+        assert!(error(&value.to_string()).contains("no pins"));
+
+        let duplicated = serde_json::to_string(&export(2, &[10, 10])).unwrap();
+        assert!(error(&duplicated).contains("message 10 twice"));
+
+        let ids: Vec<_> = (1..=51).collect();
+        let full = serde_json::to_string(&export(2, &ids)).unwrap();
+        assert!(error(&full).contains("51 pins"));
+    }
+
+    #[test]
+    fn restores_the_newest_pins_that_fit() {
+        let export = export(2, &[40, 30, 20, 10]);
+
+        let plan = Plan::new(&export, Id::new(2), &[Id::new(30)]);
+        let pending: Vec<_> = plan
+            .pending
+            .iter()
+            .map(|pin| pin.message_id.get())
+            .collect();
+        assert_eq!(pending, [10, 20, 40]);
+        assert_eq!((plan.already_pinned, plan.skipped), (1, 0));
+
+        let pinned: Vec<_> = (100..148).map(Id::new).collect();
+        let plan = Plan::new(&export, Id::new(2), &pinned);
+        let pending: Vec<_> = plan
+            .pending
+            .iter()
+            .map(|pin| pin.message_id.get())
+            .collect();
+        assert_eq!(pending, [30, 40]);
+        assert_eq!((plan.already_pinned, plan.skipped), (0, 2));
+
+        // Pins of another channel are never pinned here already
+        let plan = Plan::new(&export, Id::new(3), &[Id::new(30)]);
+        assert_eq!((plan.pending.len(), plan.already_pinned), (4, 0));
+    }
+
+    #[test]
+    fn references_are_reported_apart_from_repins() {
+        let export = export(2, &[30, 20, 10]);
+        let plan = Plan::new(&export, Id::new(2), &[]);
+        let restored = Restored {
+            repinned: 1,
+            deleted: 1,
+            failed: vec![&export.pins[0]],
+            ..Restored::default()
+        };
+        let summary = restored.summary(&export, &plan, "someone", "en-US");
+        assert!(summary.contains("restored 2 of 3 pins"));
+        assert!(summary.contains("reference to 1 message that couldn't be re-pinned here"));
+        assert!(!summary.contains("of <#2>"));
+        assert!(summary.contains("1 failed: https://discord.com/channels/1/2/30"));
+
+        let plan = Plan::new(&export, Id::new(3), &[]);
+        let restored = Restored {
+            foreign: 3,
+            ..Restored::default()
+        };
+        let summary = restored.summary(&export, &plan, "someone", "en-US");
+        assert!(summary.contains("reference to 3 messages of <#2>"));
+        assert!(!summary.contains("re-pinned"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing as log;
+use twilight_model::channel::{Channel, ChannelType};
+
+use crate::{do_pin, error, events::PinEvent, metrics, pinlog, Context};
+
+/// The starter message can arrive a moment after the thread, so a missing one is tried again
+const STARTER_DELAY: Duration = Duration::from_secs(2);
+
+/// Pin the starter message of a new forum post if it carries one of the configured tags
+pub async fn handle(ctx: &Context, thread: &Channel) -> Result<()> {
+    // ThreadCreate is also sent when we are added to an existing thread
+    if thread.newly_created != Some(true) {
+        return Ok(());
+    }
+    let (Some(guild_id), Some(parent_id), Some(applied)) = (
+        thread.guild_id,
+        thread.parent_id,
+        thread.applied_tags.as_ref(),
+    ) else {
+        return Ok(());
+    };
+    if applied.is_empty() || !ctx.config.is_guild_allowed(guild_id) {
+        return Ok(());
+    }
+
+    // Tags can be renamed at any time, so their names are looked up for every post
+    let forum = ctx.http.channel(parent_id).await?.model().await?;
+    if forum.kind != ChannelType::GuildForum {
+        return Ok(());
+    }
+    let tag = forum
+        .available_tags
+        .iter()
+        .flatten()
+        .filter(|tag| applied.contains(&tag.id))
+        .find(|tag| {
+            ctx.config
+                .forum_pin_tags
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&tag.name))
+        });
+    let Some(tag) = tag else {
+        return Ok(());
+    };
+
+    // The starter message of a forum post has the id of the thread
+    let message_id = thread.id.cast();
+    let reason = format!("Forum post tagged {}", tag.name);
+    let mut result = do_pin(&ctx.http, thread.id, message_id, true, &reason).await;
+    if result
+        .as_ref()
+        .is_err_and(|e| e.code() == Some(error::UNKNOWN_MESSAGE))
+    {
+        tokio::time::sleep(STARTER_DELAY).await;
+        result = do_pin(&ctx.http, thread.id, message_id, true, &reason).await;
+    }
+    if let Err(e) = result {
+        metrics::observe_pin_error(&e, "create_pin");
+        return Err(e.into());
+    }
+
+    log::info!(
+        "[{}] Pinned forum post {} for its tag {}",
+        parent_id,
+        thread.id,
+        tag.name
+    );
+    // The tag pinned it, so the bot shows up as the one who did
+    if let Some(bot_id) = ctx.store.bot_user_id() {
+        let mut event = PinEvent::new(true, guild_id, thread.id, message_id, bot_id);
+        if let Some(owner_id) = thread.owner_id {
+            event = event.author(owner_id);
+        }
+        pinlog::record_with_reason(ctx, event, Some(reason)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    id::{marker::ChannelMarker, Id},
+};
+
+use crate::{ephemeral, label, time, Context};
+
+/// Handle `/pin-freeze`, which stops every pin and unpin in this channel for a while
+pub async fn freeze(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let duration = data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::String(ref value) => time::parse_duration(value),
+        _ => None,
+    });
+
+    let response = match (duration, event.author()) {
+        // Server admins can hand the command to anyone, which must not bypass this
+        _ if !label::can_manage_messages(event) => {
+            ephemeral("You need the **Manage Messages** permission to do this.")
+        }
+        (Some(duration), Some(user)) => {
+            let now = time::now();
+            let until = now.saturating_add(duration.as_secs());
+            ctx.store
+                .set_pin_freeze(channel_id, Some(until), now)
+                .await?;
+            log::info!(
+                "[{}] {} froze the pins until {}",
+                channel_id,
+                user.name,
+                until
+            );
+            ephemeral(format!(
+                "Pins in this channel are frozen until <t:{until}:f>. Use `/pin-unfreeze` to lift it early."
+            ))
+        }
+        _ => ephemeral(
+            "I couldn't understand that duration. Try something like `30m`, `2h` or `1d`.",
+        ),
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Handle `/pin-unfreeze`, which lifts the freeze of this channel early
+pub async fn unfreeze(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    if !label::can_manage_messages(event) {
+        let response = ephemeral("You need the **Manage Messages** permission to do this.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    let frozen = frozen_until(ctx, channel_id).is_some();
+    ctx.store
+        .set_pin_freeze(channel_id, None, time::now())
+        .await?;
+
+    let response = if frozen {
+        if let Some(user) = event.author() {
+            log::info!("[{}] {} lifted the pin freeze", channel_id, user.name);
+        }
+        ephemeral("Pins in this channel are no longer frozen.")
+    } else {
+        ephemeral("Pins in this channel aren't frozen.")
+    };
+    client
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// When the freeze of the channel ends, if it is frozen right now
+pub fn frozen_until(ctx: &Context, channel_id: Id<ChannelMarker>) -> Option<u64> {
+    ctx.store
+        .pin_freeze(channel_id)
+        .filter(|until| *until > time::now())
+}
+
+/// What to tell users who try to pin or unpin in the channel, if it is frozen right now.
+///
+/// Every path which pins or unpins for a user checks this, automatic ones use [`frozen_until`].
+pub fn rejection(ctx: &Context, channel_id: Id<ChannelMarker>) -> Option<String> {
+    frozen_until(ctx, channel_id).map(|until| {
+        format!("Pinning is temporarily frozen in <#{channel_id}> (until <t:{until}:f>).")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn frozen_channels_reject_pins_until_the_freeze_ends() {
+        let ctx = crate::tests::context(serde_json::json!({ "token": "" })).await;
+        let (frozen, other) = (Id::new(1), Id::new(2));
+        let now = time::now();
+        ctx.store
+            .set_pin_freeze(frozen, Some(now + 60), now)
+            .await
+            .unwrap();
+
+        let rejection = super::rejection(&ctx, frozen).unwrap();
+        assert!(rejection.contains("<#1>"));
+        assert!(rejection.contains(&format!("<t:{}:f>", now + 60)));
+        assert_eq!(super::rejection(&ctx, other), None);
+
+        // Freezes which already ended are ignored, even before they are cleaned up
+        ctx.store
+            .set_pin_freeze(frozen, Some(now - 1), now - 2)
+            .await
+            .unwrap();
+        assert_eq!(super::rejection(&ctx, frozen), None);
+    }
+}
@@ -0,0 +1,110 @@
+use twilight_model::{
+    application::interaction::Interaction,
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{freeze, grant, pinner, Context};
+
+/// Why the user of the interaction can't pin or unpin in the channel, if they can't.
+///
+/// Every path which pins or unpins for a user goes through this, so none of them can skip a
+/// check. Our permissions are only known in the channel of the interaction, the callers check
+/// them in other channels.
+pub fn rejection(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    pin: bool,
+) -> Option<String> {
+    let roles = event
+        .member
+        .as_ref()
+        .map_or(&[][..], |member| &member.roles);
+    // The command might still be registered from before it was disabled
+    let enabled = if pin {
+        ctx.config.enable_pin
+    } else {
+        ctx.config.enable_unpin
+    };
+    let in_channel = event
+        .channel
+        .as_ref()
+        .is_some_and(|channel| channel.id == channel_id);
+
+    if !enabled || !ctx.config.is_guild_allowed(guild_id) {
+        return Some("This command is disabled.".to_owned());
+    }
+    let Some(user) = pinner(event) else {
+        let action = if pin { "pin" } else { "unpin" };
+        return Some(format!("Only users can {action} messages."));
+    };
+    // Throwaway accounts shouldn't be able to mess with the pins
+    if ctx.config.is_account_too_new(user.id) {
+        Some("Your account is too new to pin messages here.".to_owned())
+    } else if let Some(role_id) = grant::missing_role(ctx, guild_id, Some(user.id), pin, roles) {
+        Some(format!("You need the <@&{role_id}> role to do this."))
+    } else if let Some(content) = missing_permission(event).filter(|_| in_channel) {
+        Some(content.to_owned())
+    } else {
+        freeze::rejection(ctx, channel_id)
+    }
+}
+
+/// Fail right away instead of deferring a response which can only report the error
+fn missing_permission(event: &Interaction) -> Option<&'static str> {
+    let perms = event.app_permissions?;
+    if !perms.contains(Permissions::VIEW_CHANNEL) {
+        Some("I can't see this channel \u{2014} check my permissions.")
+    } else if !perms.contains(Permissions::MANAGE_MESSAGES) {
+        Some("I need the **Manage Messages** permission in this channel.")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{command, context, created_at},
+        time,
+    };
+
+    #[tokio::test]
+    async fn every_check_applies_to_pins_and_unpins() {
+        let ctx = context(serde_json::json!({ "token": "", "min_account_age_days": 7 })).await;
+        let (guild_id, channel_id) = (Id::new(4), Id::new(5));
+        let all = Permissions::VIEW_CHANNEL | Permissions::MANAGE_MESSAGES;
+        let old = created_at(time::now() - 30 * 24 * 60 * 60);
+        let new = created_at(time::now() - 60 * 60);
+
+        for pin in [true, false] {
+            let event = command(old, all);
+            assert_eq!(rejection(&ctx, &event, guild_id, channel_id, pin), None);
+
+            let event = command(new, all);
+            let content = rejection(&ctx, &event, guild_id, channel_id, pin).unwrap();
+            assert!(content.contains("too new"));
+
+            let event = command(old, Permissions::VIEW_CHANNEL);
+            let content = rejection(&ctx, &event, guild_id, channel_id, pin).unwrap();
+            assert!(content.contains("Manage Messages"));
+            // Our permissions in other channels aren't known from the interaction
+            assert_eq!(rejection(&ctx, &event, guild_id, Id::new(6), pin), None);
+        }
+
+        let now = time::now();
+        ctx.store
+            .set_pin_freeze(channel_id, Some(now + 60), now)
+            .await
+            .unwrap();
+        let event = command(old, all);
+        let content = rejection(&ctx, &event, guild_id, channel_id, true).unwrap();
+        assert!(content.contains("frozen"));
+    }
+}
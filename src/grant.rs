@@ -0,0 +1,145 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    id::{
+        marker::{GuildMarker, RoleMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{ephemeral, lock, time, Context};
+
+/// Handle `/grant-pin`, which lets a member pin without the required role for a while
+pub async fn grant_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let mut user_id = None;
+    let mut duration = None;
+    for option in &data.options {
+        match (option.name.as_str(), &option.value) {
+            ("user", CommandOptionValue::User(id)) => user_id = Some(*id),
+            ("duration", CommandOptionValue::String(value)) => {
+                duration = time::parse_duration(value);
+            }
+            _ => {}
+        }
+    }
+
+    let response = match (user_id, duration, event.author()) {
+        _ if !lock::can_manage_guild(event) => {
+            ephemeral("You need the **Manage Server** permission to do this.")
+        }
+        (Some(user_id), Some(duration), Some(moderator)) => {
+            let now = time::now();
+            let until = now.saturating_add(duration.as_secs());
+            ctx.store
+                .set_pin_grant(guild_id, user_id, Some(until), now)
+                .await?;
+            log::info!(
+                "[{}] {} allowed {} to pin until {}",
+                guild_id,
+                moderator.name,
+                user_id,
+                until
+            );
+
+            let mut content = format!(
+                "<@{user_id}> can pin messages without the required role until <t:{until}:f>. \
+                 Use `/revoke-pin` to end it early."
+            );
+            if ctx.store.guild(guild_id).role_for(true).is_none() {
+                content.push_str("\nNo role is required to pin in this server right now.");
+            }
+            if !ctx.store.is_persistent() {
+                content
+                    .push_str("\nNo database is configured, so this resets when the bot restarts.");
+            }
+            ephemeral(content)
+        }
+        _ => ephemeral(
+            "I couldn't understand that duration. Try something like `30m`, `2h` or `1d`.",
+        ),
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Handle `/revoke-pin`, which ends the grant of a member early
+pub async fn revoke_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let user_id = data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::User(id) => Some(id),
+        _ => None,
+    });
+
+    let response = match user_id {
+        _ if !lock::can_manage_guild(event) => {
+            ephemeral("You need the **Manage Server** permission to do this.")
+        }
+        None => ephemeral("Pick the member whose grant should end."),
+        Some(user_id) => {
+            let granted = is_granted(ctx, guild_id, user_id);
+            ctx.store
+                .set_pin_grant(guild_id, user_id, None, time::now())
+                .await?;
+            if granted {
+                if let Some(moderator) = event.author() {
+                    log::info!(
+                        "[{}] {} revoked the pin grant of {}",
+                        guild_id,
+                        moderator.name,
+                        user_id
+                    );
+                }
+                ephemeral(format!(
+                    "<@{user_id}> needs the required role to pin again."
+                ))
+            } else {
+                ephemeral(format!("<@{user_id}> has no pin grant."))
+            }
+        }
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Whether the member can pin without the required role right now
+pub fn is_granted(ctx: &Context, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> bool {
+    ctx.store
+        .pin_grant(guild_id, user_id)
+        .is_some_and(|until| until > time::now())
+}
+
+/// The required role, if the member has neither the role nor a grant for pinning.
+///
+/// Grants only cover pinning, unpinning always needs the role.
+pub fn missing_role(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    user_id: Option<Id<UserMarker>>,
+    pin: bool,
+    roles: &[Id<RoleMarker>],
+) -> Option<Id<RoleMarker>> {
+    let role_id = ctx.store.guild(guild_id).missing_role(pin, roles)?;
+    let granted = pin && user_id.is_some_and(|user_id| is_granted(ctx, guild_id, user_id));
+    (!granted).then_some(role_id)
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use twilight_model::{
+    application::{command::CommandType, interaction::Interaction},
+    guild::Permissions,
+    id::{marker::GuildMarker, Id},
+};
+
+use crate::{commands, ephemeral, Context};
+
+/// Handle `/pin-help`, which lists every registered command and what it needs in this channel
+pub async fn help(ctx: &Context, event: &Interaction, guild_id: Id<GuildMarker>) -> Result<()> {
+    let settings = ctx.store.guild(guild_id);
+    // Unknown permissions are assumed to be fine, the command reports them when it is used
+    let bot_has = |permission| {
+        event
+            .app_permissions
+            .is_none_or(|perms| perms.contains(permission))
+    };
+
+    let mut lines =
+        vec!["Message commands are found by right-clicking a message, under **Apps**.".to_owned()];
+    for command in commands::definitions(&ctx.config) {
+        let name = command.name.as_str();
+        let summary = match name {
+            commands::PIN => "Pin the message",
+            commands::UNPIN => "Unpin the message",
+            commands::SLASH_PIN => "Pin a message of this channel by its link",
+            commands::SLASH_UNPIN => "Unpin a message of this channel by its link",
+            commands::TEMP_PIN => "Pin the message for a while, it is unpinned automatically",
+            commands::WHO_PINNED => "Look up who pinned the message in the audit log",
+            commands::SHOW_PIN => "Show the confirmation of a pinned message again",
+            commands::BOOKMARK => "Send the message to your DMs, without pinning it",
+            commands::LOCK_PIN => "Lock the pin, so only server managers can unpin it",
+            commands::UNLOCK_PIN => "Unlock the pin again",
+            commands::PREVIEW_PIN => "Show what the confirmation looks like, without pinning",
+            commands::LABEL_PIN => "Give the pin a label, which `/pins` shows instead of its text",
+            commands::CATEGORIZE_PIN => "Put the pin in a category, which `/pins` can filter by",
+            commands::LIST_PINS => "List the pinned messages of this channel",
+            commands::PIN_COUNT => "Show how many messages are pinned in this channel",
+            commands::MY_PINS => "List your pinned messages in this server",
+            commands::PIN_SNAPSHOT => "Save the current pins of this channel under a name",
+            commands::PIN_DIFF => "Show which pins changed since a saved snapshot",
+            commands::EXPORT_PINS => "Save the pins of this channel to a file",
+            commands::IMPORT_PINS => "Restore the pins of an exported file in this channel",
+            commands::COPY_PIN => "Copy a pinned message to another channel, or move the pin",
+            commands::PIN_TRANSFER => "Repost and pin every pin of this channel in another channel",
+            commands::PIN_SELECT => "Pick one of the recent messages of this channel to pin",
+            commands::BULK_PIN => "Pin several messages of this channel at once",
+            commands::PIN_REACTED => "Pin the recent messages of this channel with a reaction",
+            commands::SCHEDULE_PIN => "Pin a message of this channel at a later time",
+            commands::RANDOM_PIN => "Show a random pinned message of this channel",
+            commands::UNPIN_OLDER_THAN => "Unpin every message of this channel older than an age",
+            commands::OLDEST_PIN => {
+                "Show the oldest pin of this channel, with a button to unpin it"
+            }
+            commands::PIN_FREEZE => "Stop every pin and unpin in this channel for a while",
+            commands::PIN_UNFREEZE => "Lift the pin freeze of this channel early",
+            commands::PIN_LOG => "Show the recent pins and unpins in this server",
+            commands::PIN_LOG_PURGE => "Remove old entries from the pin log of this server",
+            commands::PIN_TRENDS => "Chart the pins and unpins in this server over time",
+            commands::SELF_TEST => "Check that I can pin messages in this channel",
+            commands::EFFECTIVE_CONFIG => "Show the pin settings that apply in this channel",
+            commands::SET_ROLE => "Set the role required to pin messages",
+            commands::UNPIN_APPROVAL => "Require a second moderator to approve every unpin",
+            commands::GRANT_PIN => "Let a member pin without the required role for a while",
+            commands::REVOKE_PIN => "End the pin grant of a member early",
+            _ => continue,
+        };
+        let name = match command.kind {
+            CommandType::ChatInput => format!("`/{name}`"),
+            _ => format!("**{name}**"),
+        };
+
+        let mut requirements = Vec::new();
+        match command.name.as_str() {
+            commands::SET_ROLE
+            | commands::UNPIN_APPROVAL
+            | commands::GRANT_PIN
+            | commands::REVOKE_PIN
+            | commands::PIN_LOG
+            | commands::PIN_LOG_PURGE
+            | commands::PIN_TRENDS
+            | commands::LOCK_PIN
+            | commands::UNLOCK_PIN
+            | commands::PREVIEW_PIN
+            | commands::SELF_TEST
+            | commands::EFFECTIVE_CONFIG => {
+                requirements.push("needs **Manage Server**".to_owned());
+            }
+            commands::PIN_TRANSFER => {
+                requirements.push("needs **Manage Server**".to_owned());
+                if let Some(role_id) = settings.role_for(true) {
+                    requirements.push(format!("needs the <@&{role_id}> role"));
+                }
+            }
+            commands::BULK_PIN
+            | commands::PIN_REACTED
+            | commands::SCHEDULE_PIN
+            | commands::IMPORT_PINS
+            | commands::UNPIN_OLDER_THAN => {
+                requirements.push("needs **Manage Messages**".to_owned());
+                let pin = command.name != commands::UNPIN_OLDER_THAN;
+                if let Some(role_id) = settings.role_for(pin) {
+                    requirements.push(format!("needs the <@&{role_id}> role"));
+                }
+                if !bot_has(Permissions::MANAGE_MESSAGES) {
+                    requirements.push("I'm missing **Manage Messages** here".to_owned());
+                }
+            }
+            commands::LABEL_PIN
+            | commands::CATEGORIZE_PIN
+            | commands::PIN_SNAPSHOT
+            | commands::PIN_DIFF
+            | commands::EXPORT_PINS
+            | commands::PIN_FREEZE
+            | commands::PIN_UNFREEZE => {
+                requirements.push("needs **Manage Messages**".to_owned());
+            }
+            commands::WHO_PINNED if !bot_has(Permissions::VIEW_AUDIT_LOG) => {
+                requirements.push("I'm missing **View Audit Log** here".to_owned());
+            }
+            commands::WHO_PINNED
+            | commands::RANDOM_PIN
+            | commands::SHOW_PIN
+            | commands::BOOKMARK
+            | commands::LIST_PINS
+            | commands::PIN_COUNT
+            | commands::MY_PINS => {}
+            _ => {
+                let pin = !matches!(
+                    command.name.as_str(),
+                    commands::UNPIN | commands::SLASH_UNPIN
+                );
+                if let Some(role_id) = settings.role_for(pin) {
+                    requirements.push(format!("needs the <@&{role_id}> role"));
+                }
+                if !bot_has(Permissions::MANAGE_MESSAGES) {
+                    requirements.push("I'm missing **Manage Messages** here".to_owned());
+                }
+            }
+        }
+
+        if requirements.is_empty() {
+            lines.push(format!("{name}: {summary}"));
+        } else {
+            lines.push(format!("{name}: {summary} ({})", requirements.join(", ")));
+        }
+    }
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &ephemeral(lines.join("\n")))
+        .await?;
+    Ok(())
+}
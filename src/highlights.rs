@@ -0,0 +1,81 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    channel::{
+        message::component::{ActionRow, Button, ButtonStyle},
+        Message,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{confirmation, error, jump_link, metrics, Context};
+
+/// Longest excerpt of the message shown in the highlight
+const EXCERPT_LENGTH: usize = 300;
+
+/// Repost a compact version of a newly pinned message to the highlights channel of the guild.
+///
+/// The pin itself already happened, so failures are only logged.
+pub async fn post(ctx: &Context, guild_id: Id<GuildMarker>, message: &Message) {
+    let Some(&target_id) = ctx.config.highlights_channels.get(&guild_id) else {
+        return;
+    };
+    // The pins of the highlights channel are right there already
+    if target_id == message.channel_id {
+        return;
+    }
+
+    if let Err(e) = send(ctx, guild_id, target_id, message).await {
+        if let Some(e) = e.downcast_ref() {
+            metrics::observe_http_error(e, "highlights");
+        }
+        match error::error_code(&e) {
+            Some(error::MISSING_ACCESS | error::MISSING_PERMISSIONS) => log::warn!(
+                "[{}] Missing permissions to post highlights in {}",
+                message.channel_id,
+                target_id
+            ),
+            _ => log::error!("Failed to post highlight: {e}"),
+        }
+    }
+}
+
+/// The start of the message as a block quote on its own line, for reposts of the pin
+pub fn quote(message: &Message) -> String {
+    if message.content.is_empty() {
+        return "\n*Open the message to see it.*".to_owned();
+    }
+    let mut excerpt: String = message.content.chars().take(EXCERPT_LENGTH).collect();
+    if excerpt.len() < message.content.len() {
+        excerpt.push('\u{2026}');
+    }
+    format!("\n>>> {excerpt}")
+}
+
+async fn send(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    target_id: Id<ChannelMarker>,
+    message: &Message,
+) -> Result<()> {
+    let mut content = format!(
+        "\u{2B50} Pinned in <#{}>, by <@{}>:",
+        message.channel_id, message.author.id
+    );
+    content.push_str(&quote(message));
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, message.channel_id, message.id)
+    ));
+
+    ctx.http
+        .create_message(target_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
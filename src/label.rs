@@ -0,0 +1,300 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        modal::ModalInteractionData,
+        Interaction,
+    },
+    channel::{
+        message::{
+            component::{ActionRow, TextInput, TextInputStyle},
+            MessageFlags,
+        },
+        Message,
+    },
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    category, confirmation, ephemeral, jump_link, locale, metrics, pins::PinApi, Context,
+    DEFER_EPHEMERAL,
+};
+
+/// Prefix of the modal custom id, followed by the message id
+pub const MODAL_PREFIX: &str = "label-pin";
+const LABEL_INPUT: &str = "label";
+const MAX_LABEL_LENGTH: u16 = 50;
+/// Longest excerpt shown for pins without a label
+const EXCERPT_LENGTH: usize = 40;
+/// Discord rejects messages with more characters than this
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// Whether the member who used the interaction can manage messages
+pub fn can_manage_messages(event: &Interaction) -> bool {
+    event
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|perms| perms.contains(Permissions::MANAGE_MESSAGES))
+}
+
+/// Handle "Label Pin", which asks for the label of a pinned message
+pub async fn prompt(ctx: &Context, event: &Interaction, message: &Message) -> Result<()> {
+    let response = if !can_manage_messages(event) {
+        ephemeral("You need the **Manage Messages** permission to do this.")
+    } else if !message.pinned {
+        ephemeral("This message isn't pinned.")
+    } else {
+        let input = TextInput {
+            custom_id: LABEL_INPUT.to_owned(),
+            label: "Label".to_owned(),
+            max_length: Some(MAX_LABEL_LENGTH),
+            min_length: None,
+            placeholder: Some("Rules, FAQ, leave empty to remove it".to_owned()),
+            required: Some(false),
+            style: TextInputStyle::Short,
+            // Relabeling starts from the current label
+            value: ctx.store.label(message.id),
+        };
+        InteractionResponse {
+            kind: InteractionResponseType::Modal,
+            data: Some(InteractionResponseData {
+                custom_id: Some(format!("{MODAL_PREFIX}:{}", message.id)),
+                title: Some("Label pin".to_owned()),
+                components: Some(row!(input).into()),
+                ..Default::default()
+            }),
+        }
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Store the label once the user submitted it
+pub async fn submit(ctx: &Context, event: &Interaction, data: &ModalInteractionData) -> Result<()> {
+    let Some(message_id) = data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|it| it.parse().ok())
+    else {
+        return Ok(());
+    };
+
+    let label = data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find(|component| component.custom_id == LABEL_INPUT)
+        .and_then(|component| component.value.as_deref())
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+        .map(ToOwned::to_owned);
+
+    let content = if !can_manage_messages(event) {
+        "You need the **Manage Messages** permission to do this.".to_owned()
+    } else {
+        let mut content = match label {
+            Some(ref label) => format!("Labeled the pin as **{label}**."),
+            None => "Removed the label of the pin.".to_owned(),
+        };
+        ctx.store.set_label(message_id, label).await?;
+        if !ctx.store.is_persistent() {
+            content.push_str("\nNo database is configured, so this resets when the bot restarts.");
+        }
+        content
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &ephemeral(content))
+        .await?;
+    Ok(())
+}
+
+/// Handle `/pins`, which lists the pinned messages of the channel with their labels, optionally
+/// only those in one category
+pub async fn list_pins(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    // `Some(None)` lists the uncategorized pins
+    let filter = match data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::String(ref value) if option.name == "category" => Some(value),
+        _ => None,
+    }) {
+        None => None,
+        Some(name) if name == category::UNCATEGORIZED => Some(None),
+        Some(name) => match category::find(ctx, name) {
+            Some(name) => Some(Some(name)),
+            // The choices could be from before the config changed
+            None => {
+                let response = ephemeral(format!("There is no category called **{name}**."));
+                client
+                    .create_response(event.id, &event.token, &response)
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            return Err(e);
+        }
+    };
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+    if pins.is_empty() {
+        request
+            .content("There are no pinned messages in this channel yet.")?
+            .await?;
+        return Ok(());
+    }
+
+    let pins: Vec<_> = pins
+        .into_iter()
+        .map(|message| (ctx.store.category(message.id), message))
+        .filter(|(category, _)| {
+            filter.is_none_or(|filter| match (filter, category) {
+                (Some(filter), Some(category)) => category.eq_ignore_ascii_case(filter),
+                (None, None) => true,
+                _ => false,
+            })
+        })
+        .collect();
+    let heading = match filter {
+        Some(Some(name)) => format!(" in {name}"),
+        Some(None) => " without a category".to_owned(),
+        None => String::new(),
+    };
+    if pins.is_empty() {
+        request
+            .content(&format!(
+                "There are no pinned messages{heading} in this channel."
+            ))?
+            .await?;
+        return Ok(());
+    }
+
+    let locale = locale(&ctx.config, event);
+    let mut content = format!(
+        "**{}{heading}**",
+        confirmation::count(pins.len(), "pinned message", "pinned messages", locale)
+    );
+    for (index, (category, message)) in pins.iter().enumerate() {
+        let mut line = format!(
+            "\n{}. [{}]({}) by <@{}>",
+            index + 1,
+            title(ctx, message),
+            jump_link(guild_id, channel_id, message.id),
+            message.author.id
+        );
+        if let (None, Some(category)) = (filter, category) {
+            line.push_str(&format!(" \u{2014} *{category}*"));
+        }
+        // Leave room for the note about the remaining pins
+        if content.chars().count() + line.chars().count() > MAX_CONTENT_LENGTH - 30 {
+            let remaining = confirmation::number(pins.len() - index, locale);
+            content.push_str(&format!("\n*and {remaining} more*"));
+            break;
+        }
+        content.push_str(&line);
+    }
+
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// The label of the pin, or the start of the message if it has none
+pub fn title(ctx: &Context, message: &Message) -> String {
+    if let Some(label) = ctx.store.label(message.id) {
+        return format!("**{}**", link_text(&label));
+    }
+
+    let content = link_text(&message.content);
+    let mut excerpt: String = content.chars().take(EXCERPT_LENGTH).collect();
+    if excerpt.is_empty() {
+        excerpt.push_str("Message");
+    } else if excerpt.len() < content.len() {
+        excerpt.push('\u{2026}');
+    }
+    excerpt
+}
+
+/// Brackets and newlines would break the markdown link
+fn link_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '[' | ']' | '\n' => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lock,
+        tests::{interaction, user},
+    };
+
+    fn member(permissions: Permissions) -> Interaction {
+        interaction(serde_json::json!({
+            "guild_id": "4",
+            "member": {
+                "user": user(3, false),
+                "roles": [],
+                "joined_at": "2024-01-01T00:00:00+00:00",
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+                "permissions": permissions.bits().to_string(),
+            },
+        }))
+    }
+
+    #[test]
+    fn moderators_need_their_own_permission() {
+        let event = member(Permissions::MANAGE_MESSAGES);
+        assert!(can_manage_messages(&event));
+        assert!(!lock::can_manage_guild(&event));
+
+        let event = member(Permissions::MANAGE_GUILD);
+        assert!(lock::can_manage_guild(&event));
+        assert!(!can_manage_messages(&event));
+
+        let event = interaction(serde_json::json!({ "user": user(3, false) }));
+        assert!(!can_manage_messages(&event));
+    }
+}
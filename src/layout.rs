@@ -0,0 +1,82 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use twilight_http::{request::Request, routing::Route};
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::message::{Embed, Message},
+};
+
+use crate::{config::ConfirmationStyle, Context};
+
+/// Message flag of the newer component system, which twilight doesn't know about yet
+const IS_COMPONENTS_V2: u64 = 1 << 15;
+/// Component types of the newer system, next to the buttons of the current one
+const SECTION: u8 = 9;
+const TEXT_DISPLAY: u8 = 10;
+const SEPARATOR: u8 = 14;
+const BUTTON: u8 = 2;
+const LINK_STYLE: u8 = 5;
+
+/// Follow up on the deferred interaction with the confirmation as a rich layout.
+///
+/// The message text sits in a section with the jump link next to it, and the reason or position
+/// goes below a divider. Messages with this layout can't have content or embeds, so the caller
+/// falls back to the classic confirmation if Discord rejects it.
+pub async fn send_rich(
+    ctx: &Context,
+    event: &Interaction,
+    content: &str,
+    embed: &Embed,
+    link: String,
+) -> Result<()> {
+    let text = match ctx.config.confirmation_style {
+        ConfirmationStyle::Text => content.to_owned(),
+        ConfirmationStyle::Embed => embed_text(embed),
+    };
+    let (headline, details) = text.split_once('\n').unwrap_or((&text, ""));
+
+    let mut components = vec![json!({
+        "type": SECTION,
+        "components": [{ "type": TEXT_DISPLAY, "content": headline }],
+        "accessory": { "type": BUTTON, "style": LINK_STYLE, "label": "Message", "url": link },
+    })];
+    if !details.trim().is_empty() {
+        components.push(json!({ "type": SEPARATOR, "divider": true }));
+        components.push(json!({ "type": TEXT_DISPLAY, "content": details.trim() }));
+    }
+    let body = json!({
+        "flags": ctx.config.response_visibility.flags().bits() | IS_COMPONENTS_V2,
+        "allowed_mentions": { "parse": Value::Array(Vec::new()) },
+        "components": components,
+    });
+
+    // Followups are executions of the webhook of the application, like twilight sends them
+    let request = Request::builder(&Route::ExecuteWebhook {
+        thread_id: None,
+        token: &event.token,
+        wait: None,
+        webhook_id: event.application_id.get(),
+    })
+    .use_authorization_token(false)
+    .json(&body)?
+    .build();
+    ctx.http.request::<Message>(request).await?;
+    Ok(())
+}
+
+/// The embed confirmation as markdown, with the title as a heading and the footer in small text
+fn embed_text(embed: &Embed) -> String {
+    let mut text = match embed.title {
+        Some(ref title) => format!("### {title}"),
+        None => String::new(),
+    };
+    if let Some(ref description) = embed.description {
+        text.push('\n');
+        text.push_str(description);
+    }
+    if let Some(ref footer) = embed.footer {
+        text.push_str("\n-# ");
+        text.push_str(&footer.text);
+    }
+    text
+}
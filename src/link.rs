@@ -0,0 +1,224 @@
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, MessageMarker},
+    Id,
+};
+use url::Url;
+
+/// Hosts which serve the Discord web client
+const HOSTS: [&str; 4] = [
+    "discord.com",
+    "ptb.discord.com",
+    "canary.discord.com",
+    "discordapp.com",
+];
+
+/// A message, as referenced by its jump link or ids
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MessageLink {
+    /// Missing for direct messages and references without a link
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Id<MessageMarker>,
+}
+
+impl MessageLink {
+    /// Whether the message is in the channel, which must be part of the guild
+    pub fn is_in(&self, guild_id: Id<GuildMarker>, channel_id: Id<ChannelMarker>) -> bool {
+        self.guild_id.is_none_or(|id| id == guild_id) && self.channel_id == channel_id
+    }
+}
+
+/// Parse a reference to a message, which is one of:
+/// - a link like `https://discord.com/channels/{guild}/{channel}/{message}`, where direct
+///   messages use `@me` instead of a guild id
+/// - `{channel}-{message}`, as copied from the client with shift held down
+///
+/// Links may be wrapped in `<>`, as done to suppress their embed, and have a query or fragment.
+pub fn parse_message_ref(input: &str) -> Option<MessageLink> {
+    let input = input.trim();
+    if let Some((channel_id, message_id)) = input.split_once('-') {
+        if let (Ok(channel_id), Ok(message_id)) = (channel_id.parse(), message_id.parse()) {
+            return Some(MessageLink {
+                guild_id: None,
+                channel_id,
+                message_id,
+            });
+        }
+    }
+
+    let input = input
+        .strip_prefix('<')
+        .and_then(|it| it.strip_suffix('>'))
+        .unwrap_or(input);
+    let url = Url::parse(input).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || !HOSTS.contains(&url.host_str()?) {
+        return None;
+    }
+
+    let mut segments = url.path_segments()?.filter(|it| !it.is_empty());
+    if segments.next()? != "channels" {
+        return None;
+    }
+    let guild_id = match segments.next()? {
+        "@me" => None,
+        id => Some(id.parse().ok()?),
+    };
+    let link = MessageLink {
+        guild_id,
+        channel_id: segments.next()?.parse().ok()?,
+        message_id: segments.next()?.parse().ok()?,
+    };
+
+    segments.next().is_none().then_some(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Xorshift, so the generated inputs are the same on every run
+    struct Generator(u64);
+
+    impl Generator {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+            choices[(self.next() % choices.len() as u64) as usize]
+        }
+
+        fn id(&mut self) -> u64 {
+            self.next() % (u64::MAX - 1) + 1
+        }
+    }
+
+    #[test]
+    fn parses_links_and_ids() {
+        let link = |guild_id: Option<u64>, channel_id, message_id| {
+            Some(MessageLink {
+                guild_id: guild_id.map(Id::new),
+                channel_id: Id::new(channel_id),
+                message_id: Id::new(message_id),
+            })
+        };
+        assert_eq!(
+            parse_message_ref("https://discord.com/channels/1/2/3"),
+            link(Some(1), 2, 3)
+        );
+        assert_eq!(
+            parse_message_ref(" <https://canary.discord.com/channels/@me/2/3?x=1#y> "),
+            link(None, 2, 3)
+        );
+        assert_eq!(parse_message_ref("2-3"), link(None, 2, 3));
+        assert_eq!(
+            parse_message_ref("https://discord.com/channels/1/2/3/4"),
+            None
+        );
+        assert_eq!(
+            parse_message_ref("https://example.com/channels/1/2/3"),
+            None
+        );
+        assert_eq!(parse_message_ref("ftp://discord.com/channels/1/2/3"), None);
+        assert_eq!(
+            parse_message_ref("https://discord.com/channels/0/2/3"),
+            None
+        );
+        assert_eq!(parse_message_ref("0-3"), None);
+        assert_eq!(parse_message_ref(""), None);
+    }
+
+    #[test]
+    fn generated_links_round_trip() {
+        let mut generator = Generator(0x2545_f491_4f6c_dd1d);
+        for _ in 0..10_000 {
+            let (guild_id, channel_id, message_id) =
+                (generator.id(), generator.id(), generator.id());
+            let host = generator.pick(&HOSTS);
+            let scheme = generator.pick(&["http", "https"]);
+            let suffix = generator.pick(&["", "/", "?a=b", "#c", "/?a=b&c=d#e"]);
+            let url = format!("{scheme}://{host}/channels/{guild_id}/{channel_id}/{message_id}");
+            let input = match generator.next() % 3 {
+                0 => format!("{url}{suffix}"),
+                1 => format!("<{url}{suffix}>"),
+                _ => format!("  {url}{suffix}\n"),
+            };
+
+            let expected = MessageLink {
+                guild_id: Some(Id::new(guild_id)),
+                channel_id: Id::new(channel_id),
+                message_id: Id::new(message_id),
+            };
+            assert_eq!(parse_message_ref(&input), Some(expected), "{input}");
+            let ids = format!("{channel_id}-{message_id}");
+            assert_eq!(
+                parse_message_ref(&ids),
+                Some(MessageLink {
+                    guild_id: None,
+                    ..expected
+                }),
+                "{ids}"
+            );
+        }
+    }
+
+    #[test]
+    fn arbitrary_input_never_panics() {
+        let pieces = [
+            "https://",
+            "http://",
+            "discord.com",
+            "ptb.",
+            "canary.",
+            "/channels/",
+            "@me",
+            "/",
+            "-",
+            "<",
+            ">",
+            "?",
+            "#",
+            "%",
+            "0",
+            "1",
+            "18446744073709551615",
+            "18446744073709551616",
+            "\u{0}",
+            "\u{1F4CC}",
+            " ",
+            "\n",
+            ":",
+            "..",
+            "[::1]",
+            "é",
+        ];
+        let mut generator = Generator(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..50_000 {
+            let length = generator.next() % 12;
+            let input: String = (0..length).map(|_| generator.pick(&pieces)).collect();
+            // Whatever comes out refers to the same message when written as ids
+            if let Some(link) = parse_message_ref(&input) {
+                let ids = format!("{}-{}", link.channel_id, link.message_id);
+                let parsed = parse_message_ref(&ids).unwrap();
+                assert_eq!(
+                    (parsed.channel_id, parsed.message_id),
+                    (link.channel_id, link.message_id)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn links_are_limited_to_their_channel() {
+        let link = parse_message_ref("https://discord.com/channels/1/2/3").unwrap();
+        assert!(link.is_in(Id::new(1), Id::new(2)));
+        assert!(!link.is_in(Id::new(1), Id::new(4)));
+        assert!(!link.is_in(Id::new(5), Id::new(2)));
+        // Without a guild, only the channel can be checked
+        let ids = parse_message_ref("2-3").unwrap();
+        assert!(ids.is_in(Id::new(5), Id::new(2)));
+    }
+}
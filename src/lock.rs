@@ -0,0 +1,64 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::Message,
+    guild::Permissions,
+    id::{marker::MessageMarker, Id},
+};
+
+use crate::{ephemeral, Context};
+
+pub const LOCKED: &str = "This pin is locked by a moderator.";
+
+/// Whether the member who used the interaction can manage the server
+pub fn can_manage_guild(event: &Interaction) -> bool {
+    event
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|perms| perms.contains(Permissions::MANAGE_GUILD))
+}
+
+/// Whether the user of the interaction is not allowed to unpin the message
+pub fn prevents_unpin(ctx: &Context, event: &Interaction, message_id: Id<MessageMarker>) -> bool {
+    ctx.store.is_locked(message_id) && !can_manage_guild(event)
+}
+
+/// Handle "Lock Pin" and "Unlock Pin", which restrict unpinning to server managers
+pub async fn set_locked(
+    ctx: &Context,
+    event: &Interaction,
+    message: &Message,
+    locked: bool,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let content = if !can_manage_guild(event) {
+        "You need the **Manage Server** permission to do this.".to_owned()
+    } else if locked && !message.pinned {
+        "This message isn't pinned.".to_owned()
+    } else if ctx.store.set_locked(message.id, locked).await? {
+        let mut content = if locked {
+            // A locked pin should stay, so it can't expire either
+            if ctx.store.remove_timed_unpin(message.id).await? {
+                ctx.scheduler.wake();
+            }
+            "Locked the pin, only server managers can unpin it now.".to_owned()
+        } else {
+            "Unlocked the pin.".to_owned()
+        };
+        if !ctx.store.is_persistent() {
+            content.push_str("\nNo database is configured, so this resets when the bot restarts.");
+        }
+        content
+    } else if locked {
+        "This pin is already locked.".to_owned()
+    } else {
+        "This pin isn't locked.".to_owned()
+    };
+
+    client
+        .create_response(event.id, &event.token, &ephemeral(content))
+        .await?;
+    Ok(())
+}
@@ -9,18 +9,55 @@
     clippy::explicit_iter_loop
 )]
 
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use serde::Deserialize;
+use config::{
+    Config, ConfirmationLayout, ConfirmationPlacement, ConfirmationStyle, SystemMessageHandling,
+    Visibility,
+};
+use error::PinbotError;
+use events::PinEvent;
+use futures::future;
+use pins::PinApi;
+use secrets::SecretSource;
+use store::Store;
+use tokio::task::JoinHandle;
 use tracing as log;
-use twilight_gateway::{Event, Intents, Shard, ShardId};
-use twilight_http::{request::AuditLogReason, Client};
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+use twilight_gateway::{error::ReceiveMessageErrorType, Event, Shard, ShardId};
+use twilight_http::Client;
 use twilight_model::{
-    application::interaction::{application_command::CommandData, Interaction, InteractionData},
-    channel::message::{
-        component::{ActionRow, Button, ButtonStyle},
-        MessageType,
+    application::{
+        command::CommandType,
+        interaction::{
+            application_command::{CommandData, CommandOptionValue},
+            message_component::MessageComponentInteractionData,
+            modal::ModalInteractionData,
+            Interaction, InteractionData,
+        },
     },
+    channel::{
+        message::{
+            component::{ActionRow, Button, ButtonStyle},
+            embed::EmbedFooter,
+            Embed, Message, MessageFlags, MessageType,
+        },
+        ChannelType,
+    },
+    gateway::{CloseCode, GatewayReaction},
     http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ApplicationMarker, ChannelMarker, GuildMarker, MessageMarker, UserMarker},
+        Id,
+    },
+    user::User,
 };
 
 macro_rules! row {
@@ -44,52 +81,391 @@ macro_rules! link {
     };
 }
 
-#[derive(Deserialize)]
-struct Config {
-    token: String,
+mod approval;
+mod audit;
+mod bookmark;
+mod bulk;
+mod category;
+mod cleanup;
+mod commands;
+mod config;
+mod confirmation;
+mod control;
+mod copy;
+mod count;
+mod dedup;
+mod digest;
+mod effective;
+mod entitlement;
+mod error;
+mod events;
+mod export;
+mod forum;
+mod freeze;
+mod gate;
+mod grant;
+mod help;
+mod highlights;
+mod label;
+mod layout;
+mod link;
+mod lock;
+mod metrics;
+mod mirror;
+mod mypins;
+mod oldest;
+mod pinlog;
+mod pins;
+mod presence;
+mod proxy;
+mod random;
+mod reaction;
+mod reason;
+mod schedule;
+mod secrets;
+mod select;
+mod selftest;
+mod settings;
+mod snapshot;
+mod store;
+mod thread;
+mod throttle;
+mod time;
+mod timed;
+mod transfer;
+mod trends;
+mod trigger;
+mod undo;
+
+/// Everything a command handler needs access to
+pub struct Context {
+    pub http: Client,
+    pub config: Config,
+    pub store: Store,
+    pub scheduler: timed::Scheduler,
+    pub interactions: dedup::RecentInteractions,
+    pub events: events::Publisher,
+    pub community_pins: reaction::CommunityPins,
+    /// Pins confirmed publicly, which need no notice for their system message
+    pub confirmed_pins: dedup::RecentPins,
+    /// Paces the deletion of pin system messages, see [`handle_system_message`]
+    pub system_deletes: throttle::TokenBucket,
+    /// When each shard received its first Ready event, by shard id
+    pub ready_at: Mutex<HashMap<u64, Instant>>,
+    /// The SKUs of recent interactions, see [`entitlement`]
+    pub entitlements: entitlement::Entitlements,
 }
 
 #[tokio::main(worker_threads = 1)]
 async fn main() -> Result<()> {
     // Parse the config and setup logger
-    tracing_subscriber::fmt::init();
+    let mut config = Config::load("config.json").await?;
+    let filter = EnvFilter::builder()
+        .with_default_directive(config.log_level.filter().into())
+        .from_env_lossy();
+    tracing_subscriber::fmt().with_env_filter(filter).init();
+    config.warn_degraded();
+    if let Some(ref source) = config.token_source {
+        log::info!("Reading the token from {}", source.name());
+        config.token = source.fetch_token().await?;
+    }
 
-    let token = {
-        let config = tokio::fs::read_to_string("config.json").await?;
-        serde_json::from_str::<Config>(config.as_str())?.token
-    };
+    // Only our copy is wiped once both are set up, twilight keeps plain copies for the whole run
+    let token = std::mem::take(&mut config.token);
 
     // Setup http and gateway connection (as minimal as possible)
-    let http = Client::new(token.clone());
-    let mut shard = Shard::new(ShardId::ONE, token.clone(), Intents::GUILD_MESSAGES);
+    let mut builder = Client::builder().token(token.expose().to_owned());
+    if let Some(proxy) = config.proxy()? {
+        let bridge = proxy::bridge(proxy.clone())?;
+        log::info!(
+            "Sending HTTP requests through the proxy at {}",
+            proxy.address()
+        );
+        log::warn!("The gateway connection is not proxied and connects to Discord directly");
+        builder = builder.proxy(bridge.to_string(), true);
+    }
+    // Lets maintainers point the bot at canary or a mock server instead of production
+    #[cfg(feature = "custom-api")]
+    if let Ok(url) = std::env::var("PINBOT_API_URL") {
+        // Either one would be ignored, which is easy to miss while testing
+        if config.proxy_url.is_some() {
+            anyhow::bail!("PINBOT_API_URL and proxy_url are both set, remove one of them");
+        }
+        let (host, use_http) = config::parse_host(&url)?;
+        log::warn!("Sending HTTP requests to {host} instead of discord.com");
+        builder = builder.proxy(host, use_http);
+    }
+    let http = builder.build();
 
-    let mut user_id = None;
+    if std::env::args().skip(1).any(|arg| arg == "--register-only") {
+        return register_only(&http, &config).await;
+    }
+    // Deploys can run this on every start, to catch manual changes and failed registrations
+    if std::env::args().skip(1).any(|arg| arg == "--sync-commands") {
+        sync_commands(&http, &config).await;
+    }
+
+    let mut builder =
+        twilight_gateway::Config::builder(token.expose().to_owned(), config.intents());
+    if let Some(ref template) = config.presence {
+        builder = presence::configure(builder, template);
+    }
+    // The shards share the identify queue of the config, which paces their logins
+    let gateway_config = builder.build();
+    let (ids, total) = config.shard_ids();
+    let shards: Vec<Shard> = ids
+        .iter()
+        .map(|&id| Shard::with_config(ShardId::new(id, total), gateway_config.clone()))
+        .collect();
+    drop(gateway_config);
+    drop(token);
+    if let Some(ref template) = config.presence {
+        for shard in &shards {
+            presence::spawn(shard.sender(), template.clone());
+        }
+    }
+    if config.shards.is_some() {
+        log::info!("Running shards {ids:?} out of {total}");
+    }
+    config.log_summary(total);
+    let store = Store::open(config.database.clone()).await?;
+    let user_id = bot_user_id(&http, &store).await;
+    let interactions = dedup::RecentInteractions::new(config.interaction_cache_size);
+    let events = events::Publisher::spawn(&config);
+    let ctx = Arc::new(Context {
+        http,
+        config,
+        store,
+        scheduler: timed::Scheduler::default(),
+        interactions,
+        events,
+        community_pins: reaction::CommunityPins::default(),
+        confirmed_pins: dedup::RecentPins::new(CONFIRMED_PINS),
+        system_deletes: throttle::TokenBucket::new(SYSTEM_DELETE_BURST, SYSTEM_DELETE_INTERVAL),
+        ready_at: Mutex::default(),
+        entitlements: entitlement::Entitlements::default(),
+    });
+
+    timed::spawn(ctx.clone());
+    control::spawn(ctx.clone())?;
+    pinlog::spawn_purge(ctx.clone());
+    digest::spawn(ctx.clone());
+
+    metrics::spawn_reporter();
+
+    // Everything logged while handling the events of a shard carries its id
+    future::try_join_all(shards.into_iter().map(|shard| {
+        let span = log::info_span!("shard", id = shard.id().number());
+        run_shard(shard, ctx.clone(), user_id).instrument(span)
+    }))
+    .await?;
+    Ok(())
+}
+
+/// Our own user id, known before Ready so the first pin messages are cleaned up as well.
+///
+/// Falls back to the id from the last run, which Ready confirms or corrects.
+async fn bot_user_id(http: &Client, store: &Store) -> Option<Id<UserMarker>> {
+    let user = match http.current_user().await {
+        Ok(response) => response.model().await.map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    match user {
+        Ok(user) => {
+            if store.bot_user_id() != Some(user.id) {
+                if let Err(e) = store.set_bot_user_id(user.id).await {
+                    log::warn!("Failed to remember our user id: {e}");
+                }
+            }
+            Some(user.id)
+        }
+        Err(e) => {
+            log::warn!("Failed to look up our user, using the id from the last run: {e}");
+            store.bot_user_id()
+        }
+    }
+}
+
+/// Handle the events of the shard until its connection fails fatally
+async fn run_shard(
+    mut shard: Shard,
+    ctx: Arc<Context>,
+    mut user_id: Option<Id<UserMarker>>,
+) -> Result<()> {
+    let http = &ctx.http;
+    // Number of connection errors in a row, used for the backoff
+    let mut failures = 0;
     log::info!("Connection established. Listening for events...");
     loop {
+        // Only the raw payload has the entitlements of interactions
+        #[cfg(feature = "entitlements")]
+        let result = entitlement::next_event(&mut shard, &ctx.entitlements).await;
+        #[cfg(not(feature = "entitlements"))]
         let result = shard.next_event().await;
+        if let Ok(ref event) = result {
+            metrics::EVENTS.record(event);
+            failures = 0;
+        }
         match result {
             Ok(Event::Ready(ready)) => {
-                user_id = Some(ready.user.id);
+                if user_id != Some(ready.user.id) {
+                    if let Some(previous) = user_id {
+                        log::warn!("Our user id changed from {previous} to {}", ready.user.id);
+                    }
+                    user_id = Some(ready.user.id);
+                    if let Err(e) = ctx.store.set_bot_user_id(ready.user.id).await {
+                        log::warn!("Failed to remember our user id: {e}");
+                    }
+                }
+                // Reconnecting doesn't lose anything the warmup waits for
+                ctx.ready_at
+                    .lock()
+                    .unwrap()
+                    .entry(shard.id().number())
+                    .or_insert_with(Instant::now);
+            }
+            // A resumed session replays missed events, so nothing from Ready has to be reset
+            Ok(Event::GatewayReconnect) => {
+                log::info!("Gateway requested reconnect, resuming");
+            }
+            Ok(Event::Resumed) => {
+                log::info!("Gateway session resumed");
             }
-            Ok(Event::InteractionCreate(ref interaction)) => {
-                if let Some(InteractionData::ApplicationCommand(ref data)) = interaction.data {
-                    if let Err(e) = handle_command(interaction, data, &http).await {
-                        log::error!("Command failed: {e}");
+            Ok(Event::InteractionCreate(interaction)) => {
+                match ctx.interactions.insert(interaction.id) {
+                    dedup::Delivery::First => {}
+                    dedup::Delivery::Running => {
+                        log::debug!("Skipping duplicate interaction {}", interaction.id);
+                        continue;
+                    }
+                    dedup::Delivery::Handled(outcome) => {
+                        let ctx = ctx.clone();
+                        tokio::spawn(
+                            async move { replay(&interaction, &ctx, &outcome).await }
+                                .in_current_span(),
+                        );
+                        continue;
                     }
                 }
+                // A panicking handler only takes down its own task, the loop keeps going
+                let shard_id = shard.id().number();
+                let handler = {
+                    let ctx = ctx.clone();
+                    let interaction = interaction.0.clone();
+                    async move { handle_interaction(&interaction, &ctx, shard_id).await }
+                };
+                let ctx = ctx.clone();
+                isolate(handler, async move {
+                    log::error!("Handler of interaction {} panicked", interaction.id);
+                    report_panic(&interaction, &ctx).await;
+                });
             }
-            Ok(Event::MessageCreate(message)) => {
-                // Delete the default "x pinned message" message in the channel, since we send our own!
-                if user_id == Some(message.author.id)
-                    && message.kind == MessageType::ChannelMessagePinned
-                {
-                    if let Err(e) = http.delete_message(message.channel_id, message.id).await {
-                        log::error!("Failed to delete pin message: {e}");
+            Ok(Event::GuildCreate(guild))
+                if ctx.config.leave_disallowed_guilds && !ctx.config.is_guild_allowed(guild.id) =>
+            {
+                log::warn!(
+                    "Leaving guild {} since it is not on the allowlist",
+                    guild.id
+                );
+                if let Err(e) = http.leave_guild(guild.id).await {
+                    log::error!("Failed to leave guild: {e}");
+                }
+            }
+            // Unavailable guilds are only down for a while, we are still in them
+            Ok(Event::GuildDelete(guild))
+                if ctx.config.cleanup_removed_guilds && !guild.unavailable =>
+            {
+                match ctx.store.remove_guild_tasks(guild.id).await {
+                    Ok(removed) => {
+                        log::info!(
+                            "Removed from guild {}, dropped {} timed and scheduled pins",
+                            guild.id,
+                            removed
+                        );
+                        ctx.scheduler.wake();
                     }
+                    Err(e) => log::error!("Failed to clean up removed guild: {e}"),
                 }
             }
+            // The default "x pinned message" message in the channel, for pins we made ourselves
+            Ok(Event::MessageCreate(message))
+                if user_id == Some(message.author.id)
+                    && message.kind == MessageType::ChannelMessagePinned =>
+            {
+                // Deletions are paced, which must not hold up the other events
+                let ctx = ctx.clone();
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = handle_system_message(&ctx, &message).await {
+                            if let Some(e) = e.downcast_ref() {
+                                metrics::observe_http_error(e, "system_message");
+                            }
+                            log::error!("Failed to handle pin message: {e}");
+                        }
+                    }
+                    .in_current_span(),
+                );
+            }
+            Ok(Event::MessageCreate(message)) if ctx.config.text_trigger.is_some() => {
+                // Its requests must not hold up the other events of the shard
+                let ctx = ctx.clone();
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = trigger::handle(&ctx, &message).await {
+                            if let Some(e) = e.downcast_ref() {
+                                metrics::observe_http_error(e, "text_trigger");
+                            }
+                            log::error!("Text trigger failed: {e}");
+                        }
+                    }
+                    .in_current_span(),
+                );
+            }
+            Ok(Event::ThreadCreate(thread)) if !ctx.config.forum_pin_tags.is_empty() => {
+                // The starter message might have to be waited for
+                let ctx = ctx.clone();
+                tokio::spawn(
+                    async move {
+                        if let Err(e) = forum::handle(&ctx, &thread).await {
+                            log::error!("Failed to pin forum post {}: {e}", thread.id);
+                        }
+                    }
+                    .in_current_span(),
+                );
+            }
+            Ok(Event::ReactionAdd(reaction)) => spawn_reaction(&ctx, reaction.0),
+            Ok(Event::ReactionRemove(reaction)) => spawn_reaction(&ctx, reaction.0),
             Err(error) => {
-                log::error!(?error, "Error in event loop");
+                let context = describe_gateway_error(error.kind());
+                match error.kind() {
+                    // A single bad payload says nothing about the connection
+                    ReceiveMessageErrorType::Deserializing { .. } => {
+                        log::warn!("Error in event loop, {context}: {error}");
+                    }
+                    // The shard backs off between attempts and resumes the session by itself
+                    ReceiveMessageErrorType::Reconnect => {
+                        log::warn!("Error in event loop, {context}: {error}");
+                    }
+                    // Nothing works until the operator changes the config or the application
+                    ReceiveMessageErrorType::FatallyClosed {
+                        close_code: CloseCode::DisallowedIntents,
+                    } => {
+                        log::error!(
+                            "Discord refused the privileged intents {:?}, enable them for the \
+                             application in the developer portal or disable text_trigger",
+                            ctx.config.privileged_intents()
+                        );
+                    }
+                    ReceiveMessageErrorType::Io | ReceiveMessageErrorType::SendingMessage => {
+                        failures += 1;
+                        let delay = backoff(failures);
+                        log::error!("Error in event loop, {context} in {delay:?}: {error}");
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => {
+                        log::error!("Error in event loop, {context}: {error}");
+                    }
+                }
                 if error.is_fatal() {
                     break;
                 }
@@ -100,9 +476,106 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-const DEFER: InteractionResponse = InteractionResponse {
+/// Human readable explanation of what happened and what the shard does next
+const fn describe_gateway_error(kind: &ReceiveMessageErrorType) -> &'static str {
+    match kind {
+        ReceiveMessageErrorType::Compression => "failed to decompress message, skipping it",
+        ReceiveMessageErrorType::Deserializing { .. } => "failed to deserialize event, skipping it",
+        ReceiveMessageErrorType::FatallyClosed { .. } => "connection closed fatally, shutting down",
+        ReceiveMessageErrorType::Io => "connection error, will reconnect",
+        ReceiveMessageErrorType::Process => "failed to process gateway message, skipping it",
+        ReceiveMessageErrorType::Reconnect => "failed to reconnect, will resume the session",
+        ReceiveMessageErrorType::SendingMessage => "failed to send message, will reconnect",
+        _ => "unknown error",
+    }
+}
+
+/// Exponential delay before trying again after the failures in a row, capped at one minute
+fn backoff(failures: u32) -> Duration {
+    let delay = Duration::from_millis(500) * 2u32.saturating_pow(failures.saturating_sub(1));
+    delay.min(Duration::from_secs(60))
+}
+
+/// Sync the command definitions without starting the gateway connection
+async fn register_only(http: &Client, config: &Config) -> Result<()> {
+    let application_id = application_id(http, config).await?;
+    match commands::register_commands(http, application_id, config).await {
+        Ok(commands) => {
+            log::info!(
+                "Registered {} commands for {}",
+                commands.len(),
+                application_id
+            );
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("Failed to register commands: {e}");
+            Err(e)
+        }
+    }
+}
+
+/// Bring the registered commands in line with the definitions, reporting what was out of sync.
+///
+/// Startup goes on if this fails, the commands only stay as they were.
+async fn sync_commands(http: &Client, config: &Config) {
+    let result = match application_id(http, config).await {
+        Ok(application_id) => commands::sync_commands(http, application_id, config).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(drift) if drift.is_empty() => log::info!("Registered commands are in sync"),
+        Ok(drift) => log::warn!(
+            missing = ?drift.missing,
+            extra = ?drift.extra,
+            changed = ?drift.changed,
+            "Registered commands were out of sync, registered them again"
+        ),
+        Err(e) => log::error!("Failed to sync commands: {e}"),
+    }
+}
+
+async fn application_id(http: &Client, config: &Config) -> Result<Id<ApplicationMarker>> {
+    match config.application_id {
+        Some(id) => Ok(id),
+        None => Ok(http.current_user_application().await?.model().await?.id),
+    }
+}
+
+/// Publicly confirmed pins remembered until their system message arrives
+const CONFIRMED_PINS: usize = 100;
+/// Pin messages deleted right away, before the deletions are spread out
+const SYSTEM_DELETE_BURST: u32 = 5;
+/// Time between deletions of pin messages once the burst is used up
+const SYSTEM_DELETE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Acknowledge the interaction with a "thinking" response.
+///
+/// The first followup replaces it and keeps its visibility, so both have to use the same one.
+pub fn defer(visibility: Visibility) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::DeferredChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            flags: Some(visibility.flags()),
+            ..Default::default()
+        }),
+    }
+}
+
+pub const DEFER_EPHEMERAL: InteractionResponse = InteractionResponse {
     kind: InteractionResponseType::DeferredChannelMessageWithSource,
-    data: None,
+    data: Some(InteractionResponseData {
+        allowed_mentions: None,
+        attachments: None,
+        choices: None,
+        components: None,
+        content: None,
+        custom_id: None,
+        embeds: None,
+        flags: Some(MessageFlags::EPHEMERAL),
+        title: None,
+        tts: None,
+    }),
 };
 
 #[inline]
@@ -119,7 +592,146 @@ fn guild_only() -> InteractionResponse {
     }
 }
 
-async fn handle_command(event: &Interaction, data: &CommandData, http: &Client) -> Result<()> {
+/// Handle the interaction received by the shard with the id
+async fn handle_interaction(interaction: &Interaction, ctx: &Context, shard_id: u64) {
+    let result = match interaction.data {
+        Some(InteractionData::ApplicationCommand(ref data)) => {
+            let start = Instant::now();
+            let result = handle_command(interaction, data, ctx, shard_id)
+                .await
+                .map_err(anyhow::Error::from);
+            metrics::COMMAND_LATENCY.observe(start.elapsed());
+            result
+        }
+        Some(InteractionData::ModalSubmit(ref data)) => handle_modal(interaction, data, ctx).await,
+        Some(InteractionData::MessageComponent(ref data)) => {
+            handle_component(interaction, data, ctx).await
+        }
+        _ => Ok(()),
+    };
+    // Handlers which tell the user something specific recorded it already
+    let outcome = match result {
+        Ok(()) => "This was already handled.".to_owned(),
+        Err(_) => ctx.config.error_message(),
+    };
+    ctx.interactions.complete(interaction.id, outcome);
+    if let Err(e) = result {
+        // Commands fail with a PinbotError, which wraps the error of the request
+        let http = e
+            .downcast_ref()
+            .or_else(|| e.downcast_ref::<PinbotError>().and_then(PinbotError::http));
+        if let Some(e) = http {
+            metrics::observe_http_error(e, "interaction");
+        }
+        // Every followup would fail the same way, so there is nothing left to do
+        if error::error_code(&e) == Some(error::UNKNOWN_INTERACTION) {
+            log::warn!(
+                "Interaction {} timed out before it was acknowledged",
+                interaction.id
+            );
+        } else {
+            log::error!("Command failed: {e}");
+        }
+    }
+}
+
+/// How long the token of an interaction can be used for followups
+const INTERACTION_TOKEN_SECS: u64 = 15 * 60;
+
+/// Repeat the outcome of the first delivery of the interaction, while its token is still valid.
+///
+/// The first delivery already acknowledged the interaction, so this is a followup.
+async fn replay(interaction: &Interaction, ctx: &Context, outcome: &str) {
+    let age = time::now().saturating_sub(time::snowflake_timestamp(interaction.id.get()));
+    if age >= INTERACTION_TOKEN_SECS {
+        log::debug!("Skipping expired duplicate interaction {}", interaction.id);
+        return;
+    }
+    log::debug!(
+        "Replaying the outcome of duplicate interaction {}",
+        interaction.id
+    );
+    let client = ctx.http.interaction(interaction.application_id);
+    let mentions = confirmation::NO_PINGS;
+    let followup = client
+        .create_followup(&interaction.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .allowed_mentions(Some(&mentions))
+        .content(outcome);
+    let result = match followup {
+        Ok(request) => request.await.map(drop).map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        log::warn!(
+            "Failed to replay the outcome of interaction {}: {e}",
+            interaction.id
+        );
+    }
+}
+
+/// Tell the user their command broke, whether or not the handler got to respond before panicking
+async fn report_panic(interaction: &Interaction, ctx: &Context) {
+    let client = ctx.http.interaction(interaction.application_id);
+    let content = ctx.config.error_message();
+    ctx.interactions.complete(interaction.id, content.as_str());
+    let response = ephemeral(content.as_str());
+    match client
+        .create_response(interaction.id, &interaction.token, &response)
+        .await
+    {
+        Ok(_) => return,
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_INTERACTION) => {
+            log::warn!(
+                "Interaction {} timed out before the panic could be reported",
+                interaction.id
+            );
+            return;
+        }
+        Err(_) => {}
+    }
+    // The interaction was already deferred or answered
+    let followup = match client
+        .create_followup(&interaction.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .content(&content)
+    {
+        Ok(request) => request.await,
+        Err(e) => {
+            log::warn!(
+                "Failed to report panic of interaction {}: {e}",
+                interaction.id
+            );
+            return;
+        }
+    };
+    if let Err(e) = followup {
+        log::warn!(
+            "Failed to report panic of interaction {}: {e}",
+            interaction.id
+        );
+    }
+}
+
+#[inline]
+fn ephemeral(content: impl Into<String>) -> InteractionResponse {
+    InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            content: Some(content.into()),
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    }
+}
+
+async fn handle_command(
+    event: &Interaction,
+    data: &CommandData,
+    ctx: &Context,
+    shard_id: u64,
+) -> Result<(), PinbotError> {
+    let Context { http, config, .. } = ctx;
     let channel_id = event
         .channel
         .as_ref()
@@ -127,6 +739,21 @@ async fn handle_command(event: &Interaction, data: &CommandData, http: &Client)
         .expect("Message command must have a channel id");
     let client = http.interaction(event.application_id);
 
+    // Settings and caches might not be consistent right after the shard connected
+    let warming_up = ctx
+        .ready_at
+        .lock()
+        .unwrap()
+        .get(&shard_id)
+        .is_none_or(|ready_at| ready_at.elapsed() < config.warmup());
+    if warming_up {
+        let response = ephemeral("Just starting up, try again in a moment.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
     // Only allow pinning in guilds
     let Some(guild_id) = event.guild_id else {
         client
@@ -135,72 +762,939 @@ async fn handle_command(event: &Interaction, data: &CommandData, http: &Client)
         return Ok(());
     };
 
+    if !config.is_guild_allowed(guild_id) {
+        let response = ephemeral("This bot is not available in this server.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(response) = entitlement::upsell(ctx, event, &data.name) {
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
     // Check that we are responding to the right command
-    let pin = match data.name.as_str() {
-        "Pin Message" => true,
-        "Unpin Message" => false,
+    let target = resolved_message(data);
+    let (pin, temporary) = match (data.name.as_str(), target) {
+        // Discord always resolves the target of a message command, but that's no reason to panic
+        (_, None) if data.kind == CommandType::Message => {
+            client
+                .create_response(
+                    event.id,
+                    &event.token,
+                    &ephemeral("Please use this on a message."),
+                )
+                .await?;
+            return Ok(());
+        }
+        (commands::PIN, _) => (true, false),
+        (commands::UNPIN, _) => (false, false),
+        (commands::TEMP_PIN, _) => (true, true),
+        (commands::SLASH_PIN, _) => (true, false),
+        (commands::SLASH_UNPIN, _) => (false, false),
+        (commands::SET_ROLE, _) => return Ok(settings::set_role(ctx, event, data, guild_id).await?),
+        (commands::UNPIN_APPROVAL, _) => {
+            return Ok(settings::set_unpin_approval(ctx, event, data, guild_id).await?)
+        }
+        (commands::HELP, _) => return Ok(help::help(ctx, event, guild_id).await?),
+        (commands::EFFECTIVE_CONFIG, _) => {
+            return Ok(effective::effective_config(ctx, event, guild_id, channel_id).await?)
+        }
+        (commands::SELF_TEST, _) => return Ok(selftest::self_test(ctx, event, channel_id).await?),
+        (commands::LOCK_PIN, Some(message)) => {
+            return Ok(lock::set_locked(ctx, event, message, true).await?)
+        }
+        (commands::UNLOCK_PIN, Some(message)) => {
+            return Ok(lock::set_locked(ctx, event, message, false).await?)
+        }
+        (commands::LABEL_PIN, Some(message)) => {
+            return Ok(label::prompt(ctx, event, message).await?)
+        }
+        (commands::CATEGORIZE_PIN, Some(message)) => {
+            return Ok(category::prompt(ctx, event, message).await?)
+        }
+        (commands::MY_PINS, _) => return Ok(mypins::my_pins(ctx, event, guild_id).await?),
+        (commands::PIN_FREEZE, _) => return Ok(freeze::freeze(ctx, event, data, channel_id).await?),
+        (commands::PIN_UNFREEZE, _) => return Ok(freeze::unfreeze(ctx, event, channel_id).await?),
+        (commands::PIN_COUNT, _) => return Ok(count::pin_count(ctx, event, channel_id).await?),
+        (commands::LIST_PINS, _) => {
+            return Ok(label::list_pins(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::PREVIEW_PIN, Some(message)) => {
+            return Ok(preview_pin(ctx, event, guild_id, message).await?)
+        }
+        (commands::SHOW_PIN, Some(message)) => {
+            return Ok(show_pin(ctx, event, guild_id, channel_id, message).await?)
+        }
+        (commands::PIN_LOG, _) => return Ok(pinlog::pin_log(ctx, event, guild_id).await?),
+        (commands::PIN_LOG_PURGE, _) => return Ok(pinlog::purge(ctx, event, data, guild_id).await?),
+        (commands::GRANT_PIN, _) => return Ok(grant::grant_pin(ctx, event, data, guild_id).await?),
+        (commands::REVOKE_PIN, _) => {
+            return Ok(grant::revoke_pin(ctx, event, data, guild_id).await?)
+        }
+        (commands::PIN_TRENDS, _) => {
+            return Ok(trends::pin_trends(ctx, event, data, guild_id).await?)
+        }
+        (commands::OLDEST_PIN, _) => {
+            return Ok(oldest::oldest_pin(ctx, event, guild_id, channel_id).await?)
+        }
+        (commands::RANDOM_PIN, _) => {
+            return Ok(random::random_pin(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::PIN_REACTED, _) => {
+            return Ok(bulk::pin_reacted(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::SCHEDULE_PIN, _) => {
+            return Ok(schedule::schedule_pin(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::UNPIN_OLDER_THAN, _) => {
+            return Ok(cleanup::unpin_older_than(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::PIN_SNAPSHOT, _) => {
+            return Ok(snapshot::save(ctx, event, data, channel_id).await?)
+        }
+        (commands::PIN_DIFF, _) => {
+            return Ok(snapshot::diff(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::PIN_SELECT, _) => {
+            return Ok(select::pin_select(ctx, event, guild_id, channel_id).await?)
+        }
+        (commands::BULK_PIN, _) => {
+            return Ok(bulk::bulk_pin(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::EXPORT_PINS, _) => {
+            return Ok(export::export_pins(ctx, event, guild_id, channel_id).await?)
+        }
+        (commands::IMPORT_PINS, _) => {
+            return Ok(export::import_pins(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::PIN_TRANSFER, _) => {
+            return Ok(transfer::transfer(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::COPY_PIN, _) => {
+            return Ok(copy::copy_pin(ctx, event, data, guild_id, channel_id).await?)
+        }
+        (commands::BOOKMARK, Some(message)) => {
+            return Ok(bookmark::bookmark(ctx, event, guild_id, message).await?)
+        }
+        (commands::WHO_PINNED, Some(message)) => {
+            return Ok(audit::who_pinned(ctx, event, guild_id, message).await?)
+        }
         _ => return Ok(()),
     };
 
-    // Pull the message data used for pinning
-    let message = data
-        .resolved
-        .as_ref()
-        .and_then(|it| it.messages.values().next())
-        .expect("Message command is missing resolved message!");
+    // Checks the role, the freeze, the age of the account and our permissions
+    if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, pin) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let Some(author) = pinner(event) else {
+        return Ok(());
+    };
+
+    // Pull the message data used for pinning, slash commands only have a link to it
+    let linked;
+    let message = match target {
+        Some(message) => message,
+        None => match linked_message(ctx, data, guild_id, channel_id).await? {
+            Some(message) => {
+                linked = message;
+                &linked
+            }
+            None => {
+                let response = ephemeral("Give me a link to a message of this channel.");
+                client
+                    .create_response(event.id, &event.token, &response)
+                    .await?;
+                return Ok(());
+            }
+        },
+    };
+
+    if !pin && lock::prevents_unpin(ctx, event, message.id) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(lock::LOCKED))
+            .await?;
+        return Ok(());
+    }
+
+    if !pin && ctx.store.guild(guild_id).unpin_approval {
+        return Ok(approval::request(ctx, event, guild_id, message, author).await?);
+    }
+
+    if temporary {
+        return Ok(timed::prompt(ctx, event, channel_id, message.id, message.author.id).await?);
+    }
+
+    // Pinning it again succeeds, but the confirmation would claim it was just pinned
+    if pin && config.check_already_pinned && already_pinned(&ctx.http, channel_id, message.id).await
+    {
+        let button = row!(link!(
+            "Message",
+            jump_link(guild_id, channel_id, message.id)
+        ));
+        let mut response = ephemeral("That message is already pinned.");
+        if let Some(ref mut data) = response.data {
+            data.components = Some(button.into());
+        }
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    // Regulated communities want every pin to be accounted for
+    if pin && config.require_pin_reason {
+        return Ok(reason::prompt(ctx, event, channel_id, message.id).await?);
+    }
+
+    Ok(apply_pin(ctx, event, guild_id, message, author, pin, None).await?)
+}
+
+/// Pin or unpin the message and confirm it, once every check passed.
+///
+/// The reason is added to the audit log, the pin log and the confirmation.
+async fn apply_pin(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+    author: &User,
+    pin: bool,
+    reason: Option<&str>,
+) -> Result<()> {
+    let Context { http, config, .. } = ctx;
+    let client = http.interaction(event.application_id);
+    let channel_id = message.channel_id;
+    // Channels full of automated posts don't need to hear about every one of them
+    let quiet = config.quiet_for_bot_messages && message.author.bot;
+    let visibility = if quiet {
+        Visibility::Ephemeral
+    } else {
+        config.response_visibility
+    };
 
     // Acknowledge the interaction before doing anything else
     client
-        .create_response(event.id, &event.token, &DEFER)
+        .create_response(event.id, &event.token, &defer(visibility))
         .await?;
 
-    let channel_name: &str = event
-        .channel
-        .as_ref()
-        .and_then(|channel| channel.name.as_deref())
-        .unwrap_or("");
-    let username = &event.author().unwrap().name;
+    let channel_name = config.channel_name(event.channel.as_ref());
+    let username = &author.name;
 
     // Pin or unpin the message
-    let result = if pin {
-        http.create_pin(channel_id, message.id)
-            .reason(&format!("{username} pinned a message in {channel_name}"))
-            .unwrap()
-            .await
+    let mut audit_reason = if pin {
+        format!("{username} pinned a message in {channel_name}")
     } else {
-        http.delete_pin(channel_id, message.id)
-            .reason(&format!("{username} unpinned a message in {channel_name}"))
-            .unwrap()
-            .await
+        format!("{username} unpinned a message in {channel_name}")
     };
-
-    let button = row!(link!(
-        "Message",
-        format!(
-            "https://discord.com/channels/{}/{}/{}",
-            guild_id, channel_id, message.id
-        )
-    ));
-
-    let request = client.create_followup(&event.token);
+    if let Some(reason) = reason {
+        audit_reason.push_str(": ");
+        audit_reason.push_str(reason);
+    }
+    // The channel sees our confirmation, so the system message needs no notice in its place
+    if pin && visibility == Visibility::Public {
+        ctx.confirmed_pins.insert(message.id);
+    }
+    let result = do_pin(http, channel_id, message.id, pin, &audit_reason).await;
 
     if let Err(e) = result {
         // Could happen if we are missing permissions
+        metrics::observe_pin_error(&e, if pin { "create_pin" } else { "delete_pin" });
         log::error!("Failed to process pin due to error: {}", e);
-        request
-            .content("Encountered some error, sorry about that... Try again?")?
+        let content = config.pin_error_message(e.code());
+        ctx.interactions.complete(event.id, content.as_str());
+        client
+            .create_followup(&event.token)
+            .flags(visibility.flags())
+            .content(&content)?
             .await?;
     } else {
+        // Pinning or unpinning it by hand overrides any previous expiry
+        if ctx.store.remove_timed_unpin(message.id).await? {
+            ctx.scheduler.wake();
+        }
+        pinlog::record_with_reason(
+            ctx,
+            PinEvent::new(pin, guild_id, channel_id, message.id, author.id)
+                .author(message.author.id),
+            reason.map(ToOwned::to_owned),
+        )
+        .await;
+
+        let unmirrored = if pin {
+            highlights::post(ctx, guild_id, message).await;
+            mirror::post(ctx, guild_id, message).await
+        } else {
+            Vec::new()
+        };
+
         // Send final response
-        let content = format!(
-            "\u{1F4CC} **{}** {}pinned message in this channel.",
-            username,
-            if pin { "" } else { "un" }
+        let mut content = confirmation::content(config, author, message.author.id, pin);
+        let mut embed = confirmation::embed(author, message, pin, locale(config, event));
+        if let Some(reason) = reason {
+            content.push_str(&format!("\nReason: {reason}"));
+            embed.description = Some(format!(
+                "{}\n\n**Reason:** {reason}",
+                embed.description.unwrap_or_default()
+            ));
+        }
+        if pin && config.show_pin_position {
+            if let Some((index, total)) = pin_position(ctx, channel_id, message.id).await {
+                let position = confirmation::position(index, total);
+                content.push('\n');
+                content.push_str(&position);
+                embed.footer = Some(EmbedFooter {
+                    icon_url: None,
+                    proxy_icon_url: None,
+                    text: position,
+                });
+            }
+        }
+        ctx.interactions.complete(event.id, content.as_str());
+        if quiet {
+            log::info!("[{}] {}", channel_id, content);
+            client
+                .create_followup(&event.token)
+                .flags(MessageFlags::EPHEMERAL)
+                .allowed_mentions(Some(&confirmation::NO_PINGS))
+                .content(&content)?
+                .await?;
+        } else {
+            send_confirmation(
+                ctx, event, guild_id, channel_id, message.id, &content, embed,
+            )
+            .await?;
+        }
+        if !unmirrored.is_empty() {
+            client
+                .create_followup(&event.token)
+                .flags(MessageFlags::EPHEMERAL)
+                .content(&mirror::failure(&unmirrored))?
+                .await?;
+        }
+        undo::offer(ctx, event, message.id, author.id, pin).await;
+    }
+
+    Ok(())
+}
+
+/// The message linked in the options of a slash command, if it exists in this channel
+async fn linked_message(
+    ctx: &Context,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<Option<Message>> {
+    let link = data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::String(ref value) => link::parse_message_ref(value),
+        _ => None,
+    });
+    // Links to other channels are rejected, since the user might not be able to see them
+    let Some(link) = link.filter(|it| it.is_in(guild_id, channel_id)) else {
+        return Ok(None);
+    };
+
+    match ctx.http.message(channel_id, link.message_id).await {
+        Ok(response) => Ok(Some(response.model().await?)),
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_MESSAGE) => Ok(None),
+        Err(e) => {
+            metrics::observe_http_error(&e, "message");
+            Err(e.into())
+        }
+    }
+}
+
+/// Whether the message is pinned already, assumes it isn't if the pins can't be loaded
+async fn already_pinned(
+    http: &impl PinApi,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> bool {
+    match http.is_pinned(channel_id, message_id).await {
+        Ok(pinned) => pinned,
+        Err(e) => {
+            log::warn!("[{}] Failed to check the pins: {}", channel_id, e);
+            false
+        }
+    }
+}
+
+/// The position of the message among the pins and their total, if it could be found
+async fn pin_position(
+    ctx: &Context,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> Option<(usize, usize)> {
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            log::warn!("[{}] Failed to load the pins: {}", channel_id, e);
+            return None;
+        }
+    };
+    // The newest pin comes first
+    let index = pins.iter().position(|message| message.id == message_id)?;
+    Some((index + 1, pins.len()))
+}
+
+/// Follow up on the deferred interaction in the configured style, with a jump link to the message
+async fn send_confirmation(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    content: &str,
+    embed: Embed,
+) -> Result<()> {
+    let link = jump_link(guild_id, channel_id, message_id);
+    let button = row!(link!("Message", link.clone()));
+
+    log::info!("[{}] {}", channel_id, content);
+    let mentions = confirmation::NO_PINGS;
+    let client = ctx.http.interaction(event.application_id);
+
+    let posted = match ctx.config.confirmation_placement {
+        ConfirmationPlacement::Interaction => None,
+        // Threads can't be started in the text chat of voice channels
+        ConfirmationPlacement::Thread if in_voice_chat(event) => None,
+        ConfirmationPlacement::Reply => {
+            Some(reply(ctx, channel_id, message_id, content, &embed).await)
+        }
+        ConfirmationPlacement::Thread => {
+            Some(thread::post(ctx, channel_id, content, &embed, &button).await)
+        }
+    };
+    match posted {
+        Some(Ok(())) => {
+            // The message replaces the deferred response, which would otherwise keep loading
+            client.delete_response(&event.token).await?;
+            return Ok(());
+        }
+        Some(Err(e)) => log::warn!("Failed to post confirmation, responding instead: {e}"),
+        None => {}
+    }
+
+    if ctx.config.confirmation_layout == ConfirmationLayout::Rich {
+        match layout::send_rich(ctx, event, content, &embed, link).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                log::warn!("Failed to send the rich confirmation, using the classic one: {e}")
+            }
+        }
+    }
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags())
+        .allowed_mentions(Some(&mentions))
+        .components(&button)?;
+    match ctx.config.confirmation_style {
+        ConfirmationStyle::Text => request.content(content)?.await?,
+        ConfirmationStyle::Embed => request.embeds(&[embed])?.await?,
+    };
+    Ok(())
+}
+
+/// Post the confirmation as a reply to the message, or on its own if the message is gone
+async fn reply(
+    ctx: &Context,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    content: &str,
+    embed: &Embed,
+) -> Result<()> {
+    let mentions = confirmation::NO_PINGS;
+    let request = ctx
+        .http
+        .create_message(channel_id)
+        .reply(message_id)
+        .fail_if_not_exists(false)
+        .allowed_mentions(Some(&mentions));
+    let result = match ctx.config.confirmation_style {
+        ConfirmationStyle::Text => request.content(content)?.await,
+        ConfirmationStyle::Embed => request.embeds(std::slice::from_ref(embed))?.await,
+    };
+    if let Err(e) = result {
+        metrics::observe_http_error(&e, "create_message");
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// The locale used for responses to the interaction
+fn locale<'a>(config: &'a Config, event: &'a Interaction) -> &'a str {
+    config
+        .force_locale
+        .as_deref()
+        .or(event.locale.as_deref())
+        .unwrap_or("en-US")
+}
+
+/// Handle "Show Pin", which repeats the confirmation for a message that is already pinned
+async fn show_pin(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    message: &Message,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    // Reposting a confirmation is as public as pinning, so it needs the same permission
+    let roles = event
+        .member
+        .as_ref()
+        .map_or(&[][..], |member| &member.roles);
+    let rejection = if !ctx.config.enable_pin {
+        Some("This command is disabled.".to_owned())
+    } else if let Some(role_id) = grant::missing_role(ctx, guild_id, event.author_id(), true, roles)
+    {
+        Some(format!("You need the <@&{role_id}> role to do this."))
+    } else if !message.pinned {
+        Some("This message isn't pinned.".to_owned())
+    } else {
+        None
+    };
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    // The pin might have been done by hand, then it isn't known who did it
+    let pinner_id = pinlog::pinner(ctx, guild_id, message.id);
+    let content = confirmation::reminder(&ctx.config, message.author.id, pinner_id);
+    let embed = confirmation::reminder_embed(message, pinner_id, locale(&ctx.config, event));
+    send_confirmation(
+        ctx, event, guild_id, channel_id, message.id, &content, embed,
+    )
+    .await
+}
+
+/// Handle "Preview Pin", which shows the configured confirmation without pinning anything
+async fn preview_pin(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+) -> Result<()> {
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+    let config = &ctx.config;
+
+    let note = "Preview of the confirmation, nothing was pinned:";
+    let (content, embeds) = match config.confirmation_style {
+        ConfirmationStyle::Text => {
+            let confirmation = confirmation::content(config, user, message.author.id, true);
+            (format!("{note}\n{confirmation}"), None)
+        }
+        ConfirmationStyle::Embed => {
+            let embed = confirmation::embed(user, message, true, locale(config, event));
+            (note.to_owned(), Some(vec![embed]))
+        }
+    };
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, message.channel_id, message.id)
+    ));
+
+    let response = InteractionResponse {
+        kind: InteractionResponseType::ChannelMessageWithSource,
+        data: Some(InteractionResponseData {
+            allowed_mentions: Some(confirmation::NO_PINGS),
+            components: Some(button.into()),
+            content: Some(content),
+            embeds,
+            flags: Some(MessageFlags::EPHEMERAL),
+            ..Default::default()
+        }),
+    };
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Apply the configured [`SystemMessageHandling`] to the system message of a pin
+async fn handle_system_message(ctx: &Context, message: &Message) -> Result<()> {
+    let handling = ctx.config.system_message_handling;
+    if handling == SystemMessageHandling::Keep {
+        return Ok(());
+    }
+
+    let channel_id = message.channel_id;
+    let waited = ctx.system_deletes.acquire().await;
+    if !waited.is_zero() {
+        log::info!(
+            "[{}] Throttled deletion of pin message by {}ms",
+            channel_id,
+            waited.as_millis()
         );
+    }
+    ctx.http.delete_message(channel_id, message.id).await?;
 
-        log::info!("[{}] {}", channel_id, content);
-        request.components(&button)?.content(&content)?.await?;
+    let (SystemMessageHandling::Replace, Some(guild_id), Some(pinned_id)) = (
+        handling,
+        message.guild_id,
+        message.reference.as_ref().and_then(|it| it.message_id),
+    ) else {
+        return Ok(());
+    };
+    if ctx.confirmed_pins.take(pinned_id) {
+        return Ok(());
     }
 
+    let button = row!(link!("Message", jump_link(guild_id, channel_id, pinned_id)));
+    ctx.http
+        .create_message(channel_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&confirmation::notice(&ctx.config))?
+        .await?;
     Ok(())
 }
+
+/// The target of a message command
+fn resolved_message(data: &CommandData) -> Option<&Message> {
+    data.resolved
+        .as_ref()
+        .and_then(|it| it.messages.values().next())
+}
+
+async fn handle_modal(
+    event: &Interaction,
+    data: &ModalInteractionData,
+    ctx: &Context,
+) -> Result<()> {
+    match data.custom_id.split(':').next() {
+        Some(timed::MODAL_PREFIX) => timed::submit(ctx, event, data).await,
+        Some(label::MODAL_PREFIX) => label::submit(ctx, event, data).await,
+        Some(reason::MODAL_PREFIX) => reason::submit(ctx, event, data).await,
+        _ => Ok(()),
+    }
+}
+
+async fn handle_component(
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+    ctx: &Context,
+) -> Result<()> {
+    match data.custom_id.split(':').next() {
+        Some(oldest::BUTTON_PREFIX) => oldest::unpin(ctx, event, data).await,
+        Some(pinlog::BUTTON_PREFIX) => pinlog::turn_page(ctx, event, data).await,
+        Some(cleanup::BUTTON_PREFIX) => cleanup::confirm(ctx, event, data).await,
+        Some(undo::BUTTON_PREFIX) => undo::undo(ctx, event, data).await,
+        Some(approval::BUTTON_PREFIX) => approval::approve(ctx, event, data).await,
+        Some(reason::BUTTON_PREFIX) => reason::retry(ctx, event, data).await,
+        Some(select::MENU_ID) => select::select(ctx, event, data).await,
+        Some(category::MENU_PREFIX) => category::select(ctx, event, data).await,
+        _ => Ok(()),
+    }
+}
+
+/// Longest reason the audit log accepts, in characters
+const MAX_AUDIT_REASON: usize = 512;
+
+/// Pin or unpin the message, with the reason shown in the audit log
+async fn do_pin(
+    http: &impl PinApi,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    pin: bool,
+    reason: &str,
+) -> Result<(), PinbotError> {
+    // Reasons given by users and long channel names would fail the whole request
+    let reason = reason
+        .char_indices()
+        .nth(MAX_AUDIT_REASON)
+        .and_then(|(end, _)| reason.get(..end))
+        .unwrap_or(reason);
+    if pin {
+        http.pin(channel_id, message_id, reason).await?;
+        metrics::PINS_TOTAL.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    } else {
+        Ok(http.unpin(channel_id, message_id, reason).await?)
+    }
+}
+
+/// Run the handler in its own task, and `on_panic` in its place if it panics
+fn isolate(
+    handler: impl Future<Output = ()> + Send + 'static,
+    on_panic: impl Future<Output = ()> + Send + 'static,
+) -> JoinHandle<()> {
+    let handler = tokio::spawn(handler.in_current_span());
+    tokio::spawn(
+        async move {
+            if handler.await.is_err_and(|e| e.is_panic()) {
+                on_panic.await;
+            }
+        }
+        .in_current_span(),
+    )
+}
+
+/// Count the reactions in the background, its requests must not hold up the other events
+fn spawn_reaction(ctx: &Arc<Context>, reaction: GatewayReaction) {
+    let ctx = ctx.clone();
+    tokio::spawn(
+        async move {
+            if let Err(e) = reaction::handle(&ctx, &reaction).await {
+                if let Some(e) = e.downcast_ref() {
+                    metrics::observe_http_error(e, "reaction_pin");
+                }
+                log::error!("Reaction pin failed: {e}");
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Whether the interaction comes from the text chat of a voice or stage channel
+fn in_voice_chat(event: &Interaction) -> bool {
+    event.channel.as_ref().is_some_and(|channel| {
+        matches!(
+            channel.kind,
+            ChannelType::GuildVoice | ChannelType::GuildStageVoice
+        )
+    })
+}
+
+/// The user who runs the command, bots and webhooks should never show up as the pinner
+fn pinner(event: &Interaction) -> Option<&User> {
+    event.author().filter(|user| !user.bot)
+}
+
+fn jump_link(
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> String {
+    format!("https://discord.com/channels/{guild_id}/{channel_id}/{message_id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pins::{Call, RecordingPins};
+    use twilight_model::guild::Permissions;
+
+    /// A context which keeps everything in memory, its client can't reach Discord
+    pub async fn context(config: serde_json::Value) -> Context {
+        let config: Config = serde_json::from_value(config).unwrap();
+        Context {
+            http: Client::new(String::new()),
+            store: Store::open(None).await.unwrap(),
+            scheduler: timed::Scheduler::default(),
+            interactions: dedup::RecentInteractions::new(0),
+            events: events::Publisher::spawn(&config),
+            community_pins: reaction::CommunityPins::default(),
+            confirmed_pins: dedup::RecentPins::new(CONFIRMED_PINS),
+            system_deletes: throttle::TokenBucket::new(SYSTEM_DELETE_BURST, SYSTEM_DELETE_INTERVAL),
+            ready_at: Mutex::default(),
+            entitlements: entitlement::Entitlements::default(),
+            config,
+        }
+    }
+
+    pub fn interaction(fields: serde_json::Value) -> Interaction {
+        let mut value = serde_json::json!({
+            "application_id": "1",
+            "id": "2",
+            "type": 2,
+            "data": { "id": "3", "name": commands::PIN, "type": 3 },
+            "token": "token",
+        });
+        if let serde_json::Value::Object(fields) = fields {
+            value.as_object_mut().unwrap().extend(fields);
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A user id created at the given unix time
+    pub fn created_at(timestamp: u64) -> u64 {
+        (timestamp * 1000 - time::DISCORD_EPOCH) << 22
+    }
+
+    /// A command of the member in channel 5 of guild 4, with our permissions there
+    pub fn command(user_id: u64, permissions: Permissions) -> Interaction {
+        interaction(serde_json::json!({
+            "guild_id": "4",
+            "channel": { "id": "5", "type": 0 },
+            "app_permissions": permissions.bits().to_string(),
+            "member": {
+                "user": user(user_id, false),
+                "roles": [],
+                "joined_at": "2024-01-01T00:00:00+00:00",
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+            },
+        }))
+    }
+
+    pub fn user(id: u64, bot: bool) -> serde_json::Value {
+        serde_json::json!({
+            "id": id.to_string(),
+            "username": "someone",
+            "discriminator": "0",
+            "avatar": null,
+            "bot": bot,
+        })
+    }
+
+    #[test]
+    fn only_users_can_be_the_pinner() {
+        let event = interaction(serde_json::json!({ "user": user(3, false) }));
+        assert_eq!(pinner(&event).map(|user| user.id), Some(Id::new(3)));
+
+        let event = interaction(serde_json::json!({
+            "guild_id": "4",
+            "member": {
+                "user": user(3, false),
+                "roles": [],
+                "joined_at": "2024-01-01T00:00:00+00:00",
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+            },
+        }));
+        assert_eq!(pinner(&event).map(|user| user.id), Some(Id::new(3)));
+
+        let event = interaction(serde_json::json!({ "user": user(5, true) }));
+        assert!(pinner(&event).is_none());
+        assert!(pinner(&interaction(serde_json::json!({}))).is_none());
+    }
+
+    #[tokio::test]
+    async fn panicking_handlers_only_take_down_their_task() {
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let send = |event| {
+            let sender = sender.clone();
+            async move { sender.send(event).unwrap() }
+        };
+        let first = isolate(async { panic!("broken handler") }, send("first panicked"));
+        let second = isolate(send("second handled"), send("second panicked"));
+        first.await.unwrap();
+        second.await.unwrap();
+        drop(sender);
+
+        let mut seen = Vec::new();
+        while let Some(event) = events.recv().await {
+            seen.push(event);
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, ["first panicked", "second handled"]);
+    }
+
+    #[test]
+    fn voice_chats_are_named_and_linked_like_text_channels() {
+        let channel = |kind: u8| {
+            serde_json::json!({
+                "channel": { "id": "5", "guild_id": "4", "name": "Lounge", "type": kind },
+            })
+        };
+        let config: Config = serde_json::from_value(serde_json::json!({ "token": "" })).unwrap();
+
+        let voice = interaction(channel(2));
+        assert!(in_voice_chat(&voice));
+        assert_eq!(config.channel_name(voice.channel.as_ref()), "#Lounge");
+        assert_eq!(
+            jump_link(Id::new(4), voice.channel.as_ref().unwrap().id, Id::new(6)),
+            "https://discord.com/channels/4/5/6"
+        );
+        assert!(in_voice_chat(&interaction(channel(13))));
+        assert!(!in_voice_chat(&interaction(channel(0))));
+        assert!(!in_voice_chat(&interaction(serde_json::json!({}))));
+    }
+
+    #[tokio::test]
+    async fn do_pin_calls_the_matching_request() {
+        let http = RecordingPins::default();
+        let (channel_id, message_id) = (Id::new(1), Id::new(2));
+
+        do_pin(&http, channel_id, message_id, true, "pinned")
+            .await
+            .unwrap();
+        do_pin(&http, channel_id, message_id, false, "unpinned")
+            .await
+            .unwrap();
+
+        let call = |method, reason: &str| Call {
+            method,
+            channel_id,
+            message_id: Some(message_id),
+            reason: Some(reason.to_owned()),
+        };
+        assert_eq!(
+            *http.calls.lock().unwrap(),
+            [call("pin", "pinned"), call("unpin", "unpinned")]
+        );
+    }
+
+    #[tokio::test]
+    async fn pinned_messages_are_already_pinned() {
+        let http = RecordingPins {
+            pinned: vec![pins::message(1, 2), pins::message(1, 3)],
+            ..RecordingPins::default()
+        };
+        assert!(already_pinned(&http, Id::new(1), Id::new(3)).await);
+        assert!(!already_pinned(&http, Id::new(1), Id::new(4)).await);
+    }
+
+    #[tokio::test]
+    async fn unknown_pins_are_not_pinned_already() {
+        let http = RecordingPins {
+            failing: true,
+            ..RecordingPins::default()
+        };
+        assert!(!already_pinned(&http, Id::new(1), Id::new(2)).await);
+    }
+
+    #[tokio::test]
+    async fn long_audit_reasons_are_cut_off() {
+        let http = RecordingPins::default();
+        let reason = format!(
+            "{}\u{1F4CC} and then some",
+            "x".repeat(MAX_AUDIT_REASON - 1)
+        );
+        do_pin(&http, Id::new(1), Id::new(2), true, &reason)
+            .await
+            .unwrap();
+        do_pin(&http, Id::new(1), Id::new(2), false, "short")
+            .await
+            .unwrap();
+
+        let calls = http.calls.lock().unwrap();
+        let expected = format!("{}\u{1F4CC}", "x".repeat(MAX_AUDIT_REASON - 1));
+        assert_eq!(calls[0].reason.as_deref(), Some(expected.as_str()));
+        assert_eq!(calls[1].reason.as_deref(), Some("short"));
+    }
+
+    #[tokio::test]
+    async fn is_pinned_lists_the_pins() {
+        let http = RecordingPins::default();
+        assert!(!http.is_pinned(Id::new(1), Id::new(2)).await.unwrap());
+        let calls = http.calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].method, "pinned_messages");
+    }
+}
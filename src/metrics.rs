@@ -0,0 +1,184 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+    time::Duration,
+};
+
+use tracing as log;
+use twilight_gateway::Event;
+use twilight_http::{api_error::ApiError, error::ErrorType};
+
+use crate::error::PinbotError;
+
+/// How often the counters are written to the debug log
+const REPORT_INTERVAL: Duration = Duration::from_secs(300);
+
+pub static EVENTS: EventCounters = EventCounters::new();
+
+/// Number of messages pinned through the bot since it started
+pub static PINS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Time from receiving a command until its final response was sent
+pub static COMMAND_LATENCY: Histogram = Histogram::new();
+
+/// Number of requests rejected by Discord with a 429
+pub static RATELIMITED_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Number of gateway events received, by kind
+pub struct EventCounters {
+    ready: AtomicU64,
+    interaction_create: AtomicU64,
+    message_create: AtomicU64,
+    other: AtomicU64,
+}
+
+impl EventCounters {
+    const fn new() -> Self {
+        Self {
+            ready: AtomicU64::new(0),
+            interaction_create: AtomicU64::new(0),
+            message_create: AtomicU64::new(0),
+            other: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record(&self, event: &Event) {
+        let counter = match event {
+            Event::Ready(_) => &self.ready,
+            Event::InteractionCreate(_) => &self.interaction_create,
+            Event::MessageCreate(_) => &self.message_create,
+            _ => &self.other,
+        };
+        counter.fetch_add(1, Relaxed);
+    }
+}
+
+/// Upper bounds of the histogram buckets in milliseconds, the last bucket has no bound
+const BUCKETS_MS: [u64; 9] = [50, 100, 250, 500, 1000, 2500, 5000, 10000, 30000];
+
+/// Number of observed durations, by bucket
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS_MS.len() + 1],
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let millis = duration.as_millis();
+        let index = BUCKETS_MS
+            .iter()
+            .position(|bound| millis <= u128::from(*bound))
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[index].fetch_add(1, Relaxed);
+    }
+
+    /// Upper bound of the bucket containing the quantile, `None` if nothing was observed yet
+    /// or it is beyond the last bound
+    pub fn quantile(&self, quantile: f64) -> Option<u64> {
+        let counts: Vec<u64> = self.buckets.iter().map(|it| it.load(Relaxed)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((total as f64) * quantile).ceil() as u64;
+        let mut seen = 0;
+        for (count, bound) in counts.iter().zip(BUCKETS_MS) {
+            seen += count;
+            if seen >= rank {
+                return Some(bound);
+            }
+        }
+        None
+    }
+}
+
+/// Count the error if it was caused by a ratelimit.
+///
+/// Call this exactly once for every failed request, where the error is finally handled.
+pub fn observe_http_error(error: &twilight_http::Error, route: &str) {
+    let ErrorType::Response { status, error, .. } = error.kind() else {
+        return;
+    };
+    if status.get() != 429 {
+        return;
+    }
+
+    RATELIMITED_TOTAL.fetch_add(1, Relaxed);
+    if let ApiError::Ratelimited(ratelimit) = error {
+        log::debug!(
+            route,
+            retry_after = ratelimit.retry_after,
+            global = ratelimit.global,
+            "Request was ratelimited"
+        );
+    } else {
+        log::debug!(route, "Request was ratelimited");
+    }
+}
+
+/// Same as [`observe_http_error`], for the failed pins and unpins of [`crate::do_pin`]
+pub fn observe_pin_error(error: &PinbotError, route: &str) {
+    if let Some(error) = error.http() {
+        observe_http_error(error, route);
+    }
+}
+
+/// Periodically log the event counters, so operators can see the shape of the traffic
+pub fn spawn_reporter() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(REPORT_INTERVAL);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            log::debug!(
+                ready = EVENTS.ready.load(Relaxed),
+                interaction_create = EVENTS.interaction_create.load(Relaxed),
+                message_create = EVENTS.message_create.load(Relaxed),
+                other = EVENTS.other.load(Relaxed),
+                "Gateway events received"
+            );
+            log::debug!(
+                ratelimited_total = RATELIMITED_TOTAL.load(Relaxed),
+                "HTTP requests ratelimited"
+            );
+            log::debug!(
+                p50_ms = ?COMMAND_LATENCY.quantile(0.5),
+                p99_ms = ?COMMAND_LATENCY.quantile(0.99),
+                "Command latency"
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_are_the_bound_of_their_bucket() {
+        let histogram = Histogram::new();
+        assert_eq!(histogram.quantile(0.5), None);
+
+        for millis in [10, 20, 30, 40, 60, 70, 200, 400, 900, 4000] {
+            histogram.observe(Duration::from_millis(millis));
+        }
+        assert_eq!(histogram.quantile(0.4), Some(50));
+        assert_eq!(histogram.quantile(0.5), Some(100));
+        assert_eq!(histogram.quantile(0.9), Some(1000));
+        assert_eq!(histogram.quantile(1.0), Some(5000));
+    }
+
+    #[test]
+    fn quantiles_beyond_the_last_bound_are_unknown() {
+        let histogram = Histogram::new();
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_secs(60));
+        assert_eq!(histogram.quantile(0.5), Some(50));
+        assert_eq!(histogram.quantile(0.99), None);
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use tracing as log;
+use twilight_model::{
+    channel::{
+        message::component::{ActionRow, Button, ButtonStyle},
+        Message,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{confirmation, do_pin, error, freeze, highlights, jump_link, metrics, Context};
+
+/// Channels which share their pins, like the same channel split by language
+#[derive(Deserialize)]
+pub struct PinMirror {
+    pub channels: Vec<Id<ChannelMarker>>,
+    /// Pin the reference in the linked channels too, instead of only posting it
+    #[serde(default)]
+    pub pin: bool,
+}
+
+/// Check that every group links at least two channels, and no channel is in two groups
+pub fn validate(mirrors: &[PinMirror]) -> Result<()> {
+    let mut seen = HashSet::new();
+    for (index, mirror) in mirrors.iter().enumerate() {
+        let channels: HashSet<_> = mirror.channels.iter().collect();
+        if channels.len() < 2 {
+            bail!("pin_mirrors[{index}] needs at least two different channels");
+        }
+        if let Some(channel_id) = channels
+            .iter()
+            .find(|channel_id| !seen.insert(**channel_id))
+        {
+            bail!("Channel {channel_id} is in more than one group of pin_mirrors");
+        }
+    }
+    Ok(())
+}
+
+/// Post a reference to the newly pinned message in the channels linked to its channel.
+///
+/// The pin itself already happened, so failures don't stop the other channels. Returns the
+/// channels which didn't get the reference, for the caller to report.
+pub async fn post(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    message: &Message,
+) -> Vec<Id<ChannelMarker>> {
+    let Some(mirror) = group(ctx, message) else {
+        return Vec::new();
+    };
+
+    let mut failed = Vec::new();
+    for &target_id in &mirror.channels {
+        if target_id == message.channel_id {
+            continue;
+        }
+        if let Err(e) = send(ctx, guild_id, target_id, message, mirror.pin).await {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "mirror");
+            }
+            match error::error_code(&e) {
+                Some(error::MISSING_ACCESS | error::MISSING_PERMISSIONS) => log::warn!(
+                    "[{}] Missing permissions to mirror pins to {}",
+                    message.channel_id,
+                    target_id
+                ),
+                _ => log::error!("Failed to mirror pin to {target_id}: {e}"),
+            }
+            failed.push(target_id);
+        }
+    }
+    failed
+}
+
+/// The group the message is mirrored to, if any
+fn group<'a>(ctx: &'a Context, message: &Message) -> Option<&'a PinMirror> {
+    // Pinning one of the references must not bounce it back, other bot messages are mirrored
+    if ctx.store.is_mirror_reference(message.id) {
+        return None;
+    }
+    ctx.config
+        .pin_mirrors
+        .iter()
+        .find(|mirror| mirror.channels.contains(&message.channel_id))
+}
+
+async fn send(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    target_id: Id<ChannelMarker>,
+    message: &Message,
+    pin: bool,
+) -> Result<()> {
+    let mut content = format!(
+        "\u{1F4CC} Pinned in <#{}>, by <@{}>:",
+        message.channel_id, message.author.id
+    );
+    content.push_str(&highlights::quote(message));
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, message.channel_id, message.id)
+    ));
+
+    let reference = ctx
+        .http
+        .create_message(target_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?
+        .model()
+        .await?;
+    ctx.store.add_mirror_reference(reference.id).await?;
+
+    // The reference is still posted in frozen channels, it just isn't pinned
+    if pin && freeze::frozen_until(ctx, target_id).is_none() {
+        let reason = format!("Mirrored a pin from #{}", message.channel_id);
+        do_pin(&ctx.http, target_id, reference.id, true, &reason).await?;
+    }
+    Ok(())
+}
+
+/// What to tell the user if the pin didn't reach some of the linked channels
+pub fn failure(failed: &[Id<ChannelMarker>]) -> String {
+    let channels: Vec<_> = failed
+        .iter()
+        .map(|channel_id| format!("<#{channel_id}>"))
+        .collect();
+    format!(
+        "I couldn't mirror the pin to {}, check my permissions there.",
+        channels.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pins;
+
+    #[tokio::test]
+    async fn only_our_references_are_not_mirrored() {
+        let ctx = crate::tests::context(serde_json::json!({
+            "token": "token",
+            "pin_mirrors": [{ "channels": ["2", "3"] }],
+        }))
+        .await;
+        ctx.store.set_bot_user_id(Id::new(1)).await.unwrap();
+        ctx.store.add_mirror_reference(Id::new(20)).await.unwrap();
+
+        // Written by the bot too, but not one of the references
+        let announcement = pins::message(2, 10);
+        assert!(group(&ctx, &announcement).is_some());
+        assert!(group(&ctx, &pins::message(3, 20)).is_none());
+        assert!(group(&ctx, &pins::message(4, 30)).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::{message::MessageFlags, ChannelType},
+    guild::Permissions,
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::permission_calculator::PermissionCalculator;
+
+use crate::{confirmation, jump_link, label, metrics, pins::PinApi, Context, DEFER_EPHEMERAL};
+
+/// Most channels whose pins are looked at, every one of them is a request
+const MAX_CHANNELS: usize = 25;
+/// Most pins listed per channel, so a single channel can't fill the whole response
+const MAX_PER_CHANNEL: usize = 10;
+/// Discord rejects messages with more characters than this
+const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// Handle `/my-pins`, which lists the pinned messages of the user in every channel they can see
+pub async fn my_pins(ctx: &Context, event: &Interaction, guild_id: Id<GuildMarker>) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    let (Some(user), Some(member)) = (event.author(), event.member.as_ref()) else {
+        return Ok(());
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let guild = match ctx.http.guild(guild_id).await {
+        Ok(response) => response.model().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "guild");
+            return Err(e.into());
+        }
+    };
+    let mut channels = match ctx.http.guild_channels(guild_id).await {
+        Ok(response) => response.models().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "guild_channels");
+            return Err(e.into());
+        }
+    };
+
+    // The permissions of the interaction only apply to this channel, the others need computing
+    let everyone = guild
+        .roles
+        .iter()
+        .find(|role| role.id.cast() == guild_id)
+        .map_or(Permissions::empty(), |role| role.permissions);
+    let roles: Vec<_> = guild
+        .roles
+        .iter()
+        .filter(|role| member.roles.contains(&role.id))
+        .map(|role| (role.id, role.permissions))
+        .collect();
+    let required = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY;
+
+    channels.retain(|channel| {
+        let Some(kind) = permission_kind(channel.kind) else {
+            return false;
+        };
+        PermissionCalculator::new(guild_id, user.id, everyone, &roles)
+            .owner_id(guild.owner_id)
+            .in_channel(
+                kind,
+                channel.permission_overwrites.as_deref().unwrap_or_default(),
+            )
+            .contains(required)
+    });
+    channels.sort_by_key(|channel| channel.position);
+    let skipped = channels.len().saturating_sub(MAX_CHANNELS);
+    channels.truncate(MAX_CHANNELS);
+
+    let mut sections = Vec::new();
+    for channel in &channels {
+        // We might not be able to see every channel the user can see
+        let pins = match ctx.http.pinned_messages(channel.id).await {
+            Ok(pins) => pins,
+            Err(e) => {
+                log::debug!("[{}] Skipping channel in my-pins: {}", channel.id, e);
+                continue;
+            }
+        };
+        let own: Vec<_> = pins
+            .iter()
+            .filter(|message| message.author.id == user.id)
+            .collect();
+        let mut lines: Vec<String> = own
+            .iter()
+            .take(MAX_PER_CHANNEL)
+            .map(|message| {
+                format!(
+                    "- [{}]({})",
+                    label::title(ctx, message),
+                    jump_link(guild_id, channel.id, message.id)
+                )
+            })
+            .collect();
+        if own.len() > MAX_PER_CHANNEL {
+            lines.push(format!("*and {} more*", own.len() - MAX_PER_CHANNEL));
+        }
+        if !lines.is_empty() {
+            sections.push(format!("<#{}>\n{}", channel.id, lines.join("\n")));
+        }
+    }
+
+    let mut notes = Vec::new();
+    if skipped > 0 {
+        notes.push(format!(
+            "*Only the first {MAX_CHANNELS} channels were searched, {skipped} were skipped.*"
+        ));
+    }
+
+    let mut content = if sections.is_empty() {
+        "None of your messages are pinned in the channels I searched.".to_owned()
+    } else {
+        "**Your pinned messages**".to_owned()
+    };
+    for (index, section) in sections.iter().enumerate() {
+        // Leave room for the notes about anything left out
+        if content.chars().count() + section.chars().count() > MAX_CONTENT_LENGTH - 200 {
+            notes.push(format!(
+                "*{} more channels didn't fit into this message.*",
+                sections.len() - index
+            ));
+            break;
+        }
+        content.push_str("\n\n");
+        content.push_str(section);
+    }
+    for note in notes {
+        content.push('\n');
+        content.push_str(&note);
+    }
+
+    client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// The kind of channel to compute the permissions for, `None` for channels without messages.
+///
+/// The calculator predates the text chat of voice channels and would drop the permissions to
+/// read it, which work the same as in text channels.
+fn permission_kind(kind: ChannelType) -> Option<ChannelType> {
+    match kind {
+        ChannelType::GuildText | ChannelType::GuildVoice | ChannelType::GuildStageVoice => {
+            Some(ChannelType::GuildText)
+        }
+        ChannelType::GuildAnnouncement => Some(ChannelType::GuildAnnouncement),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_of_voice_chats_are_readable() {
+        let required = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY;
+        let (guild_id, user_id) = (Id::new(1), Id::new(2));
+        let calculator = || PermissionCalculator::new(guild_id, user_id, required, &[]);
+
+        // Computed as a voice channel, the text permissions are dropped
+        let voice = calculator().in_channel(ChannelType::GuildVoice, &[]);
+        assert!(!voice.contains(required));
+        for kind in [ChannelType::GuildVoice, ChannelType::GuildStageVoice] {
+            let kind = permission_kind(kind).unwrap();
+            assert!(calculator().in_channel(kind, &[]).contains(required));
+        }
+        assert_eq!(permission_kind(ChannelType::GuildCategory), None);
+    }
+}
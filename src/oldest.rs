@@ -0,0 +1,165 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{message_component::MessageComponentInteractionData, Interaction},
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        MessageFlags,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    approval, confirmation, do_pin, ephemeral, events::PinEvent, gate, jump_link, locale, lock,
+    metrics, pinlog, pins::PinApi, Context, DEFER_EPHEMERAL,
+};
+
+/// Prefix of the unpin button custom id, followed by the channel and message id
+pub const BUTTON_PREFIX: &str = "unpin-oldest";
+
+/// Handle `/oldest-pin`, which shows the pin that has been around the longest
+pub async fn oldest_pin(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            return Err(e);
+        }
+    };
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+
+    // Pins are listed with the most recently pinned message first
+    let Some(message) = pins.last() else {
+        request
+            .content("There are no pinned messages in this channel yet.")?
+            .await?;
+        return Ok(());
+    };
+
+    let content = format!(
+        "The oldest of {} is a message by <@{}> from <t:{}:R>.",
+        confirmation::count(pins.len(), "pin", "pins", locale(&ctx.config, event)),
+        message.author.id,
+        message.timestamp.as_secs()
+    );
+    let link = link!("Message", jump_link(guild_id, channel_id, message.id));
+    let mut buttons = vec![link.into()];
+    if ctx.config.enable_unpin {
+        let unpin = Button {
+            style: ButtonStyle::Danger,
+            url: None,
+            custom_id: Some(format!("{BUTTON_PREFIX}:{channel_id}:{}", message.id)),
+            disabled: false,
+            label: Some("Unpin it".to_owned()),
+            emoji: None,
+        };
+        buttons.push(unpin.into());
+    }
+
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&[ActionRow {
+            components: buttons,
+        }
+        .into()])?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Unpin the message once the "Unpin it" button is clicked
+pub async fn unpin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut ids = data.custom_id.split(':').skip(1);
+    let (Some(guild_id), Some(user), Some(channel_id), Some(message_id)) = (
+        event.guild_id,
+        event.author(),
+        ids.next().and_then(|it| it.parse().ok()),
+        ids.next().and_then(|it| it.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    // Settings could have changed since the button was sent
+    let rejection = if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, false)
+    {
+        Some(content)
+    } else if ctx.store.guild(guild_id).unpin_approval {
+        Some(approval::REQUIRED.to_owned())
+    } else if lock::prevents_unpin(ctx, event, message_id) {
+        Some(lock::LOCKED.to_owned())
+    } else {
+        None
+    };
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{username} unpinned the oldest pin in {channel_name}");
+    if let Err(e) = do_pin(&ctx.http, channel_id, message_id, false, &reason).await {
+        metrics::observe_pin_error(&e, "delete_pin");
+        log::error!("Failed to process pin due to error: {}", e);
+        let response = ephemeral(ctx.config.pin_error_message(e.code()));
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    if ctx.store.remove_timed_unpin(message_id).await? {
+        ctx.scheduler.wake();
+    }
+    pinlog::record(
+        ctx,
+        PinEvent::new(false, guild_id, channel_id, message_id, user.id),
+    )
+    .await;
+
+    log::info!("[{}] {} unpinned the oldest pin", channel_id, username);
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message_id)
+    ));
+    let response = InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            content: Some("Unpinned the oldest pin.".to_owned()),
+            components: Some(button.into()),
+            ..Default::default()
+        }),
+    };
+    client
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
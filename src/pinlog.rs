@@ -0,0 +1,295 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+        Interaction,
+    },
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        MessageFlags,
+    },
+    guild::Permissions,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{GuildMarker, MessageMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    confirmation, ephemeral, events::PinEvent, jump_link, locale, store::PinAction, time, Context,
+};
+
+/// Prefix of the paging button custom ids, followed by the page they open
+pub const BUTTON_PREFIX: &str = "pin-log";
+const PAGE_SIZE: usize = 10;
+const DAY: u64 = 24 * 60 * 60;
+/// How often the configured retention is enforced
+const PURGE_INTERVAL: Duration = Duration::from_secs(DAY);
+
+/// Remember a successful pin or unpin done through the bot.
+///
+/// Every pin path calls this, it publishes the event and adds it to `/pin-log`, which is only
+/// kept with a database configured.
+pub async fn record(ctx: &Context, event: PinEvent) {
+    record_with_reason(ctx, event, None).await;
+}
+
+/// Same as [`record`], with the reason the user gave for it
+pub async fn record_with_reason(ctx: &Context, event: PinEvent, reason: Option<String>) {
+    let guild_id = event.guild_id;
+    let action = PinAction {
+        user_id: event.user_id,
+        channel_id: event.channel_id,
+        message_id: event.message_id,
+        pin: event.is_pin(),
+        timestamp: event.timestamp,
+        reason,
+    };
+    ctx.events.publish(event);
+    if !ctx.store.is_persistent() {
+        return;
+    }
+
+    if let Err(e) = ctx.store.add_pin_action(guild_id, action).await {
+        log::error!("Failed to record pin action: {e}");
+    }
+}
+
+/// Who pinned the message through the bot, if that's the last thing the pin log knows of it
+pub fn pinner(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    message_id: Id<MessageMarker>,
+) -> Option<Id<UserMarker>> {
+    ctx.store
+        .pin_actions(guild_id)
+        .iter()
+        .rfind(|action| action.message_id == message_id)
+        .filter(|action| action.pin)
+        .map(|action| action.user_id)
+}
+
+/// Handle `/pin-log`, which shows the first page of recent pin actions in the guild
+pub async fn pin_log(ctx: &Context, event: &Interaction, guild_id: Id<GuildMarker>) -> Result<()> {
+    let response = match rejection(ctx, event) {
+        Some(response) => response,
+        None => InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(page(ctx, guild_id, 0, locale(&ctx.config, event))),
+        },
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Handle `/pin-log-purge`, which removes the old entries of the log in the guild
+pub async fn purge(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let days = data
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Integer(value) => u64::try_from(value).ok(),
+            _ => None,
+        })
+        .or(ctx.config.pin_log_retention_days);
+
+    let response = match (rejection(ctx, event), days) {
+        (Some(response), _) => response,
+        (None, None) => ephemeral("Pick how many days of the log to keep."),
+        (None, Some(days)) => {
+            let removed = ctx
+                .store
+                .purge_pin_actions(Some(guild_id), cutoff(days))
+                .await?;
+            log::info!("[{guild_id}] Purged {removed} pin log entries older than {days} days");
+            let locale = locale(&ctx.config, event);
+            ephemeral(format!(
+                "Removed {} older than {} from the pin log.",
+                confirmation::count(removed, "entry", "entries", locale),
+                confirmation::count(days as usize, "day", "days", locale)
+            ))
+        }
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Run the background task, which removes expired log entries once a day
+pub fn spawn_purge(ctx: Arc<Context>) {
+    let Some(days) = ctx.config.pin_log_retention_days else {
+        return;
+    };
+    if !ctx.store.is_persistent() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        // The first tick completes right away, so entries which expired while offline go first
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match ctx.store.purge_pin_actions(None, cutoff(days)).await {
+                Ok(0) => {}
+                Ok(removed) => {
+                    log::info!("Purged {removed} pin log entries older than {days} days");
+                }
+                Err(e) => log::error!("Failed to purge the pin log: {e}"),
+            }
+        }
+    });
+}
+
+/// The timestamp before which entries are older than the given days
+fn cutoff(days: u64) -> u64 {
+    time::now().saturating_sub(days.saturating_mul(DAY))
+}
+
+/// Show another page once one of the paging buttons is clicked
+pub async fn turn_page(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let (Some(guild_id), Some(number)) = (
+        event.guild_id,
+        data.custom_id
+            .split(':')
+            .nth(1)
+            .and_then(|it| it.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    let response = match rejection(ctx, event) {
+        Some(response) => response,
+        None => InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(page(ctx, guild_id, number, locale(&ctx.config, event))),
+        },
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// The response if the user can't see the log
+pub fn rejection(ctx: &Context, event: &Interaction) -> Option<InteractionResponse> {
+    // The command is registered with this permission, but server admins can override that
+    let allowed = event
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|perms| perms.contains(Permissions::MANAGE_GUILD));
+
+    if !allowed {
+        Some(ephemeral(
+            "You need the **Manage Server** permission to do this.",
+        ))
+    } else if !ctx.store.is_persistent() {
+        Some(ephemeral(
+            "No database is configured, so pin actions are not recorded.",
+        ))
+    } else {
+        None
+    }
+}
+
+/// Render one page of the log, newest actions first
+fn page(
+    ctx: &Context,
+    guild_id: Id<GuildMarker>,
+    number: usize,
+    locale: &str,
+) -> InteractionResponseData {
+    let actions = ctx.store.pin_actions(guild_id);
+    let pages = actions.len().div_ceil(PAGE_SIZE).max(1);
+    let number = number.min(pages - 1);
+
+    let lines: Vec<String> = actions
+        .iter()
+        .rev()
+        .skip(number * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|action| describe(guild_id, action))
+        .collect();
+
+    let content = if lines.is_empty() {
+        "Nothing was pinned or unpinned through me yet.".to_owned()
+    } else {
+        format!(
+            "**Pin log** (page {} of {})\n{}",
+            confirmation::number(number + 1, locale),
+            confirmation::number(pages, locale),
+            lines.join("\n")
+        )
+    };
+
+    let buttons = [
+        ("Previous", number.checked_sub(1)),
+        ("Next", Some(number + 1).filter(|next| *next < pages)),
+    ]
+    .into_iter()
+    .map(|(label, target)| {
+        Button {
+            style: ButtonStyle::Secondary,
+            url: None,
+            // Disabled buttons still need a unique custom id
+            custom_id: Some(match target {
+                Some(target) => format!("{BUTTON_PREFIX}:{target}"),
+                None => format!("{BUTTON_PREFIX}:{label}"),
+            }),
+            disabled: target.is_none(),
+            label: Some(label.to_owned()),
+            emoji: None,
+        }
+        .into()
+    })
+    .collect();
+
+    InteractionResponseData {
+        allowed_mentions: Some(confirmation::NO_PINGS),
+        components: Some(vec![ActionRow {
+            components: buttons,
+        }
+        .into()]),
+        content: Some(content),
+        flags: Some(MessageFlags::EPHEMERAL),
+        ..Default::default()
+    }
+}
+
+fn describe(guild_id: Id<GuildMarker>, action: &PinAction) -> String {
+    let mut line = format!(
+        "<t:{}:R> <@{}> {} [a message]({}) in <#{}>",
+        action.timestamp,
+        action.user_id,
+        if action.pin { "pinned" } else { "unpinned" },
+        jump_link(guild_id, action.channel_id, action.message_id),
+        action.channel_id
+    );
+    if let Some(ref reason) = action.reason {
+        line.push_str(&format!(": *{reason}*"));
+    }
+    line
+}
@@ -0,0 +1,189 @@
+use twilight_http::{request::AuditLogReason, Client};
+use twilight_model::{
+    channel::Message,
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::error::PinbotError;
+
+/// Every pin related request the bot makes.
+///
+/// Discord moved pins to `/channels/{channel}/messages/pins`, while twilight 0.15 still uses
+/// the older `/channels/{channel}/pins` routes. Both are served for now, so once twilight
+/// follows this is the only place which has to change.
+pub trait PinApi {
+    async fn pin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError>;
+
+    async fn unpin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError>;
+
+    /// The messages currently pinned in the channel, newest first
+    async fn pinned_messages(&self, channel_id: Id<ChannelMarker>) -> anyhow::Result<Vec<Message>>;
+
+    /// Whether the message is currently pinned in the channel
+    async fn is_pinned(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+    ) -> anyhow::Result<bool> {
+        let pins = self.pinned_messages(channel_id).await?;
+        Ok(pins.iter().any(|message| message.id == message_id))
+    }
+}
+
+impl PinApi for Client {
+    async fn pin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError> {
+        // A reason past the length limit fails the request instead of the whole handler
+        self.create_pin(channel_id, message_id)
+            .reason(reason)
+            .map_err(|e| PinbotError::Other(e.into()))?
+            .await?;
+        Ok(())
+    }
+
+    async fn unpin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError> {
+        self.delete_pin(channel_id, message_id)
+            .reason(reason)
+            .map_err(|e| PinbotError::Other(e.into()))?
+            .await?;
+        Ok(())
+    }
+
+    async fn pinned_messages(&self, channel_id: Id<ChannelMarker>) -> anyhow::Result<Vec<Message>> {
+        Ok(self.pins(channel_id).await?.models().await?)
+    }
+}
+
+/// Records the requests instead of sending them, to check which of them the callers make
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingPins {
+    pub calls: std::sync::Mutex<Vec<Call>>,
+    /// The messages listed as pinned
+    pub pinned: Vec<Message>,
+    /// Fail listing the pins, like without access to the channel
+    pub failing: bool,
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Call {
+    pub method: &'static str,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Option<Id<MessageMarker>>,
+    pub reason: Option<String>,
+}
+
+#[cfg(test)]
+impl RecordingPins {
+    fn record(
+        &self,
+        method: &'static str,
+        channel_id: Id<ChannelMarker>,
+        message_id: Option<Id<MessageMarker>>,
+        reason: Option<&str>,
+    ) {
+        self.calls.lock().unwrap().push(Call {
+            method,
+            channel_id,
+            message_id,
+            reason: reason.map(ToOwned::to_owned),
+        });
+    }
+}
+
+#[cfg(test)]
+impl PinApi for RecordingPins {
+    async fn pin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError> {
+        self.record("pin", channel_id, Some(message_id), Some(reason));
+        Ok(())
+    }
+
+    async fn unpin(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        message_id: Id<MessageMarker>,
+        reason: &str,
+    ) -> Result<(), PinbotError> {
+        self.record("unpin", channel_id, Some(message_id), Some(reason));
+        Ok(())
+    }
+
+    async fn pinned_messages(&self, channel_id: Id<ChannelMarker>) -> anyhow::Result<Vec<Message>> {
+        self.record("pinned_messages", channel_id, None, None);
+        if self.failing {
+            anyhow::bail!("Missing Access");
+        }
+        Ok(self.pinned.clone())
+    }
+}
+
+/// A plain message, as it would be listed among the pins
+#[cfg(test)]
+pub fn message(channel_id: u64, message_id: u64) -> Message {
+    serde_json::from_value(serde_json::json!({
+        "id": message_id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "type": 0,
+        "content": "",
+        "author": {
+            "id": "1",
+            "username": "someone",
+            "discriminator": "0",
+            "avatar": null,
+        },
+        "timestamp": "2024-01-01T00:00:00+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": true,
+    }))
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalid_reasons_fail_the_request() {
+        // Validated before anything is sent, so no token is needed
+        let client = Client::new(String::new());
+        let reason = "x".repeat(600);
+        let result = client.pin(Id::new(1), Id::new(2), &reason).await;
+        assert!(matches!(result, Err(PinbotError::Other(_))));
+        let result = client.unpin(Id::new(1), Id::new(2), &reason).await;
+        assert!(matches!(result, Err(PinbotError::Other(_))));
+    }
+}
@@ -0,0 +1,71 @@
+use std::{sync::atomic::Ordering::Relaxed, time::Duration};
+
+use tracing as log;
+use twilight_gateway::{ConfigBuilder, MessageSender};
+use twilight_model::gateway::{
+    payload::outgoing::{update_presence::UpdatePresencePayload, UpdatePresence},
+    presence::{Activity, ActivityType, MinimalActivity, Status},
+};
+
+use crate::{confirmation, metrics};
+
+/// Discord only accepts a few presence updates per minute, so changes are sent at most this often
+const UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The presence text with the `{pins}` placeholder filled in
+fn render(template: &str) -> String {
+    confirmation::render(template, |key| match key {
+        "pins" => Some(metrics::PINS_TOTAL.load(Relaxed).to_string()),
+        _ => None,
+    })
+}
+
+fn activity(name: String) -> Activity {
+    MinimalActivity {
+        kind: ActivityType::Watching,
+        name,
+        url: None,
+    }
+    .into()
+}
+
+/// Show the presence right when the shard identifies
+pub fn configure(builder: ConfigBuilder, template: &str) -> ConfigBuilder {
+    let activity = activity(render(template));
+    match UpdatePresencePayload::new(vec![activity], false, None, Status::Online) {
+        Ok(presence) => builder.presence(presence),
+        Err(e) => {
+            log::warn!("Invalid presence: {e}");
+            builder
+        }
+    }
+}
+
+/// Keep the presence up to date while the template shows the number of pins
+pub fn spawn(sender: MessageSender, template: String) {
+    if !template.contains("{pins}") {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut current = render(&template);
+        let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let name = render(&template);
+            if name == current {
+                continue;
+            }
+
+            let activity = activity(name.clone());
+            let Ok(command) = UpdatePresence::new(vec![activity], false, None, Status::Online)
+            else {
+                continue;
+            };
+            match sender.command(&command) {
+                Ok(()) => current = name,
+                Err(e) => log::warn!("Failed to update presence: {e}"),
+            }
+        }
+    });
+}
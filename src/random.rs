@@ -0,0 +1,95 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        MessageFlags,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    config::Visibility, confirmation, defer, jump_link, metrics, pins::PinApi, Context,
+    DEFER_EPHEMERAL,
+};
+
+/// Longest excerpt of the message shown in the response
+const EXCERPT_LENGTH: usize = 500;
+
+/// Handle `/pin-random`, which shows a random pinned message of the channel
+pub async fn random_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let public = data.options.iter().any(|option| {
+        option.name == "public" && matches!(option.value, CommandOptionValue::Boolean(true))
+    });
+    // Picked by the user, so the configured visibility doesn't apply
+    let (response, flags) = if public {
+        (defer(Visibility::Public), MessageFlags::empty())
+    } else {
+        (DEFER_EPHEMERAL, MessageFlags::EPHEMERAL)
+    };
+
+    client
+        .create_response(event.id, &event.token, &response)
+        .await?;
+
+    let pins = match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => pins,
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            return Err(e);
+        }
+    };
+
+    let request = client.create_followup(&event.token).flags(flags);
+    if pins.is_empty() {
+        request
+            .content("There are no pinned messages in this channel yet.")?
+            .await?;
+        return Ok(());
+    }
+
+    // Good enough for picking a message, this doesn't need a proper random number generator
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |it| it.subsec_nanos());
+    let message = &pins[seed as usize % pins.len()];
+
+    let mut content = format!("\u{1F3B2} From the pins, by <@{}>:", message.author.id);
+    if !message.content.is_empty() {
+        let mut excerpt: String = message.content.chars().take(EXCERPT_LENGTH).collect();
+        if excerpt.len() < message.content.len() {
+            excerpt.push('\u{2026}');
+        }
+        content.push_str("\n>>> ");
+        content.push_str(&excerpt);
+    }
+
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message.id)
+    ));
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
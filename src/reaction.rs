@@ -0,0 +1,171 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing as log;
+use twilight_model::{
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        ReactionType,
+    },
+    gateway::GatewayReaction,
+    id::{marker::MessageMarker, Id},
+};
+
+use crate::{
+    confirmation, do_pin, events::PinEvent, freeze, highlights, jump_link, metrics, mirror, pinlog,
+    Context,
+};
+
+/// Pin messages once enough members reacted with the emoji
+#[derive(Deserialize)]
+pub struct ReactionPin {
+    /// Unicode emoji, or the id of a custom emoji
+    #[serde(default = "pin_emoji")]
+    pub emoji: String,
+    /// Number of reactions needed to pin the message
+    pub threshold: u64,
+    /// Unpin community pins again once they drop below the threshold
+    #[serde(default)]
+    pub unpin_below: bool,
+}
+
+fn pin_emoji() -> String {
+    "\u{1F4CC}".to_owned()
+}
+
+impl ReactionPin {
+    fn matches(&self, emoji: &ReactionType) -> bool {
+        emoji_matches(&self.emoji, emoji)
+    }
+}
+
+/// Whether the reaction is the unicode emoji, or the custom emoji with this id
+pub fn emoji_matches(emoji: &str, reaction: &ReactionType) -> bool {
+    match reaction {
+        ReactionType::Unicode { name } => name == emoji,
+        ReactionType::Custom { id, .. } => id.to_string() == emoji,
+    }
+}
+
+/// The id of a custom emoji like `<:name:id>`, unicode emojis are returned as they are
+pub fn emoji_id(input: &str) -> String {
+    let input = input.trim();
+    input
+        .strip_prefix('<')
+        .and_then(|it| it.strip_suffix('>'))
+        .and_then(|it| it.rsplit(':').next())
+        .unwrap_or(input)
+        .to_owned()
+}
+
+/// Messages pinned by reactions since the bot started, the only ones it unpins again
+#[derive(Default)]
+pub struct CommunityPins {
+    messages: Mutex<HashSet<Id<MessageMarker>>>,
+}
+
+/// Pin or unpin the message if its reactions crossed the threshold
+pub async fn handle(ctx: &Context, reaction: &GatewayReaction) -> Result<()> {
+    let Some(ref config) = ctx.config.reaction_pin else {
+        return Ok(());
+    };
+    let Some(guild_id) = reaction.guild_id else {
+        return Ok(());
+    };
+    if !config.matches(&reaction.emoji)
+        || !ctx.config.is_guild_allowed(guild_id)
+        || freeze::frozen_until(ctx, reaction.channel_id).is_some()
+    {
+        return Ok(());
+    }
+
+    // Reaction events don't carry the total, so it has to be looked up each time
+    let channel_id = reaction.channel_id;
+    let message = ctx
+        .http
+        .message(channel_id, reaction.message_id)
+        .await?
+        .model()
+        .await?;
+    let count = message
+        .reactions
+        .iter()
+        .find(|it| config.matches(&it.emoji))
+        .map_or(0, |it| it.count);
+
+    let pin = if count >= config.threshold && !message.pinned {
+        if !ctx.config.enable_pin {
+            return Ok(());
+        }
+        true
+    } else if count < config.threshold && message.pinned && config.unpin_below {
+        let pinned_by_us = ctx
+            .community_pins
+            .messages
+            .lock()
+            .unwrap()
+            .contains(&message.id);
+        if !pinned_by_us || !ctx.config.enable_unpin || ctx.store.is_locked(message.id) {
+            return Ok(());
+        }
+        false
+    } else {
+        return Ok(());
+    };
+
+    let reason = if pin {
+        format!(
+            "{} reacted with {}",
+            confirmation::count(count as usize, "member", "members", "en"),
+            config.emoji
+        )
+    } else {
+        format!(
+            "Fewer than {} members reacted with {}",
+            config.threshold, config.emoji
+        )
+    };
+    if let Err(e) = do_pin(&ctx.http, channel_id, message.id, pin, &reason).await {
+        metrics::observe_pin_error(&e, if pin { "create_pin" } else { "delete_pin" });
+        return Err(e.into());
+    }
+
+    {
+        let mut pins = ctx.community_pins.messages.lock().unwrap();
+        if pin {
+            pins.insert(message.id);
+        } else {
+            pins.remove(&message.id);
+        }
+    }
+
+    // The member whose reaction crossed the threshold counts as the one who pinned it
+    pinlog::record_with_reason(
+        ctx,
+        PinEvent::new(pin, guild_id, channel_id, message.id, reaction.user_id)
+            .author(message.author.id),
+        Some(reason),
+    )
+    .await;
+
+    if pin {
+        highlights::post(ctx, guild_id, &message).await;
+        mirror::post(ctx, guild_id, &message).await;
+    }
+
+    let content = confirmation::community(&ctx.config, message.author.id, pin);
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message.id)
+    ));
+
+    log::info!("[{}] {}", channel_id, content);
+    ctx.http
+        .create_message(channel_id)
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::{
+        message_component::MessageComponentInteractionData, modal::ModalInteractionData,
+        Interaction,
+    },
+    channel::message::component::{ActionRow, Button, ButtonStyle, TextInput, TextInputStyle},
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{apply_pin, ephemeral, error, gate, metrics, pinner, Context};
+
+/// Prefix of the modal custom id, followed by the channel and message id
+pub const MODAL_PREFIX: &str = "pin-reason";
+/// Prefix of the button which opens the modal again, followed by the channel and message id
+pub const BUTTON_PREFIX: &str = "pin-reason-retry";
+const REASON_INPUT: &str = "reason";
+/// Leaves room for our own prefix within the 512 characters of the audit log reason, which
+/// is cut off past that anyway
+const MAX_REASON_LENGTH: u16 = 400;
+
+/// Ask the user why they want to pin the message
+pub async fn prompt(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> Result<()> {
+    let input = TextInput {
+        custom_id: REASON_INPUT.to_owned(),
+        label: "Reason".to_owned(),
+        max_length: Some(MAX_REASON_LENGTH),
+        min_length: Some(1),
+        placeholder: Some("Why should this message be pinned?".to_owned()),
+        required: Some(true),
+        style: TextInputStyle::Paragraph,
+        value: None,
+    };
+
+    let response = InteractionResponse {
+        kind: InteractionResponseType::Modal,
+        data: Some(InteractionResponseData {
+            custom_id: Some(format!("{MODAL_PREFIX}:{channel_id}:{message_id}")),
+            title: Some("Pin Message".to_owned()),
+            components: Some(row!(input).into()),
+            ..Default::default()
+        }),
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Pin the message once the user gave a reason, or offer to try again without one
+pub async fn submit(ctx: &Context, event: &Interaction, data: &ModalInteractionData) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut ids = data.custom_id.split(':').skip(1);
+    let (Some(guild_id), Some(user), Some(channel_id), Some(message_id)) = (
+        event.guild_id,
+        pinner(event),
+        ids.next().and_then(|it| it.parse().ok()),
+        ids.next().and_then(|it| it.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    // The channel could have been frozen, or the role changed, while the modal was open
+    if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, true) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    let reason = data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find(|component| component.custom_id == REASON_INPUT)
+        .and_then(|component| component.value.as_deref())
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    // Discord only checks that the field isn't empty, whitespace gets through.
+    // A modal can't be answered with another modal, so a button opens it again.
+    let Some(reason) = reason else {
+        let button = Button {
+            style: ButtonStyle::Primary,
+            url: None,
+            custom_id: Some(format!("{BUTTON_PREFIX}:{channel_id}:{message_id}")),
+            disabled: false,
+            label: Some("Add a reason".to_owned()),
+            emoji: None,
+        };
+        let mut response = ephemeral("A reason is required to pin messages here.");
+        if let Some(ref mut data) = response.data {
+            data.components = Some(row!(button).into());
+        }
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    };
+
+    // The modal only carries the ids, the confirmation needs the whole message
+    let message = match ctx.http.message(channel_id, message_id).await {
+        Ok(response) => response.model().await?,
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_MESSAGE) => {
+            client
+                .create_response(
+                    event.id,
+                    &event.token,
+                    &ephemeral("That message doesn't exist anymore."),
+                )
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            metrics::observe_http_error(&e, "message");
+            return Err(e.into());
+        }
+    };
+
+    apply_pin(ctx, event, guild_id, &message, user, true, Some(reason)).await
+}
+
+/// Open the modal again once the "Add a reason" button is clicked
+pub async fn retry(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let mut ids = data.custom_id.split(':').skip(1);
+    let (Some(channel_id), Some(message_id)) = (
+        ids.next().and_then(|it| it.parse().ok()),
+        ids.next().and_then(|it| it.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+    prompt(ctx, event, channel_id, message_id).await
+}
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        AllowedMentions, MessageFlags,
+    },
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    do_pin, ephemeral, error, events::PinEvent, freeze, gate, jump_link, link, metrics, pinlog,
+    store::ScheduledPin, time, Context, DEFER_EPHEMERAL,
+};
+
+/// How long to wait before trying a failed pin again
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Handle `/schedule-pin`, which pins a message of this channel at a later time
+pub async fn schedule_pin(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut link = None;
+    let mut pin_at = None;
+    for option in &data.options {
+        match (option.name.as_str(), &option.value) {
+            ("message", CommandOptionValue::String(value)) => link = link::parse_message_ref(value),
+            ("time", CommandOptionValue::String(value)) => pin_at = time::parse_time(value),
+            _ => {}
+        }
+    }
+
+    let now = time::now();
+    // Links to other channels are rejected, since the user might not be able to see them
+    let link = link.filter(|it| it.is_in(guild_id, channel_id));
+    let pin_at = pin_at.filter(|it| *it > now);
+    let rejection = gate::rejection(ctx, event, guild_id, channel_id, true).or_else(|| {
+        if link.is_none() {
+            Some("Give me a link to a message of this channel.".to_owned())
+        } else if pin_at.is_none() {
+            Some(
+                "Give me a time in the future, like `2h`, `1d 12h` or `2024-05-01 18:30` (UTC)."
+                    .to_owned(),
+            )
+        } else {
+            None
+        }
+    });
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let (Some(link), Some(pin_at), Some(user)) = (link, pin_at, event.author()) else {
+        return Ok(());
+    };
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+
+    let message = match ctx.http.message(channel_id, link.message_id).await {
+        Ok(response) => Some(response.model().await?),
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_MESSAGE) => None,
+        Err(e) => {
+            metrics::observe_http_error(&e, "message");
+            return Err(e.into());
+        }
+    };
+    let Some(message) = message else {
+        request.content("That message doesn't exist.")?.await?;
+        return Ok(());
+    };
+    if message.pinned {
+        request.content("That message is already pinned.")?.await?;
+        return Ok(());
+    }
+
+    let entry = ScheduledPin {
+        guild_id,
+        channel_id,
+        message_id: message.id,
+        user_id: user.id,
+        pin_at,
+    };
+    ctx.store.add_scheduled_pin(entry).await?;
+    ctx.scheduler.wake();
+
+    log::info!(
+        "[{}] {} scheduled message {} to be pinned at {}",
+        channel_id,
+        user.name,
+        message.id,
+        pin_at
+    );
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message.id)
+    ));
+    request
+        .components(&button)?
+        .content(&format!("Scheduled to pin at <t:{pin_at}:f>."))?
+        .await?;
+    Ok(())
+}
+
+/// Pin the scheduled message, or retry a while after `now` if that isn't possible right now
+pub async fn fire(ctx: &Context, entry: &ScheduledPin, now: u64) -> Result<()> {
+    // The guild might have changed its mind since the pin was scheduled
+    if !ctx.config.is_guild_allowed(entry.guild_id) {
+        log::info!(
+            "[{}] Dropping scheduled pin of message {} in a guild which is no longer allowed",
+            entry.channel_id,
+            entry.message_id
+        );
+        ctx.store.remove_scheduled_pin(entry.message_id).await?;
+        return Ok(());
+    }
+    if !ctx.config.enable_pin {
+        return cancel(ctx, entry, "pinning is disabled").await;
+    }
+    if let Some(until) = freeze::frozen_until(ctx, entry.channel_id) {
+        log::info!(
+            "[{}] Postponing scheduled pin of message {} until the freeze ends",
+            entry.channel_id,
+            entry.message_id
+        );
+        ctx.store
+            .postpone_scheduled_pin(entry.message_id, until)
+            .await?;
+        return Ok(());
+    }
+
+    let reason = format!("Pin scheduled by user {}", entry.user_id);
+    match do_pin(&ctx.http, entry.channel_id, entry.message_id, true, &reason).await {
+        Ok(()) => {
+            ctx.store.remove_scheduled_pin(entry.message_id).await?;
+            pinlog::record(
+                ctx,
+                PinEvent::new(
+                    true,
+                    entry.guild_id,
+                    entry.channel_id,
+                    entry.message_id,
+                    entry.user_id,
+                ),
+            )
+            .await;
+            log::info!(
+                "[{}] Pinned scheduled message {}",
+                entry.channel_id,
+                entry.message_id
+            );
+            Ok(())
+        }
+        Err(e)
+            if matches!(
+                e.code(),
+                Some(error::UNKNOWN_CHANNEL | error::UNKNOWN_MESSAGE)
+            ) =>
+        {
+            log::info!(
+                "[{}] Dropping scheduled pin of deleted message {}",
+                entry.channel_id,
+                entry.message_id
+            );
+            cancel(ctx, entry, "the message was deleted").await
+        }
+        Err(e) => match permanent_failure(e.code()) {
+            Some(why) => {
+                metrics::observe_pin_error(&e, "create_pin");
+                log::warn!(
+                    "[{}] Dropping scheduled pin of message {}: {e}",
+                    entry.channel_id,
+                    entry.message_id
+                );
+                cancel(ctx, entry, why).await
+            }
+            None => {
+                metrics::observe_pin_error(&e, "create_pin");
+                let retry_at = now.saturating_add(RETRY_DELAY.as_secs());
+                ctx.store
+                    .postpone_scheduled_pin(entry.message_id, retry_at)
+                    .await?;
+                Err(e.into())
+            }
+        },
+    }
+}
+
+/// Why the pin can never succeed, for the errors which retrying doesn't fix
+fn permanent_failure(code: Option<u64>) -> Option<&'static str> {
+    match code? {
+        error::MAX_PINS => Some("the channel already has the maximum number of pins"),
+        error::MISSING_ACCESS | error::MISSING_PERMISSIONS => {
+            Some("I'm missing permissions in this channel")
+        }
+        _ => None,
+    }
+}
+
+/// Drop the scheduled pin and let the user know why
+async fn cancel(ctx: &Context, entry: &ScheduledPin, why: &str) -> Result<()> {
+    ctx.store.remove_scheduled_pin(entry.message_id).await?;
+    // Fails if the whole channel is gone, or we can't see it anymore
+    if let Err(e) = notify_cancelled(ctx, entry, why).await {
+        log::warn!("Failed to report cancelled scheduled pin: {e}");
+    }
+    Ok(())
+}
+
+async fn notify_cancelled(ctx: &Context, entry: &ScheduledPin, why: &str) -> Result<()> {
+    let content = format!(
+        "<@{}> I cancelled the pin you scheduled, since {why}.",
+        entry.user_id
+    );
+    ctx.http
+        .create_message(entry.channel_id)
+        .allowed_mentions(Some(&only_pings(entry)))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// The notice only pings the user who scheduled the pin, whatever the reason says
+fn only_pings(entry: &ScheduledPin) -> AllowedMentions {
+    AllowedMentions {
+        users: vec![entry.user_id],
+        ..AllowedMentions::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_some_failures_are_permanent() {
+        assert!(permanent_failure(Some(error::MAX_PINS)).is_some());
+        assert!(permanent_failure(Some(error::MISSING_ACCESS)).is_some());
+        assert!(permanent_failure(Some(error::MISSING_PERMISSIONS)).is_some());
+        assert!(permanent_failure(Some(error::UNKNOWN_INTERACTION)).is_none());
+        assert!(permanent_failure(None).is_none());
+    }
+
+    #[test]
+    fn cancellations_only_ping_who_scheduled_the_pin() {
+        let entry = ScheduledPin {
+            guild_id: Id::new(1),
+            channel_id: Id::new(2),
+            message_id: Id::new(3),
+            user_id: Id::new(4),
+            pin_at: 0,
+        };
+        let mentions = only_pings(&entry);
+        assert_eq!(mentions.users, [Id::new(4)]);
+        assert!(mentions.parse.is_empty());
+        assert!(mentions.roles.is_empty());
+        assert!(!mentions.replied_user);
+    }
+}
@@ -0,0 +1,407 @@
+//! Where the bot token is read from at startup, instead of the `token` field of the config.
+//!
+//! Set `token_source` to one of these:
+//!
+//! ```json
+//! { "type": "env", "variable": "DISCORD_TOKEN" }
+//! { "type": "file", "path": "/run/secrets/discord_token" }
+//! { "type": "vault", "address": "https://vault.example.com:8200", "path": "pinbot" }
+//! { "type": "aws", "secret_id": "pinbot/discord", "region": "eu-central-1" }
+//! ```
+//!
+//! Vault reads the `token` field of a KV version 2 secret from the `secret` mount, both can be
+//! changed with `key` and `mount`. It authenticates with the `VAULT_TOKEN` environment variable
+//! and needs the `vault` feature.
+//!
+//! AWS Secrets Manager uses the whole secret string as token, or its field named by `key` if the
+//! secret is JSON. It authenticates with `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, for
+//! temporary credentials, `AWS_SESSION_TOKEN`, and needs the `aws` feature.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::config::Token;
+
+/// A place the bot token can be loaded from
+pub trait SecretSource {
+    /// Load the token, failing with an error that says what to check in this backend
+    async fn fetch_token(&self) -> Result<Token>;
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TokenSource {
+    Env(EnvSource),
+    File(FileSource),
+    Vault(VaultSource),
+    Aws(AwsSource),
+}
+
+impl SecretSource for TokenSource {
+    async fn fetch_token(&self) -> Result<Token> {
+        match self {
+            Self::Env(source) => source.fetch_token().await,
+            Self::File(source) => source.fetch_token().await,
+            Self::Vault(source) => source.fetch_token().await,
+            Self::Aws(source) => source.fetch_token().await,
+        }
+    }
+}
+
+impl TokenSource {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Env(_) => "env",
+            Self::File(_) => "file",
+            Self::Vault(_) => "vault",
+            Self::Aws(_) => "aws",
+        }
+    }
+}
+
+/// The token is in an environment variable
+#[derive(Deserialize)]
+pub struct EnvSource {
+    #[serde(default = "token_variable")]
+    variable: String,
+}
+
+fn token_variable() -> String {
+    "DISCORD_TOKEN".to_owned()
+}
+
+impl SecretSource for EnvSource {
+    async fn fetch_token(&self) -> Result<Token> {
+        match std::env::var(&self.variable) {
+            Ok(token) if !token.trim().is_empty() => Ok(Token::from(token.trim().to_owned())),
+            Ok(_) => bail!(
+                "The environment variable {} with the token is empty",
+                self.variable
+            ),
+            Err(_) => bail!(
+                "The environment variable {} with the token is not set",
+                self.variable
+            ),
+        }
+    }
+}
+
+/// The token is the only content of a file, like a mounted Docker or Kubernetes secret
+#[derive(Deserialize)]
+pub struct FileSource {
+    path: String,
+}
+
+impl SecretSource for FileSource {
+    async fn fetch_token(&self) -> Result<Token> {
+        let content = Token::from(
+            tokio::fs::read_to_string(&self.path)
+                .await
+                .with_context(|| format!("Failed to read the token from {}", self.path))?,
+        );
+        let token = content.expose().trim();
+        if token.is_empty() {
+            bail!("The token file {} is empty", self.path);
+        }
+        Ok(Token::from(token.to_owned()))
+    }
+}
+
+/// The token is a field of a KV version 2 secret in HashiCorp Vault
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "vault"), allow(dead_code))]
+pub struct VaultSource {
+    /// Like `https://vault.example.com:8200`
+    address: String,
+    #[serde(default = "vault_mount")]
+    mount: String,
+    path: String,
+    #[serde(default = "vault_key")]
+    key: String,
+}
+
+fn vault_mount() -> String {
+    "secret".to_owned()
+}
+
+fn vault_key() -> String {
+    "token".to_owned()
+}
+
+impl SecretSource for VaultSource {
+    #[cfg(feature = "vault")]
+    async fn fetch_token(&self) -> Result<Token> {
+        use hyper::{Body, Method, Request, StatusCode};
+
+        let Ok(vault_token) = std::env::var("VAULT_TOKEN") else {
+            bail!("VAULT_TOKEN is not set, Vault needs it to authenticate");
+        };
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.address.trim_end_matches('/'),
+            self.mount,
+            self.path.trim_start_matches('/')
+        );
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header("X-Vault-Token", vault_token)
+            .body(Body::empty())
+            .with_context(|| format!("Invalid Vault address {:?}", self.address))?;
+
+        let (status, body) = send(request)
+            .await
+            .with_context(|| format!("Failed to reach Vault at {}", self.address))?;
+        match status {
+            StatusCode::OK => {}
+            StatusCode::FORBIDDEN => bail!(
+                "Vault denied access to {}/{}, check VAULT_TOKEN and its policies",
+                self.mount,
+                self.path
+            ),
+            StatusCode::NOT_FOUND => bail!(
+                "Vault has no secret at {}/{}, check mount and path",
+                self.mount,
+                self.path
+            ),
+            status => bail!("Vault answered with {status}: {}", body["errors"]),
+        }
+
+        match body["data"]["data"][&self.key].as_str() {
+            Some(token) if !token.is_empty() => Ok(Token::from(token.to_owned())),
+            _ => bail!(
+                "The Vault secret {}/{} has no {:?} field with the token",
+                self.mount,
+                self.path,
+                self.key
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "vault"))]
+    async fn fetch_token(&self) -> Result<Token> {
+        bail!("Reading the token from Vault needs the vault feature, build with `--features vault`")
+    }
+}
+
+/// The token is a secret in AWS Secrets Manager
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "aws"), allow(dead_code))]
+pub struct AwsSource {
+    /// Name or ARN of the secret
+    secret_id: String,
+    region: String,
+    /// Field with the token, if the secret string is JSON
+    #[serde(default)]
+    key: Option<String>,
+}
+
+impl SecretSource for AwsSource {
+    #[cfg(feature = "aws")]
+    async fn fetch_token(&self) -> Result<Token> {
+        use hyper::StatusCode;
+
+        let (status, body) = send(aws::get_secret_value(&self.secret_id, &self.region)?)
+            .await
+            .with_context(|| format!("Failed to reach AWS Secrets Manager in {}", self.region))?;
+        if status != StatusCode::OK {
+            let kind = body["__type"].as_str().unwrap_or("unknown error");
+            let message = body["message"]
+                .as_str()
+                .or_else(|| body["Message"].as_str())
+                .unwrap_or_default();
+            let hint = match kind.rsplit('#').next().unwrap_or(kind) {
+                "ResourceNotFoundException" => ", check secret_id and region",
+                "AccessDeniedException" => ", check the permissions of the AWS credentials",
+                "UnrecognizedClientException" | "InvalidSignatureException" => {
+                    ", check the AWS credentials"
+                }
+                _ => "",
+            };
+            bail!(
+                "AWS Secrets Manager failed to return {}: {kind} {message}{hint}",
+                self.secret_id
+            );
+        }
+
+        let Some(secret) = body["SecretString"].as_str() else {
+            bail!(
+                "The AWS secret {} has no secret string, binary secrets aren't supported",
+                self.secret_id
+            );
+        };
+        let token = match self.key {
+            Some(ref key) => serde_json::from_str::<serde_json::Value>(secret)
+                .ok()
+                .and_then(|json| json[key].as_str().map(ToOwned::to_owned)),
+            None => Some(secret.trim().to_owned()),
+        };
+        match token {
+            Some(token) if !token.is_empty() => Ok(Token::from(token)),
+            _ => bail!(
+                "The AWS secret {} has no token{}",
+                self.secret_id,
+                self.key
+                    .as_ref()
+                    .map(|key| format!(" in its {key:?} field"))
+                    .unwrap_or_default()
+            ),
+        }
+    }
+
+    #[cfg(not(feature = "aws"))]
+    async fn fetch_token(&self) -> Result<Token> {
+        bail!(
+            "Reading the token from AWS Secrets Manager needs the aws feature, build with \
+             `--features aws`"
+        )
+    }
+}
+
+/// Send the request and parse the response as JSON, which is `null` if it isn't
+#[cfg(any(feature = "vault", feature = "aws"))]
+async fn send(
+    request: hyper::Request<hyper::Body>,
+) -> Result<(hyper::StatusCode, serde_json::Value)> {
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = hyper::Client::builder().build::<_, hyper::Body>(https);
+    let response = client.request(request).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok((
+        status,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    ))
+}
+
+/// Requests signed with AWS Signature Version 4, which is all Secrets Manager needs
+#[cfg(feature = "aws")]
+mod aws {
+    use anyhow::{Context, Result};
+    use hyper::{Body, Method, Request};
+    use ring::{digest, hmac};
+
+    use crate::{config::Token, time};
+
+    const SERVICE: &str = "secretsmanager";
+    const TARGET: &str = "secretsmanager.GetSecretValue";
+    const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+    pub fn get_secret_value(secret_id: &str, region: &str) -> Result<Request<Body>> {
+        let access_key = std::env::var("AWS_ACCESS_KEY_ID")
+            .context("AWS_ACCESS_KEY_ID is not set, AWS Secrets Manager needs it")?;
+        let secret_key = Token::from(
+            std::env::var("AWS_SECRET_ACCESS_KEY")
+                .context("AWS_SECRET_ACCESS_KEY is not set, AWS Secrets Manager needs it")?,
+        );
+        let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
+
+        let host = format!("{SERVICE}.{region}.amazonaws.com");
+        let body = serde_json::json!({ "SecretId": secret_id }).to_string();
+        let (date, amz_date) = timestamps(time::now());
+
+        // Headers have to be signed in alphabetical order
+        let mut headers = vec![
+            ("content-type", CONTENT_TYPE.to_owned()),
+            ("host", host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(ref token) = session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        headers.push(("x-amz-target", TARGET.to_owned()));
+
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+        let canonical_request = format!(
+            "POST\n/\n\n{canonical_headers}\n{signed_headers}\n{}",
+            sha256(body.as_bytes())
+        );
+
+        let scope = format!("{date}/{region}/{SERVICE}/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            sha256(canonical_request.as_bytes())
+        );
+        let mut key = sign(
+            format!("AWS4{}", secret_key.expose()).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [region, SERVICE, "aws4_request"] {
+            key = sign(&key, part.as_bytes());
+        }
+        let signature = hex(&sign(&key, string_to_sign.as_bytes()));
+
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("https://{host}/"))
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={access_key}/{scope}, \
+                     SignedHeaders={signed_headers}, Signature={signature}"
+                ),
+            );
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        request
+            .body(Body::from(body))
+            .with_context(|| format!("Invalid AWS region {region:?}"))
+    }
+
+    fn sign(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+        hmac::sign(&key, data).as_ref().to_vec()
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        hex(digest::digest(&digest::SHA256, data).as_ref())
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// The date as `20240501` and the time as `20240501T183000Z`, both in UTC
+    fn timestamps(unix: u64) -> (String, String) {
+        let days = unix / 86_400;
+        let seconds = unix % 86_400;
+
+        // Civil date from days since 1970, see http://howardhinnant.github.io/date_algorithms.html
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let day_of_era = z % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        let date = format!("{year:04}{month:02}{day:02}");
+        let time = format!(
+            "{date}T{:02}{:02}{:02}Z",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        );
+        (date, time)
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{message_component::MessageComponentInteractionData, Interaction},
+    channel::{
+        message::{
+            component::{ActionRow, SelectMenu, SelectMenuOption},
+            MessageType,
+        },
+        Message,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{apply_pin, ephemeral, error, gate, metrics, reason, Context};
+
+/// Custom id of the select menu, the options carry the message ids
+pub const MENU_ID: &str = "pin-select";
+/// Select menus hold at most 25 options
+const CANDIDATES: u16 = 25;
+/// Longest label Discord accepts for an option
+const LABEL_LENGTH: usize = 100;
+
+/// Handle `/pin-select`, which offers the recent messages of this channel to pick one to pin
+pub async fn pin_select(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, true) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    let messages = match ctx
+        .http
+        .channel_messages(channel_id)
+        .limit(CANDIDATES)?
+        .await
+    {
+        Ok(response) => response.models().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "channel_messages");
+            log::warn!("[{}] Failed to load recent messages: {}", channel_id, e);
+            let response = ephemeral(ctx.config.pin_error_message(error::api_code(&e)));
+            client
+                .create_response(event.id, &event.token, &response)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // System messages can't be pinned, and pinned ones would only be pinned again
+    let options: Vec<SelectMenuOption> = messages
+        .iter()
+        .filter(|message| !message.pinned)
+        .filter(|message| matches!(message.kind, MessageType::Regular | MessageType::Reply))
+        .map(|message| SelectMenuOption {
+            default: false,
+            description: Some(format!("by {}", message.author.name)),
+            emoji: None,
+            label: label(message),
+            value: message.id.to_string(),
+        })
+        .collect();
+    if options.is_empty() {
+        let response = ephemeral("There are no recent messages to pin in this channel.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    let menu = SelectMenu {
+        custom_id: MENU_ID.to_owned(),
+        disabled: false,
+        max_values: Some(1),
+        min_values: Some(1),
+        options,
+        placeholder: Some("Pick a message".to_owned()),
+    };
+    let mut response = ephemeral("Which message should I pin?");
+    if let Some(ref mut data) = response.data {
+        data.components = Some(row!(menu).into());
+    }
+    client
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Pin the message once it is picked from the menu
+pub async fn select(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let (Some(guild_id), Some(user), Some(channel_id)) = (
+        event.guild_id,
+        event.author(),
+        event.channel.as_ref().map(|channel| channel.id),
+    ) else {
+        return Ok(());
+    };
+    let Some(message_id) = data.values.first().and_then(|it| it.parse().ok()) else {
+        return Ok(());
+    };
+
+    // Settings could have changed since the menu was sent
+    if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, true) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    // The menu might be old, so the message could be gone or pinned by now
+    let message = match ctx.http.message(channel_id, message_id).await {
+        Ok(response) => response.model().await?,
+        Err(e) if error::api_code(&e) == Some(error::UNKNOWN_MESSAGE) => {
+            let response = InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(InteractionResponseData {
+                    content: Some(
+                        "That message doesn't exist anymore. Use `/pin-select` to pick another one."
+                            .to_owned(),
+                    ),
+                    components: Some(Vec::new()),
+                    ..Default::default()
+                }),
+            };
+            client
+                .create_response(event.id, &event.token, &response)
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            metrics::observe_http_error(&e, "message");
+            return Err(e.into());
+        }
+    };
+    if message.pinned {
+        client
+            .create_response(
+                event.id,
+                &event.token,
+                &ephemeral("That message is already pinned."),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    if ctx.config.require_pin_reason {
+        return reason::prompt(ctx, event, channel_id, message.id).await;
+    }
+
+    apply_pin(ctx, event, guild_id, &message, user, true, None).await
+}
+
+/// The start of the message, as the label of its option
+fn label(message: &Message) -> String {
+    let content = message.content.replace('\n', " ");
+    let content = content.trim();
+    if content.is_empty() {
+        return "Message without text".to_owned();
+    }
+
+    let mut label: String = content.chars().take(LABEL_LENGTH - 1).collect();
+    if label.len() < content.len() {
+        label.push('\u{2026}');
+    }
+    label
+}
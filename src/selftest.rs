@@ -0,0 +1,102 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::Interaction,
+    id::{
+        marker::{ChannelMarker, MessageMarker},
+        Id,
+    },
+};
+
+use crate::{
+    ephemeral, error, error::PinbotError, lock, metrics, pins::PinApi, Context, DEFER_EPHEMERAL,
+};
+
+const REASON: &str = "Self test";
+
+/// Handle `/selftest`, which goes through a whole pin cycle with a throwaway message
+pub async fn self_test(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    if !lock::can_manage_guild(event) {
+        let response = ephemeral("You need the **Manage Server** permission to do this.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let content = match run(ctx, channel_id).await {
+        Ok(()) => {
+            "\u{2705} Self test passed, I can send, pin, unpin and delete messages here.".to_owned()
+        }
+        Err((step, e)) => {
+            metrics::observe_pin_error(&e, "selftest");
+            log::warn!("[{}] Self test failed to {}: {}", channel_id, step, e);
+            let hint = match e.code() {
+                Some(error::MISSING_PERMISSIONS) => match step {
+                    "send a message" => " I'm missing **Send Messages** here.",
+                    _ => " I'm missing **Manage Messages** here.",
+                },
+                Some(error::MISSING_ACCESS) => " I can't see this channel.",
+                _ => "",
+            };
+            format!("\u{274C} Self test failed to {step}.{hint}\n`{e}`")
+        }
+    };
+
+    client
+        .create_followup(&event.token)
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Send, pin, unpin and delete a message, returns the step that failed first
+async fn run(
+    ctx: &Context,
+    channel_id: Id<ChannelMarker>,
+) -> Result<(), (&'static str, PinbotError)> {
+    let message = ctx
+        .http
+        .create_message(channel_id)
+        .content("Self test, this message is removed in a moment.")
+        .expect("Self test message is valid")
+        .await
+        .map_err(|e| ("send a message", e.into()))?;
+    // The message was sent, so an unreadable response is no reason to fail the test
+    let Ok(message) = message.model().await else {
+        return Ok(());
+    };
+
+    let result = pin_cycle(ctx, channel_id, message.id).await;
+
+    // Clean up even if pinning failed, the message is useless either way
+    let deleted = ctx.http.delete_message(channel_id, message.id).await;
+    result?;
+    deleted.map_err(|e| ("delete the message", e.into()))?;
+    Ok(())
+}
+
+async fn pin_cycle(
+    ctx: &Context,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> Result<(), (&'static str, PinbotError)> {
+    ctx.http
+        .pin(channel_id, message_id, REASON)
+        .await
+        .map_err(|e| ("pin the message", e))?;
+    ctx.http
+        .unpin(channel_id, message_id, REASON)
+        .await
+        .map_err(|e| ("unpin the message", e))
+}
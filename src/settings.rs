@@ -0,0 +1,132 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    guild::Permissions,
+    id::{marker::GuildMarker, Id},
+};
+
+use crate::{ephemeral, lock, Context};
+
+/// Handle `/pin-set-role`, which changes the role required for pinning in the guild
+pub async fn set_role(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    // The command is registered with these permissions, but server admins can override that
+    let allowed = event
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|perms| perms.contains(Permissions::MANAGE_GUILD));
+
+    if !allowed {
+        let response = ephemeral("You need the **Manage Server** permission to do this.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    let mut role_id = None;
+    let mut action = "both";
+    for option in &data.options {
+        match (option.name.as_str(), &option.value) {
+            ("role", CommandOptionValue::Role(id)) => role_id = Some(*id),
+            ("for", CommandOptionValue::String(value)) => action = value.as_str(),
+            _ => {}
+        }
+    }
+
+    // Discord resolves every role passed as an option, anything else is not a role of this guild
+    if let Some(role_id) = role_id {
+        let exists = data
+            .resolved
+            .as_ref()
+            .is_some_and(|resolved| resolved.roles.contains_key(&role_id));
+        if !exists || role_id.cast() == guild_id {
+            let response = ephemeral("That role can't be used here. Pick a role of this server.");
+            client
+                .create_response(event.id, &event.token, &response)
+                .await?;
+            return Ok(());
+        }
+    }
+
+    // The specific roles take precedence, so setting the shared role replaces them
+    ctx.store
+        .update_guild(guild_id, |settings| match action {
+            "pin" => settings.pin_role = role_id,
+            "unpin" => settings.unpin_role = role_id,
+            _ => {
+                settings.required_role = role_id;
+                settings.pin_role = None;
+                settings.unpin_role = None;
+            }
+        })
+        .await?;
+
+    let settings = ctx.store.guild(guild_id);
+    let mut content = [("pin", true), ("unpin", false)]
+        .into_iter()
+        .map(|(verb, pin)| match settings.role_for(pin) {
+            Some(id) => format!("Members need the <@&{id}> role to {verb} messages."),
+            None => format!("Anyone can {verb} messages."),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !ctx.store.is_persistent() {
+        content.push_str("\nNo database is configured, so this resets when the bot restarts.");
+    }
+
+    client
+        .create_response(event.id, &event.token, &ephemeral(content))
+        .await?;
+    Ok(())
+}
+
+/// Handle `/pin-unpin-approval`, which makes unpins in the guild wait for a second moderator
+pub async fn set_unpin_approval(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    if !lock::can_manage_guild(event) {
+        let response = ephemeral("You need the **Manage Server** permission to do this.");
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    let enabled = data
+        .options
+        .iter()
+        .any(|option| matches!(option.value, CommandOptionValue::Boolean(true)));
+    ctx.store
+        .update_guild(guild_id, |settings| settings.unpin_approval = enabled)
+        .await?;
+
+    let mut content = if enabled {
+        "Unpins now need the approval of a second moderator.".to_owned()
+    } else {
+        "Unpins no longer need an approval.".to_owned()
+    };
+    if !ctx.store.is_persistent() {
+        content.push_str("\nNo database is configured, so this resets when the bot restarts.");
+    }
+
+    client
+        .create_response(event.id, &event.token, &ephemeral(content))
+        .await?;
+    Ok(())
+}
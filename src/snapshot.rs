@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::{message::MessageFlags, Message},
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    confirmation, jump_link, label, locale, metrics, pins::PinApi, store::PinSnapshot, time,
+    Context, DEFER_EPHEMERAL,
+};
+
+/// Name of the snapshot when the command doesn't give one
+const DEFAULT_NAME: &str = "default";
+/// Most snapshots kept per channel, each of them holds every pin of the channel
+const MAX_SNAPSHOTS: usize = 10;
+/// Longest list of added or removed pins, so both fit into one message of 2000 characters
+const SECTION_LENGTH: usize = 850;
+
+/// Handle `/pin-snapshot`, which saves the current pins of this channel under a name
+pub async fn save(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    let Some(user) = event.author() else {
+        return Ok(());
+    };
+    let name = name(data);
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let pins = pins(ctx, channel_id).await?;
+    let snapshot = PinSnapshot {
+        message_ids: pins.iter().map(|message| message.id).collect(),
+        user_id: user.id,
+        timestamp: time::now(),
+    };
+    let saved = ctx
+        .store
+        .save_pin_snapshot(channel_id, name.to_owned(), snapshot, MAX_SNAPSHOTS)
+        .await?;
+
+    let content = if saved {
+        log::info!(
+            "[{}] {} saved a snapshot of {} pins as {}",
+            channel_id,
+            user.name,
+            pins.len(),
+            name
+        );
+        format!(
+            "Saved {} of this channel as `{name}`. Use `/pin-diff` to see what changed since.",
+            confirmation::count(pins.len(), "pin", "pins", locale(&ctx.config, event))
+        )
+    } else {
+        format!(
+            "This channel already has {MAX_SNAPSHOTS} snapshots. Reuse one of their names to replace it."
+        )
+    };
+    client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Handle `/pin-diff`, which compares the current pins of this channel with a snapshot
+pub async fn diff(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+    let name = name(data);
+
+    client
+        .create_response(event.id, &event.token, &DEFER_EPHEMERAL)
+        .await?;
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL);
+    let Some(snapshot) = ctx.store.pin_snapshot(channel_id, name) else {
+        request
+            .content(&format!(
+                "There is no snapshot called `{name}` in this channel. Save one with `/pin-snapshot` first."
+            ))?
+            .await?;
+        return Ok(());
+    };
+
+    let pins = pins(ctx, channel_id).await?;
+    let before: HashSet<_> = snapshot.message_ids.iter().copied().collect();
+    let now: HashSet<_> = pins.iter().map(|message| message.id).collect();
+
+    let added: Vec<String> = pins
+        .iter()
+        .filter(|message| !before.contains(&message.id))
+        .map(|message| {
+            format!(
+                "- [{}]({}) by <@{}>",
+                label::title(ctx, message),
+                jump_link(guild_id, channel_id, message.id),
+                message.author.id
+            )
+        })
+        .collect();
+    // Unpinned messages might be deleted by now, so there is nothing left to show but the link
+    let removed: Vec<String> = snapshot
+        .message_ids
+        .iter()
+        .filter(|message_id| !now.contains(message_id))
+        .map(|&message_id| format!("- {}", jump_link(guild_id, channel_id, message_id)))
+        .collect();
+
+    let mut content = format!(
+        "**Changes since `{name}`** (saved <t:{}:R> by <@{}>)",
+        snapshot.timestamp, snapshot.user_id
+    );
+    if added.is_empty() && removed.is_empty() {
+        content.push_str("\nThe pins are the same as in the snapshot.");
+    }
+    if pins.is_empty() {
+        content.push_str("\nThere are no pins in this channel right now.");
+    }
+    for (heading, lines) in [("Added", &added), ("Removed", &removed)] {
+        if !lines.is_empty() {
+            content.push_str(&section(heading, lines));
+        }
+    }
+
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// A list of changes, cut short so both lists fit into one message
+fn section(heading: &str, lines: &[String]) -> String {
+    let mut section = format!("\n\n**{heading}** ({})", lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        // Leave room for the header and the note about the remaining pins
+        if section.chars().count() + line.chars().count() > SECTION_LENGTH {
+            section.push_str(&format!("\n*and {} more*", lines.len() - index));
+            break;
+        }
+        section.push('\n');
+        section.push_str(line);
+    }
+    section
+}
+
+/// The snapshot name given to the command, or the default one
+fn name(data: &CommandData) -> &str {
+    data.options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value.trim()),
+            _ => None,
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_NAME)
+}
+
+async fn pins(ctx: &Context, channel_id: Id<ChannelMarker>) -> Result<Vec<Message>> {
+    match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => Ok(pins),
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,764 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use twilight_model::id::{
+    marker::{ChannelMarker, GuildMarker, MessageMarker, RoleMarker, UserMarker},
+    Id,
+};
+
+/// Settings that server admins can change for their own guild
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct GuildSettings {
+    /// Role a member must have to use the pin commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_role: Option<Id<RoleMarker>>,
+    /// Role needed for pinning, takes precedence over `required_role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_role: Option<Id<RoleMarker>>,
+    /// Role needed for unpinning, takes precedence over `required_role`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unpin_role: Option<Id<RoleMarker>>,
+    /// Unpins wait until a second moderator approves them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unpin_approval: bool,
+}
+
+impl GuildSettings {
+    /// The role needed to pin or unpin, the specific role before the shared one
+    pub fn role_for(&self, pin: bool) -> Option<Id<RoleMarker>> {
+        if pin { self.pin_role } else { self.unpin_role }.or(self.required_role)
+    }
+
+    /// The required role, if the member with these roles doesn't have it
+    pub fn missing_role(&self, pin: bool, roles: &[Id<RoleMarker>]) -> Option<Id<RoleMarker>> {
+        self.role_for(pin)
+            .filter(|role_id| !roles.contains(role_id))
+    }
+}
+
+/// A temporary pin, which is removed once it expires
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimedUnpin {
+    pub guild_id: Id<GuildMarker>,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Id<MessageMarker>,
+    /// Unix timestamp in seconds
+    pub unpin_at: u64,
+}
+
+/// A pin planned for later, added with `/schedule-pin`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduledPin {
+    pub guild_id: Id<GuildMarker>,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Id<MessageMarker>,
+    /// Who scheduled the pin, named in the audit log
+    pub user_id: Id<UserMarker>,
+    /// Unix timestamp in seconds
+    pub pin_at: u64,
+}
+
+/// A pin or unpin done through the bot, shown in `/pin-log`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PinAction {
+    pub user_id: Id<UserMarker>,
+    pub channel_id: Id<ChannelMarker>,
+    pub message_id: Id<MessageMarker>,
+    /// Whether the message was pinned, otherwise it was unpinned
+    pub pin: bool,
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    /// Given by the user, see `require_pin_reason`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Most pin actions kept per guild, older ones are dropped
+const MAX_PIN_ACTIONS: usize = 500;
+/// Lines appended to the pin log before it is rewritten without the dropped actions
+const MAX_LOG_APPENDS: usize = 5000;
+
+type PinActions = HashMap<Id<GuildMarker>, Vec<PinAction>>;
+
+/// A line of the pin log file
+#[derive(Serialize, Deserialize)]
+struct LoggedAction {
+    guild_id: Id<GuildMarker>,
+    #[serde(flatten)]
+    action: PinAction,
+}
+
+/// The pins of a channel at some point, compared against by `/pin-diff`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PinSnapshot {
+    /// Newest pin first, like Discord lists them
+    pub message_ids: Vec<Id<MessageMarker>>,
+    pub user_id: Id<UserMarker>,
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Data {
+    #[serde(default)]
+    guilds: HashMap<Id<GuildMarker>, GuildSettings>,
+    #[serde(default)]
+    timed_unpins: Vec<TimedUnpin>,
+    #[serde(default)]
+    scheduled_pins: Vec<ScheduledPin>,
+    /// Pins which only server managers can remove, message ids are unique across channels
+    #[serde(default)]
+    locked_pins: HashSet<Id<MessageMarker>>,
+    /// Thread of each channel which confirmations are posted in, see `confirmation_placement`
+    #[serde(default)]
+    pin_threads: HashMap<Id<ChannelMarker>, Id<ChannelMarker>>,
+    /// Labels shown by `/pins`, kept after unpinning so they return if the message is pinned again
+    #[serde(default)]
+    pin_labels: HashMap<Id<MessageMarker>, String>,
+    /// Category of each pin, one of `pin_categories` in the config when it was set
+    #[serde(default)]
+    pin_categories: HashMap<Id<MessageMarker>, String>,
+    /// Named snapshots of the pins of each channel
+    #[serde(default)]
+    pin_snapshots: HashMap<Id<ChannelMarker>, HashMap<String, PinSnapshot>>,
+    /// Channels where pins can't change until the timestamp, see `/pin-freeze`
+    #[serde(default)]
+    pin_freezes: HashMap<Id<ChannelMarker>, u64>,
+    /// Members who can pin without the required role until the timestamp, see `/grant-pin`
+    #[serde(default)]
+    pin_grants: HashMap<Id<GuildMarker>, HashMap<Id<UserMarker>, u64>>,
+    /// End of the period of the last pin digest of each guild, see `pin_digests`
+    #[serde(default)]
+    digests_sent: HashMap<Id<GuildMarker>, u64>,
+    /// Our own user id from the last run, to recognize our pin messages before Ready arrives
+    #[serde(default)]
+    bot_user_id: Option<Id<UserMarker>>,
+    /// References posted by `pin_mirrors`, which are never mirrored again when pinned
+    #[serde(default)]
+    mirror_references: HashSet<Id<MessageMarker>>,
+}
+
+/// Persistent state of the bot, stored as a JSON file.
+///
+/// The pin actions are appended to a separate JSON lines file, `<database>.pinlog`, so the
+/// database isn't rewritten for every pin. Without a configured path, everything is only kept
+/// in memory until the process exits.
+pub struct Store {
+    path: Option<String>,
+    data: Mutex<Data>,
+    /// The pin actions of each guild, oldest first, as read from the pin log
+    pin_actions: Mutex<PinActions>,
+    /// Held while writing, so the files are written in the order the changes were made
+    write: tokio::sync::Mutex<usize>,
+}
+
+impl Store {
+    pub async fn open(path: Option<String>) -> Result<Self> {
+        let data = match path {
+            Some(ref path) => match tokio::fs::read_to_string(path).await {
+                Ok(json) => serde_json::from_str(json.as_str())?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Data::default(),
+                Err(e) => return Err(e.into()),
+            },
+            None => Data::default(),
+        };
+
+        let mut pin_actions = PinActions::new();
+        if let Some(ref path) = path {
+            for (guild_id, action) in read_pin_log(&pin_log_path(path)).await? {
+                push_pin_action(&mut pin_actions, guild_id, action);
+            }
+        }
+
+        Ok(Self {
+            path,
+            data: Mutex::new(data),
+            pin_actions: Mutex::new(pin_actions),
+            write: tokio::sync::Mutex::new(0),
+        })
+    }
+
+    pub const fn is_persistent(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn guild(&self, guild_id: Id<GuildMarker>) -> GuildSettings {
+        let data = self.data.lock().unwrap();
+        data.guilds.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn update_guild(
+        &self,
+        guild_id: Id<GuildMarker>,
+        update: impl FnOnce(&mut GuildSettings),
+    ) -> Result<()> {
+        self.update(|data| update(data.guilds.entry(guild_id).or_default()))
+            .await
+    }
+
+    pub fn timed_unpins(&self) -> Vec<TimedUnpin> {
+        self.data.lock().unwrap().timed_unpins.clone()
+    }
+
+    /// The timed unpin which expires first
+    pub fn next_timed_unpin(&self) -> Option<TimedUnpin> {
+        let data = self.data.lock().unwrap();
+        data.timed_unpins
+            .iter()
+            .min_by_key(|entry| entry.unpin_at)
+            .cloned()
+    }
+
+    /// Add a timed unpin, replacing any previous one for the same message
+    pub async fn add_timed_unpin(&self, entry: TimedUnpin) -> Result<()> {
+        self.update(|data| {
+            data.timed_unpins
+                .retain(|it| it.message_id != entry.message_id);
+            data.timed_unpins.push(entry);
+        })
+        .await
+    }
+
+    /// Remove the timed unpin of the message, returns whether there was one
+    pub async fn remove_timed_unpin(&self, message_id: Id<MessageMarker>) -> Result<bool> {
+        self.update(|data| {
+            let before = data.timed_unpins.len();
+            data.timed_unpins.retain(|it| it.message_id != message_id);
+            before != data.timed_unpins.len()
+        })
+        .await
+    }
+
+    /// Move the expiry of a timed unpin, used to retry failed unpins later
+    pub async fn postpone_timed_unpin(
+        &self,
+        message_id: Id<MessageMarker>,
+        unpin_at: u64,
+    ) -> Result<()> {
+        self.update(|data| {
+            data.timed_unpins
+                .iter_mut()
+                .filter(|it| it.message_id == message_id)
+                .for_each(|it| it.unpin_at = unpin_at);
+        })
+        .await
+    }
+
+    pub fn scheduled_pins(&self) -> Vec<ScheduledPin> {
+        self.data.lock().unwrap().scheduled_pins.clone()
+    }
+
+    /// The scheduled pin which is due first
+    pub fn next_scheduled_pin(&self) -> Option<ScheduledPin> {
+        let data = self.data.lock().unwrap();
+        data.scheduled_pins
+            .iter()
+            .min_by_key(|entry| entry.pin_at)
+            .cloned()
+    }
+
+    /// Add a scheduled pin, replacing any previous one for the same message
+    pub async fn add_scheduled_pin(&self, entry: ScheduledPin) -> Result<()> {
+        self.update(|data| {
+            data.scheduled_pins
+                .retain(|it| it.message_id != entry.message_id);
+            data.scheduled_pins.push(entry);
+        })
+        .await
+    }
+
+    /// Remove the scheduled pin of the message, returns whether there was one
+    pub async fn remove_scheduled_pin(&self, message_id: Id<MessageMarker>) -> Result<bool> {
+        self.update(|data| {
+            let before = data.scheduled_pins.len();
+            data.scheduled_pins.retain(|it| it.message_id != message_id);
+            before != data.scheduled_pins.len()
+        })
+        .await
+    }
+
+    /// Move a scheduled pin, used to retry failed pins later
+    pub async fn postpone_scheduled_pin(
+        &self,
+        message_id: Id<MessageMarker>,
+        pin_at: u64,
+    ) -> Result<()> {
+        self.update(|data| {
+            data.scheduled_pins
+                .iter_mut()
+                .filter(|it| it.message_id == message_id)
+                .for_each(|it| it.pin_at = pin_at);
+        })
+        .await
+    }
+
+    /// Drop the timed unpins and scheduled pins of the guild, returns how many were removed.
+    ///
+    /// Settings and the pin log are kept, in case the bot is added back later.
+    pub async fn remove_guild_tasks(&self, guild_id: Id<GuildMarker>) -> Result<usize> {
+        self.update(|data| {
+            let before = data.timed_unpins.len() + data.scheduled_pins.len();
+            data.timed_unpins.retain(|it| it.guild_id != guild_id);
+            data.scheduled_pins.retain(|it| it.guild_id != guild_id);
+            before - data.timed_unpins.len() - data.scheduled_pins.len()
+        })
+        .await
+    }
+
+    /// Remember a pin action of the guild, oldest first.
+    ///
+    /// Only appends a line to the pin log, the database itself stays as it is.
+    pub async fn add_pin_action(&self, guild_id: Id<GuildMarker>, action: PinAction) -> Result<()> {
+        let Some(ref path) = self.path else {
+            push_pin_action(&mut self.pin_actions.lock().unwrap(), guild_id, action);
+            return Ok(());
+        };
+
+        let mut appended = self.write.lock().await;
+        let mut line = serde_json::to_string(&LoggedAction {
+            guild_id,
+            action: action.clone(),
+        })?;
+        line.push('\n');
+        push_pin_action(&mut self.pin_actions.lock().unwrap(), guild_id, action);
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(pin_log_path(path))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        *appended += 1;
+        if *appended >= MAX_LOG_APPENDS {
+            self.compact_pin_log(&mut appended).await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the pin actions from before the timestamp, in every guild if none is given.
+    ///
+    /// Returns how many were removed.
+    pub async fn purge_pin_actions(
+        &self,
+        guild_id: Option<Id<GuildMarker>>,
+        before: u64,
+    ) -> Result<usize> {
+        let mut appended = self.write.lock().await;
+        let removed = {
+            let mut pin_actions = self.pin_actions.lock().unwrap();
+            let mut removed = 0;
+            for (id, actions) in pin_actions.iter_mut() {
+                if guild_id.is_none_or(|it| it == *id) {
+                    let count = actions.len();
+                    actions.retain(|it| it.timestamp >= before);
+                    removed += count - actions.len();
+                }
+            }
+            pin_actions.retain(|_, actions| !actions.is_empty());
+            removed
+        };
+        // The purged actions have to be gone from the file as well
+        if removed > 0 {
+            self.compact_pin_log(&mut appended).await?;
+        }
+        Ok(removed)
+    }
+
+    /// The pin actions of the guild, oldest first
+    pub fn pin_actions(&self, guild_id: Id<GuildMarker>) -> Vec<PinAction> {
+        let pin_actions = self.pin_actions.lock().unwrap();
+        pin_actions.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    /// End of the period which the last pin digest of the guild covered
+    pub fn digest_sent(&self, guild_id: Id<GuildMarker>) -> Option<u64> {
+        self.data
+            .lock()
+            .unwrap()
+            .digests_sent
+            .get(&guild_id)
+            .copied()
+    }
+
+    pub async fn set_digest_sent(&self, guild_id: Id<GuildMarker>, until: u64) -> Result<()> {
+        self.update(|data| {
+            data.digests_sent.insert(guild_id, until);
+        })
+        .await
+    }
+
+    pub fn pin_thread(&self, channel_id: Id<ChannelMarker>) -> Option<Id<ChannelMarker>> {
+        self.data
+            .lock()
+            .unwrap()
+            .pin_threads
+            .get(&channel_id)
+            .copied()
+    }
+
+    pub async fn set_pin_thread(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        thread_id: Id<ChannelMarker>,
+    ) -> Result<()> {
+        self.update(|data| {
+            data.pin_threads.insert(channel_id, thread_id);
+        })
+        .await
+    }
+
+    pub fn label(&self, message_id: Id<MessageMarker>) -> Option<String> {
+        self.data
+            .lock()
+            .unwrap()
+            .pin_labels
+            .get(&message_id)
+            .cloned()
+    }
+
+    /// Set or remove the label of the pin
+    pub async fn set_label(
+        &self,
+        message_id: Id<MessageMarker>,
+        label: Option<String>,
+    ) -> Result<()> {
+        self.update(|data| match label {
+            Some(label) => {
+                data.pin_labels.insert(message_id, label);
+            }
+            None => {
+                data.pin_labels.remove(&message_id);
+            }
+        })
+        .await
+    }
+
+    pub fn category(&self, message_id: Id<MessageMarker>) -> Option<String> {
+        self.data
+            .lock()
+            .unwrap()
+            .pin_categories
+            .get(&message_id)
+            .cloned()
+    }
+
+    /// Set or remove the category of the pin
+    pub async fn set_category(
+        &self,
+        message_id: Id<MessageMarker>,
+        category: Option<String>,
+    ) -> Result<()> {
+        self.update(|data| match category {
+            Some(category) => {
+                data.pin_categories.insert(message_id, category);
+            }
+            None => {
+                data.pin_categories.remove(&message_id);
+            }
+        })
+        .await
+    }
+
+    pub fn pin_snapshot(&self, channel_id: Id<ChannelMarker>, name: &str) -> Option<PinSnapshot> {
+        self.data
+            .lock()
+            .unwrap()
+            .pin_snapshots
+            .get(&channel_id)
+            .and_then(|snapshots| snapshots.get(name))
+            .cloned()
+    }
+
+    /// Save the snapshot under its name, replacing an older one with the same name.
+    ///
+    /// Returns false without saving if the channel already has the most snapshots allowed.
+    pub async fn save_pin_snapshot(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        name: String,
+        snapshot: PinSnapshot,
+        limit: usize,
+    ) -> Result<bool> {
+        self.update(|data| {
+            let snapshots = data.pin_snapshots.entry(channel_id).or_default();
+            if snapshots.len() >= limit && !snapshots.contains_key(&name) {
+                return false;
+            }
+            snapshots.insert(name, snapshot);
+            true
+        })
+        .await
+    }
+
+    /// Unix timestamp until which pins of the channel are frozen, which might have passed already
+    pub fn pin_freeze(&self, channel_id: Id<ChannelMarker>) -> Option<u64> {
+        self.data
+            .lock()
+            .unwrap()
+            .pin_freezes
+            .get(&channel_id)
+            .copied()
+    }
+
+    /// Freeze the pins of the channel until the timestamp, or lift the freeze.
+    ///
+    /// Returns whether the channel was frozen before, freezes which already ended are dropped.
+    pub async fn set_pin_freeze(
+        &self,
+        channel_id: Id<ChannelMarker>,
+        until: Option<u64>,
+        now: u64,
+    ) -> Result<bool> {
+        self.update(|data| {
+            data.pin_freezes.retain(|_, until| *until > now);
+            let previous = match until {
+                Some(until) => data.pin_freezes.insert(channel_id, until),
+                None => data.pin_freezes.remove(&channel_id),
+            };
+            previous.is_some()
+        })
+        .await
+    }
+
+    pub fn pin_grant(&self, guild_id: Id<GuildMarker>, user_id: Id<UserMarker>) -> Option<u64> {
+        let data = self.data.lock().unwrap();
+        data.pin_grants.get(&guild_id)?.get(&user_id).copied()
+    }
+
+    /// Let the member pin without the required role until the timestamp, or revoke the grant.
+    ///
+    /// Returns whether the member had a grant before, grants which already ended are dropped.
+    pub async fn set_pin_grant(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        until: Option<u64>,
+        now: u64,
+    ) -> Result<bool> {
+        self.update(|data| {
+            data.pin_grants.retain(|_, grants| {
+                grants.retain(|_, until| *until > now);
+                !grants.is_empty()
+            });
+            let previous = match until {
+                Some(until) => data
+                    .pin_grants
+                    .entry(guild_id)
+                    .or_default()
+                    .insert(user_id, until),
+                None => data
+                    .pin_grants
+                    .get_mut(&guild_id)
+                    .and_then(|grants| grants.remove(&user_id)),
+            };
+            previous.is_some()
+        })
+        .await
+    }
+
+    pub fn bot_user_id(&self) -> Option<Id<UserMarker>> {
+        self.data.lock().unwrap().bot_user_id
+    }
+
+    pub async fn set_bot_user_id(&self, user_id: Id<UserMarker>) -> Result<()> {
+        self.update(|data| data.bot_user_id = Some(user_id)).await
+    }
+
+    pub fn is_locked(&self, message_id: Id<MessageMarker>) -> bool {
+        self.data.lock().unwrap().locked_pins.contains(&message_id)
+    }
+
+    /// Lock or unlock the pin, returns whether that changed anything
+    pub async fn set_locked(&self, message_id: Id<MessageMarker>, locked: bool) -> Result<bool> {
+        self.update(|data| {
+            if locked {
+                data.locked_pins.insert(message_id)
+            } else {
+                data.locked_pins.remove(&message_id)
+            }
+        })
+        .await
+    }
+
+    pub fn is_mirror_reference(&self, message_id: Id<MessageMarker>) -> bool {
+        self.data
+            .lock()
+            .unwrap()
+            .mirror_references
+            .contains(&message_id)
+    }
+
+    pub async fn add_mirror_reference(&self, message_id: Id<MessageMarker>) -> Result<()> {
+        self.update(|data| {
+            data.mirror_references.insert(message_id);
+        })
+        .await
+    }
+
+    /// Apply a change to the data and write it to disk
+    async fn update<R>(&self, update: impl FnOnce(&mut Data) -> R) -> Result<R> {
+        let Some(ref path) = self.path else {
+            return Ok(update(&mut self.data.lock().unwrap()));
+        };
+
+        let _write = self.write.lock().await;
+        let (result, json) = {
+            let mut data = self.data.lock().unwrap();
+            let result = update(&mut data);
+            (result, serde_json::to_string_pretty(&*data)?)
+        };
+        replace_file(path, json.as_bytes()).await?;
+        Ok(result)
+    }
+
+    /// Rewrite the pin log with only the actions which are still kept, needs the write lock
+    async fn compact_pin_log(&self, appended: &mut usize) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let mut lines = String::new();
+        {
+            let pin_actions = self.pin_actions.lock().unwrap();
+            for (&guild_id, actions) in pin_actions.iter() {
+                for action in actions {
+                    lines.push_str(&serde_json::to_string(&LoggedAction {
+                        guild_id,
+                        action: action.clone(),
+                    })?);
+                    lines.push('\n');
+                }
+            }
+        }
+        replace_file(&pin_log_path(path), lines.as_bytes()).await?;
+        *appended = 0;
+        Ok(())
+    }
+}
+
+fn pin_log_path(path: &str) -> String {
+    format!("{path}.pinlog")
+}
+
+/// Add the action to the guild, dropping the oldest ones past the limit
+fn push_pin_action(pin_actions: &mut PinActions, guild_id: Id<GuildMarker>, action: PinAction) {
+    let actions = pin_actions.entry(guild_id).or_default();
+    actions.push(action);
+    let excess = actions.len().saturating_sub(MAX_PIN_ACTIONS);
+    actions.drain(..excess);
+}
+
+/// Every action in the pin log file, a line cut off by a crash is skipped
+async fn read_pin_log(path: &str) -> Result<Vec<(Id<GuildMarker>, PinAction)>> {
+    let lines = match tokio::fs::read_to_string(path).await {
+        Ok(lines) => lines,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(lines
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LoggedAction>(line).ok())
+        .map(|logged| (logged.guild_id, logged.action))
+        .collect())
+}
+
+/// Write the file through a temporary one, so a crash leaves either the old or the new content
+async fn replace_file(path: &str, contents: &[u8]) -> Result<()> {
+    let temporary = format!("{path}.tmp");
+    let mut file = tokio::fs::File::create(&temporary).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await?;
+    drop(file);
+    tokio::fs::rename(&temporary, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specific_roles_come_before_the_shared_one() {
+        let shared = GuildSettings {
+            required_role: Some(Id::new(1)),
+            ..Default::default()
+        };
+        assert_eq!(shared.role_for(true), Some(Id::new(1)));
+        assert_eq!(shared.role_for(false), Some(Id::new(1)));
+
+        let split = GuildSettings {
+            pin_role: Some(Id::new(2)),
+            ..shared.clone()
+        };
+        assert_eq!(split.role_for(true), Some(Id::new(2)));
+        assert_eq!(split.role_for(false), Some(Id::new(1)));
+
+        let unpin_only = GuildSettings {
+            unpin_role: Some(Id::new(3)),
+            ..Default::default()
+        };
+        assert_eq!(unpin_only.role_for(true), None);
+        assert_eq!(unpin_only.role_for(false), Some(Id::new(3)));
+    }
+
+    #[test]
+    fn members_with_the_role_are_not_missing_it() {
+        let settings = GuildSettings {
+            pin_role: Some(Id::new(2)),
+            ..Default::default()
+        };
+        assert_eq!(settings.missing_role(true, &[Id::new(1)]), Some(Id::new(2)));
+        assert_eq!(settings.missing_role(true, &[Id::new(1), Id::new(2)]), None);
+        assert_eq!(settings.missing_role(false, &[]), None);
+    }
+
+    fn action(timestamp: u64) -> PinAction {
+        PinAction {
+            user_id: Id::new(1),
+            channel_id: Id::new(2),
+            message_id: Id::new(timestamp),
+            pin: true,
+            timestamp,
+            reason: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn pin_actions_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("pinbot-store-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("db.json").to_string_lossy().into_owned();
+        let guild_id = Id::new(3);
+
+        let store = Store::open(Some(path.clone())).await.unwrap();
+        store.add_pin_action(guild_id, action(10)).await.unwrap();
+        store.add_pin_action(guild_id, action(20)).await.unwrap();
+        store.set_locked(Id::new(10), true).await.unwrap();
+        store.add_mirror_reference(Id::new(30)).await.unwrap();
+        assert_eq!(store.purge_pin_actions(None, 15).await.unwrap(), 1);
+        drop(store);
+
+        let store = Store::open(Some(path.clone())).await.unwrap();
+        let timestamps: Vec<_> = store
+            .pin_actions(guild_id)
+            .iter()
+            .map(|it| it.timestamp)
+            .collect();
+        assert_eq!(timestamps, [20]);
+        assert!(store.is_locked(Id::new(10)));
+        assert!(store.is_mirror_reference(Id::new(30)));
+        assert!(!store.is_mirror_reference(Id::new(10)));
+        // The database only changes through the temporary file, which is renamed over it
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("pin_actions"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
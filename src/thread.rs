@@ -0,0 +1,90 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    channel::{
+        message::{Component, Embed},
+        thread::AutoArchiveDuration,
+        ChannelType,
+    },
+    id::{marker::ChannelMarker, Id},
+};
+
+use crate::{config::ConfirmationStyle, confirmation, error, metrics, Context};
+
+const THREAD_NAME: &str = "Pin Log";
+/// Discord rejects messages in archived threads with this code
+const THREAD_ARCHIVED: u64 = 50083;
+
+/// Post the confirmation in the pin thread of the channel, which is created on first use
+pub async fn post(
+    ctx: &Context,
+    channel_id: Id<ChannelMarker>,
+    content: &str,
+    embed: &Embed,
+    button: &[Component],
+) -> Result<()> {
+    let thread_id = match ctx.store.pin_thread(channel_id) {
+        Some(thread_id) => thread_id,
+        None => create(ctx, channel_id).await?,
+    };
+
+    let result = send(ctx, thread_id, content, embed, button).await;
+    let thread_id = match result {
+        Ok(()) => return Ok(()),
+        // Someone deleted the thread, so start a new one
+        Err(ref e) if error::error_code(e) == Some(error::UNKNOWN_CHANNEL) => {
+            create(ctx, channel_id).await?
+        }
+        // Threads are archived after a week without messages
+        Err(ref e) if error::error_code(e) == Some(THREAD_ARCHIVED) => {
+            ctx.http.update_thread(thread_id).archived(false).await?;
+            thread_id
+        }
+        Err(e) => return Err(e),
+    };
+    send(ctx, thread_id, content, embed, button).await
+}
+
+/// Start a new pin thread in the channel and remember it
+async fn create(ctx: &Context, channel_id: Id<ChannelMarker>) -> Result<Id<ChannelMarker>> {
+    let thread = match ctx
+        .http
+        .create_thread(channel_id, THREAD_NAME, ChannelType::PublicThread)?
+        .auto_archive_duration(AutoArchiveDuration::Week)
+        .await
+    {
+        Ok(response) => response.model().await?,
+        Err(e) => {
+            metrics::observe_http_error(&e, "create_thread");
+            return Err(e.into());
+        }
+    };
+
+    log::info!("[{}] Created pin thread {}", channel_id, thread.id);
+    ctx.store.set_pin_thread(channel_id, thread.id).await?;
+    Ok(thread.id)
+}
+
+async fn send(
+    ctx: &Context,
+    thread_id: Id<ChannelMarker>,
+    content: &str,
+    embed: &Embed,
+    button: &[Component],
+) -> Result<()> {
+    let mentions = confirmation::NO_PINGS;
+    let request = ctx
+        .http
+        .create_message(thread_id)
+        .allowed_mentions(Some(&mentions))
+        .components(button)?;
+    let result = match ctx.config.confirmation_style {
+        ConfirmationStyle::Text => request.content(content)?.await,
+        ConfirmationStyle::Embed => request.embeds(std::slice::from_ref(embed))?.await,
+    };
+    if let Err(e) = result {
+        metrics::observe_http_error(&e, "create_message");
+        return Err(e.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Token bucket which paces requests that Discord might see as abuse when they come in bursts.
+///
+/// Callers past the burst are not rejected, they wait their turn in the order they arrived.
+pub struct TokenBucket {
+    burst: f64,
+    /// Tokens added per second
+    rate: f64,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Goes below zero while callers are waiting for their token
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(burst: u32, interval: Duration) -> Self {
+        let burst = f64::from(burst);
+        Self {
+            burst,
+            rate: 1.0 / interval.as_secs_f64(),
+            state: Mutex::new(State {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token, waiting until one is available. Returns how long that took.
+    pub async fn acquire(&self) -> Duration {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let refilled = now.duration_since(state.updated).as_secs_f64() * self.rate;
+            state.tokens = (state.tokens + refilled).min(self.burst) - 1.0;
+            state.updated = now;
+            if state.tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-state.tokens / self.rate)
+            }
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn callers_past_the_burst_wait_in_turn() {
+        let interval = Duration::from_millis(20);
+        let bucket = TokenBucket::new(2, interval);
+        assert_eq!(bucket.acquire().await, Duration::ZERO);
+        assert_eq!(bucket.acquire().await, Duration::ZERO);
+
+        // Each caller waits for one more token than the one before it
+        let (first, second) = tokio::join!(bucket.acquire(), bucket.acquire());
+        let (shorter, longer) = (first.min(second), first.max(second));
+        assert!(
+            shorter > Duration::ZERO && shorter <= interval,
+            "{shorter:?}"
+        );
+        assert!(longer > interval && longer <= interval * 2, "{longer:?}");
+    }
+
+    #[tokio::test]
+    async fn tokens_refill_up_to_the_burst() {
+        let interval = Duration::from_millis(10);
+        let bucket = TokenBucket::new(1, interval);
+        assert_eq!(bucket.acquire().await, Duration::ZERO);
+        tokio::time::sleep(interval * 5).await;
+        // Waiting longer doesn't save up more than the burst
+        assert_eq!(bucket.acquire().await, Duration::ZERO);
+        assert!(bucket.acquire().await > Duration::ZERO);
+    }
+}
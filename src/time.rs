@@ -0,0 +1,147 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Current unix time in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |it| it.as_secs())
+}
+
+/// Parse a human duration like `30m`, `2h` or `1d 12h`
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let mut total: u64 = 0;
+    let mut value: Option<u64> = None;
+
+    for c in input.chars().filter(|c| !c.is_whitespace()) {
+        if let Some(digit) = c.to_digit(10) {
+            let current = value.unwrap_or(0);
+            value = Some(current.checked_mul(10)?.checked_add(digit.into())?);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        total = total.checked_add(value.take()?.checked_mul(unit)?)?;
+    }
+
+    // A number without unit is ambiguous, an empty input is not a duration
+    if value.is_some() || total == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Parse a point in time, as a duration from now, a unix timestamp, a Discord timestamp like
+/// `<t:1700000000:f>` or a UTC date like `2024-05-01 18:30`
+pub fn parse_time(input: &str) -> Option<u64> {
+    let input = input.trim();
+    if let Some(duration) = parse_duration(input) {
+        return now().checked_add(duration.as_secs());
+    }
+
+    let timestamp = input
+        .strip_prefix("<t:")
+        .and_then(|it| it.strip_suffix('>'))
+        .map_or(input, |it| it.split(':').next().unwrap_or(it));
+    if let Ok(timestamp) = timestamp.parse() {
+        return Some(timestamp);
+    }
+
+    let (date, time) = input.split_once([' ', 'T']).unwrap_or((input, "00:00"));
+    let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+    let mut time = time.splitn(2, ':').map(str::parse::<u64>);
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day)), Some(Ok(hour)), Some(Ok(minute))) = (
+        date.next(),
+        date.next(),
+        date.next(),
+        time.next(),
+        time.next(),
+    ) else {
+        return None;
+    };
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+    {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * 24 * 60 * 60 + hour * 60 * 60 + minute * 60)
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, by Howard Hinnant
+const fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Years start in March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Discord epoch, the first second of 2015, in milliseconds
+pub const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+/// Unix time in seconds at which the snowflake was created
+pub const fn snowflake_timestamp(id: u64) -> u64 {
+    ((id >> 22) + DISCORD_EPOCH) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        let secs = |input| parse_duration(input).map(|it| it.as_secs());
+        assert_eq!(secs("30m"), Some(30 * 60));
+        assert_eq!(secs("1d 12h"), Some(36 * 60 * 60));
+        assert_eq!(secs("2W"), Some(14 * 24 * 60 * 60));
+        assert_eq!(secs("1h1h"), Some(2 * 60 * 60));
+        assert_eq!(secs("90"), None);
+        assert_eq!(secs("h"), None);
+        assert_eq!(secs("0m"), None);
+        assert_eq!(secs(""), None);
+        assert_eq!(secs("5 minutes"), None);
+        assert_eq!(secs("99999999999999999999s"), None);
+        assert_eq!(secs("9999999999999999w"), None);
+    }
+
+    #[test]
+    fn parses_points_in_time() {
+        assert_eq!(parse_time("1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_time("<t:1700000000:f>"), Some(1_700_000_000));
+        assert_eq!(parse_time("<t:1700000000>"), Some(1_700_000_000));
+        assert_eq!(parse_time("2024-05-01 18:30"), Some(1_714_588_200));
+        assert_eq!(parse_time("2024-05-01T18:30"), Some(1_714_588_200));
+        assert_eq!(parse_time("2024-05-01"), Some(1_714_521_600));
+        assert_eq!(parse_time("2000-02-29 00:00"), Some(951_782_400));
+        assert_eq!(parse_time("1970-01-01"), Some(0));
+        assert_eq!(parse_time("2024-13-01"), None);
+        assert_eq!(parse_time("2024-05-01 24:00"), None);
+        assert_eq!(parse_time("1969-12-31"), None);
+        assert_eq!(parse_time("tomorrow"), None);
+    }
+
+    #[test]
+    fn relative_times_start_now() {
+        let before = now();
+        let time = parse_time("2h").unwrap();
+        assert!((before + 2 * 60 * 60..=now() + 2 * 60 * 60).contains(&time));
+    }
+
+    #[test]
+    fn snowflakes_carry_their_creation_time() {
+        // The example of the Discord documentation
+        assert_eq!(snowflake_timestamp(175_928_847_299_117_063), 1_462_015_105);
+    }
+}
@@ -0,0 +1,423 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use tokio::sync::Notify;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{modal::ModalInteractionData, Interaction},
+    channel::message::component::{ActionRow, Button, ButtonStyle, TextInput, TextInputStyle},
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, MessageMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    confirmation, defer, do_pin, ephemeral, error,
+    events::PinEvent,
+    freeze, gate, jump_link, metrics, pinlog,
+    pins::PinApi,
+    schedule,
+    store::{ScheduledPin, Store, TimedUnpin},
+    time, Context,
+};
+
+/// Prefix of the modal custom id, followed by the channel, message and author id
+pub const MODAL_PREFIX: &str = "timed-pin";
+const DURATION_INPUT: &str = "duration";
+/// How long to wait before trying a failed unpin again
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Wakes up the background task whenever the timed unpins or scheduled pins change
+#[derive(Default)]
+pub struct Scheduler {
+    notify: Notify,
+}
+
+impl Scheduler {
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+}
+
+/// Something the background task has to do at a certain time
+enum Task {
+    Unpin(TimedUnpin),
+    Pin(ScheduledPin),
+}
+
+impl Task {
+    /// The earliest task of either kind
+    fn next(store: &Store) -> Option<Self> {
+        let unpin = store.next_timed_unpin().map(Self::Unpin);
+        let pin = store.next_scheduled_pin().map(Self::Pin);
+        [unpin, pin].into_iter().flatten().min_by_key(Self::due)
+    }
+
+    const fn due(&self) -> u64 {
+        match self {
+            Self::Unpin(entry) => entry.unpin_at,
+            Self::Pin(entry) => entry.pin_at,
+        }
+    }
+
+    /// How long to sleep until the task is due, none if it has to run now
+    fn wait(&self, now: u64) -> Option<Duration> {
+        let due = self.due();
+        (due > now).then(|| Duration::from_secs(due - now))
+    }
+}
+
+/// The number of timed unpins which expired before the time, like while the bot was offline
+fn overdue(pending: &[TimedUnpin], now: u64) -> usize {
+    pending.iter().filter(|it| it.unpin_at <= now).count()
+}
+
+/// Ask the user for how long the message should stay pinned
+pub async fn prompt(
+    ctx: &Context,
+    event: &Interaction,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    author_id: Id<UserMarker>,
+) -> Result<()> {
+    let input = TextInput {
+        custom_id: DURATION_INPUT.to_owned(),
+        label: "Duration".to_owned(),
+        max_length: Some(32),
+        min_length: Some(2),
+        placeholder: Some("30m, 2h, 1d 12h".to_owned()),
+        required: Some(true),
+        style: TextInputStyle::Short,
+        value: None,
+    };
+
+    let response = InteractionResponse {
+        kind: InteractionResponseType::Modal,
+        data: Some(InteractionResponseData {
+            custom_id: Some(format!(
+                "{MODAL_PREFIX}:{channel_id}:{message_id}:{author_id}"
+            )),
+            title: Some("Pin temporarily".to_owned()),
+            components: Some(row!(input).into()),
+            ..Default::default()
+        }),
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Pin the message once the user picked a duration
+pub async fn submit(ctx: &Context, event: &Interaction, data: &ModalInteractionData) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let mut ids = data.custom_id.split(':').skip(1);
+    let (Some(guild_id), Some(user), Some(channel_id), Some(message_id), Some(author_id)) = (
+        event.guild_id,
+        event.author(),
+        ids.next().and_then(|it| it.parse().ok()),
+        ids.next().and_then(|it| it.parse().ok()),
+        ids.next().and_then(|it| it.parse().ok()),
+    ) else {
+        return Ok(());
+    };
+
+    let duration = data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find(|component| component.custom_id == DURATION_INPUT)
+        .and_then(|component| component.value.as_deref())
+        .and_then(time::parse_duration);
+
+    let Some(duration) = duration else {
+        let response = ephemeral(
+            "I couldn't understand that duration. Try something like `30m`, `2h` or `1d 12h`.",
+        );
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    };
+
+    // The channel could have been frozen, or the role changed, while the modal was open
+    if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, true) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    let unpin_at = time::now().saturating_add(duration.as_secs());
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+
+    let result = do_pin(
+        &ctx.http,
+        channel_id,
+        message_id,
+        true,
+        &format!("{username} temporarily pinned a message in {channel_name}"),
+    )
+    .await;
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags());
+
+    if let Err(e) = result {
+        metrics::observe_pin_error(&e, "create_pin");
+        log::error!("Failed to process pin due to error: {}", e);
+        request
+            .content(&ctx.config.pin_error_message(e.code()))?
+            .await?;
+        return Ok(());
+    }
+
+    let entry = TimedUnpin {
+        guild_id,
+        channel_id,
+        message_id,
+        unpin_at,
+    };
+    ctx.store.add_timed_unpin(entry).await?;
+    ctx.scheduler.wake();
+    pinlog::record(
+        ctx,
+        PinEvent::new(true, guild_id, channel_id, message_id, user.id).author(author_id),
+    )
+    .await;
+
+    let mut content = confirmation::content(&ctx.config, user, author_id, true);
+    content.push_str(&format!("\nPinned until <t:{unpin_at}:f>."));
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message_id)
+    ));
+
+    log::info!("[{}] {}", channel_id, content);
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .components(&button)?
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+/// Run the background task, which unpins the timed pins once they expire and pins the scheduled
+/// pins once they are due
+pub fn spawn(ctx: Arc<Context>) {
+    tokio::spawn(async move {
+        // Pins that expired while we were offline are handled right away by the loop below
+        let pending = ctx.store.timed_unpins();
+        if !pending.is_empty() {
+            log::info!(
+                "Loaded {} timed pins, {} of them expired while offline",
+                pending.len(),
+                overdue(&pending, time::now())
+            );
+        }
+        let scheduled = ctx.store.scheduled_pins().len();
+        if scheduled > 0 {
+            log::info!("Loaded {} scheduled pins", scheduled);
+        }
+
+        // Tasks in a row which failed, a failed task might still be due if it couldn't be moved
+        let mut failures = 0;
+        loop {
+            let Some(task) = Task::next(&ctx.store) else {
+                ctx.scheduler.notify.notified().await;
+                continue;
+            };
+
+            let now = time::now();
+            if let Some(delay) = task.wait(now) {
+                // Wake up early if a pin was added or removed in the meantime
+                tokio::select! {
+                    () = tokio::time::sleep(delay) => {}
+                    () = ctx.scheduler.notify.notified() => {}
+                }
+                continue;
+            }
+
+            let result = match task {
+                Task::Unpin(entry) => expire(&ctx, &entry, now)
+                    .await
+                    .inspect_err(|e| log::error!("Failed to remove expired pin: {e}")),
+                Task::Pin(entry) => schedule::fire(&ctx, &entry, now)
+                    .await
+                    .inspect_err(|e| log::error!("Failed to pin scheduled message: {e}")),
+            };
+            if result.is_ok() {
+                failures = 0;
+            } else {
+                failures += 1;
+                tokio::time::sleep(crate::backoff(failures)).await;
+            }
+        }
+    });
+}
+
+/// Unpin the expired message, or retry a while after `now` if that isn't possible right now
+async fn expire(ctx: &Context, entry: &TimedUnpin, now: u64) -> Result<()> {
+    // Nothing is unpinned during a freeze, not even by the bot
+    if let Some(until) = freeze::frozen_until(ctx, entry.channel_id) {
+        ctx.store
+            .postpone_timed_unpin(entry.message_id, until)
+            .await?;
+        return Ok(());
+    }
+    match unpin(ctx, entry).await {
+        Ok(()) => {
+            ctx.store.remove_timed_unpin(entry.message_id).await?;
+            Ok(())
+        }
+        Err(e) if error::is_unknown_target(&e) => {
+            log::info!(
+                "[{}] Dropping timed pin of deleted message {}",
+                entry.channel_id,
+                entry.message_id
+            );
+            ctx.store.remove_timed_unpin(entry.message_id).await?;
+            Ok(())
+        }
+        Err(e) => {
+            let retry_at = now.saturating_add(RETRY_DELAY.as_secs());
+            ctx.store
+                .postpone_timed_unpin(entry.message_id, retry_at)
+                .await?;
+            Err(e)
+        }
+    }
+}
+
+async fn unpin(ctx: &Context, entry: &TimedUnpin) -> Result<()> {
+    // Someone might have unpinned it already
+    if !ctx
+        .http
+        .is_pinned(entry.channel_id, entry.message_id)
+        .await?
+    {
+        return Ok(());
+    }
+
+    if let Err(e) = do_pin(
+        &ctx.http,
+        entry.channel_id,
+        entry.message_id,
+        false,
+        "Temporary pin expired",
+    )
+    .await
+    {
+        metrics::observe_pin_error(&e, "delete_pin");
+        return Err(e.into());
+    }
+
+    log::info!(
+        "[{}] Unpinned expired message {}",
+        entry.channel_id,
+        entry.message_id
+    );
+    // Nobody unpinned it, so the bot shows up as the one who did
+    if let Some(bot_id) = ctx.store.bot_user_id() {
+        let event = PinEvent::new(
+            false,
+            entry.guild_id,
+            entry.channel_id,
+            entry.message_id,
+            bot_id,
+        );
+        pinlog::record(ctx, event).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timed(message_id: u64, unpin_at: u64) -> TimedUnpin {
+        TimedUnpin {
+            guild_id: Id::new(1),
+            channel_id: Id::new(2),
+            message_id: Id::new(message_id),
+            unpin_at,
+        }
+    }
+
+    fn scheduled(message_id: u64, pin_at: u64) -> ScheduledPin {
+        ScheduledPin {
+            guild_id: Id::new(1),
+            channel_id: Id::new(2),
+            message_id: Id::new(message_id),
+            user_id: Id::new(3),
+            pin_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn overdue_tasks_run_right_away_after_a_restart() {
+        let store = Store::open(None).await.unwrap();
+        store.add_timed_unpin(timed(10, 1_000)).await.unwrap();
+        store.add_timed_unpin(timed(11, 5_000)).await.unwrap();
+        store.add_scheduled_pin(scheduled(12, 3_000)).await.unwrap();
+
+        // The bot was offline until after the first unpin was due
+        let now = 2_000;
+        assert_eq!(overdue(&store.timed_unpins(), now), 1);
+        let task = Task::next(&store).unwrap();
+        assert!(matches!(task, Task::Unpin(ref entry) if entry.message_id == Id::new(10)));
+        assert_eq!(task.wait(now), None);
+
+        // Once it's done, the scheduled pin is next and has to be waited for
+        store.remove_timed_unpin(Id::new(10)).await.unwrap();
+        let task = Task::next(&store).unwrap();
+        assert!(matches!(task, Task::Pin(ref entry) if entry.message_id == Id::new(12)));
+        assert_eq!(task.wait(now), Some(Duration::from_secs(1_000)));
+        assert_eq!(task.wait(3_000), None);
+    }
+
+    #[tokio::test]
+    async fn failed_unpins_wait_for_the_retry() {
+        let store = Store::open(None).await.unwrap();
+        store.add_timed_unpin(timed(10, 1_000)).await.unwrap();
+        store.add_scheduled_pin(scheduled(11, 1_030)).await.unwrap();
+
+        let now = 1_000;
+        let retry_at = now + RETRY_DELAY.as_secs();
+        store
+            .postpone_timed_unpin(Id::new(10), retry_at)
+            .await
+            .unwrap();
+
+        // The scheduled pin is due before the retry now
+        let task = Task::next(&store).unwrap();
+        assert!(matches!(task, Task::Pin(_)));
+        store.remove_scheduled_pin(Id::new(11)).await.unwrap();
+        let task = Task::next(&store).unwrap();
+        assert_eq!(task.wait(now), Some(RETRY_DELAY));
+        assert_eq!(task.wait(retry_at), None);
+    }
+
+    #[test]
+    fn nothing_is_overdue_before_its_time() {
+        let pending = [timed(10, 1_000), timed(11, 2_000)];
+        assert_eq!(overdue(&pending, 999), 0);
+        assert_eq!(overdue(&pending, 1_000), 1);
+        assert_eq!(overdue(&pending, 5_000), 2);
+    }
+}
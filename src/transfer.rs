@@ -0,0 +1,224 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::{
+        message::component::{ActionRow, Button, ButtonStyle},
+        Message,
+    },
+    guild::Permissions,
+    id::{
+        marker::{ChannelMarker, GuildMarker},
+        Id,
+    },
+};
+
+use crate::{
+    bulk, confirmation, copy, defer, do_pin, ephemeral, error, events::PinEvent, gate, jump_link,
+    locale, lock, metrics, pinlog, pins::PinApi, Context,
+};
+
+/// Why a single pin of the source channel was not transferred
+enum Failure {
+    /// The repost could not be sent
+    Repost,
+    /// The repost was sent, but could not be pinned
+    Pin,
+}
+
+/// Handle `/pin-transfer`, which reposts and pins every pin of this channel in another channel
+pub async fn transfer(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let target_id = data.options.iter().find_map(|option| match option.value {
+        CommandOptionValue::Channel(id) => Some(id),
+        _ => None,
+    });
+    // Discord computes the permissions of the user in every channel passed as an option
+    let target = target_id.and_then(|id| data.resolved.as_ref()?.channels.get(&id));
+    // Nothing changes in this channel, so only the destination needs our permissions
+    let rejection = if !lock::can_manage_guild(event) {
+        // Server admins can hand the command to anyone, which must not bypass this
+        Some("You need the **Manage Server** permission to do this.".to_owned())
+    } else {
+        match target {
+            Some(target) if target.id == channel_id => {
+                Some("Pick a different channel to transfer the pins to.".to_owned())
+            }
+            Some(target)
+                if !target
+                    .permissions
+                    .contains(Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES) =>
+            {
+                Some(format!("You can't send messages in <#{}>.", target.id))
+            }
+            Some(target) => gate::rejection(ctx, event, guild_id, target.id, true),
+            None => Some("Pick the channel to transfer the pins to.".to_owned()),
+        }
+    };
+    if let Some(content) = rejection {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+    let (Some(target), Some(user)) = (target, event.author()) else {
+        return Ok(());
+    };
+    let target_id = target.id;
+
+    client
+        .create_response(
+            event.id,
+            &event.token,
+            &defer(ctx.config.response_visibility),
+        )
+        .await?;
+
+    let request = client
+        .create_followup(&event.token)
+        .flags(ctx.config.response_visibility.flags());
+
+    let pins = pins(ctx, channel_id).await?;
+    let existing = match ctx.http.pinned_messages(target_id).await {
+        Ok(pins) => pins.len(),
+        Err(e) => {
+            log::warn!(
+                "[{}] Failed to load the pins of {}: {}",
+                channel_id,
+                target_id,
+                e
+            );
+            request
+                .content(&format!("I can't see the pins of <#{target_id}>."))?
+                .await?;
+            return Ok(());
+        }
+    };
+    if pins.is_empty() {
+        request
+            .content("There are no pinned messages in this channel.")?
+            .await?;
+        return Ok(());
+    }
+
+    // The newest pins are kept if not all of them fit, and reposted oldest first like the originals
+    let room = bulk::MAX_PINS.saturating_sub(existing);
+    let skipped = pins.len().saturating_sub(room);
+    let selected: Vec<&Message> = pins.iter().take(room).rev().collect();
+
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{username} transferred the pins of {channel_name}");
+    let mut transferred = 0;
+    let mut failures = Vec::new();
+    for (index, message) in selected.iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(bulk::PIN_SPACING).await;
+        }
+
+        let button = row!(link!(
+            "Original",
+            jump_link(guild_id, channel_id, message.id)
+        ));
+        let repost = ctx
+            .http
+            .create_message(target_id)
+            .allowed_mentions(Some(&confirmation::NO_PINGS))
+            .components(&button)?
+            .content(&copy::copy_content(message, channel_id))?
+            .await;
+        let repost = match repost {
+            Ok(response) => response.model().await?,
+            Err(e) => {
+                metrics::observe_http_error(&e, "create_message");
+                log::error!("Failed to repost pin due to error: {e}");
+                failures.push(Failure::Repost);
+                // Every other repost would fail the same way
+                if matches!(
+                    error::api_code(&e),
+                    Some(error::MISSING_ACCESS | error::MISSING_PERMISSIONS)
+                ) {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        match do_pin(&ctx.http, target_id, repost.id, true, &reason).await {
+            Ok(()) => {
+                transferred += 1;
+                pinlog::record(
+                    ctx,
+                    PinEvent::new(true, guild_id, target_id, repost.id, user.id)
+                        .author(repost.author.id),
+                )
+                .await;
+            }
+            Err(e) => {
+                metrics::observe_pin_error(&e, "create_pin");
+                log::error!("Failed to pin repost due to error: {e}");
+                failures.push(Failure::Pin);
+            }
+        }
+    }
+
+    let locale = locale(&ctx.config, event);
+    let mut content = format!(
+        "\u{1F4CC} **{username}** transferred {transferred} of {} to <#{target_id}>.",
+        confirmation::count(pins.len(), "pin", "pins", locale)
+    );
+    if skipped > 0 {
+        content.push_str(&format!(
+            "\nSkipped {}, <#{target_id}> has no room for them.",
+            confirmation::count(skipped, "older pin", "older pins", locale)
+        ));
+    }
+    let reposts = failures
+        .iter()
+        .filter(|it| matches!(it, Failure::Repost))
+        .count();
+    if reposts > 0 {
+        content.push_str(&format!("\n{reposts} could not be reposted."));
+    }
+    let unpinned = failures.len() - reposts;
+    if unpinned > 0 {
+        content.push_str(&format!(
+            "\n{unpinned} were reposted, but could not be pinned."
+        ));
+    }
+    let unattempted = selected.len() - transferred - failures.len();
+    if unattempted > 0 {
+        content.push_str(&format!(
+            "\n{unattempted} were not attempted, since I'm missing permissions in <#{target_id}>."
+        ));
+    }
+
+    log::info!("[{}] {}", channel_id, content);
+    request
+        .allowed_mentions(Some(&confirmation::NO_PINGS))
+        .content(&content)?
+        .await?;
+    Ok(())
+}
+
+async fn pins(ctx: &Context, channel_id: Id<ChannelMarker>) -> Result<Vec<Message>> {
+    match ctx.http.pinned_messages(channel_id).await {
+        Ok(pins) => Ok(pins),
+        Err(e) => {
+            if let Some(e) = e.downcast_ref() {
+                metrics::observe_http_error(e, "pins");
+            }
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        Interaction,
+    },
+    channel::message::Embed,
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{marker::GuildMarker, Id},
+};
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFooterBuilder};
+
+use crate::{confirmation, locale, pinlog, time, Context};
+
+const DAY: u64 = 24 * 60 * 60;
+const WEEK: u64 = 7 * DAY;
+/// Days shown with the daily chart, and weeks with the weekly one
+const DAYS: u64 = 7;
+const WEEKS: u64 = 8;
+/// Length of the bar of the busiest period
+const BAR_WIDTH: usize = 16;
+const COLOR: u32 = 0x5865F2;
+
+/// Handle `/pin-trends`, which charts the pins and unpins of the guild over the recent days or weeks
+pub async fn pin_trends(
+    ctx: &Context,
+    event: &Interaction,
+    data: &CommandData,
+    guild_id: Id<GuildMarker>,
+) -> Result<()> {
+    let weekly = data.options.iter().any(
+        |option| matches!(option.value, CommandOptionValue::String(ref value) if value == "week"),
+    );
+
+    let response = match pinlog::rejection(ctx, event) {
+        Some(response) => response,
+        None => InteractionResponse {
+            kind: InteractionResponseType::ChannelMessageWithSource,
+            data: Some(InteractionResponseData {
+                embeds: Some(vec![chart(ctx, event, guild_id, weekly)]),
+                ..Default::default()
+            }),
+        },
+    };
+
+    ctx.http
+        .interaction(event.application_id)
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+fn chart(ctx: &Context, event: &Interaction, guild_id: Id<GuildMarker>, weekly: bool) -> Embed {
+    let (length, periods) = if weekly { (WEEK, WEEKS) } else { (DAY, DAYS) };
+    // Periods end with the current UTC day, the oldest one comes first
+    let end = (time::now() / DAY + 1) * DAY;
+    let start = end.saturating_sub(length * periods);
+
+    let mut counts = vec![(0usize, 0usize); periods as usize];
+    for action in ctx.store.pin_actions(guild_id) {
+        if action.timestamp < start || action.timestamp >= end {
+            continue;
+        }
+        let (pins, unpins) = &mut counts[((action.timestamp - start) / length) as usize];
+        if action.pin {
+            *pins += 1;
+        } else {
+            *unpins += 1;
+        }
+    }
+
+    let title = if weekly {
+        format!("Pin activity of the last {WEEKS} weeks")
+    } else {
+        format!("Pin activity of the last {DAYS} days")
+    };
+    let total_pins: usize = counts.iter().map(|(pins, _)| pins).sum();
+    let total_unpins: usize = counts.iter().map(|(_, unpins)| unpins).sum();
+    let embed = EmbedBuilder::new().title(title).color(COLOR);
+
+    if total_pins + total_unpins == 0 {
+        return embed
+            .description("Nothing was pinned or unpinned through me in this time.")
+            .build();
+    }
+
+    let locale = locale(&ctx.config, event);
+    let busiest = counts
+        .iter()
+        .map(|(pins, unpins)| pins + unpins)
+        .max()
+        .unwrap_or(1);
+    let lines: Vec<String> = counts
+        .iter()
+        .enumerate()
+        .map(|(index, (pins, unpins))| {
+            let from = start + index as u64 * length;
+            let width = (pins + unpins) * BAR_WIDTH / busiest;
+            let bar = if width == 0 {
+                "\u{2581}".to_owned()
+            } else {
+                "\u{2588}".repeat(width)
+            };
+            format!(
+                "<t:{from}:d> `{bar}` {} pinned, {} unpinned",
+                confirmation::number(*pins, locale),
+                confirmation::number(*unpins, locale)
+            )
+        })
+        .collect();
+
+    embed
+        .description(lines.join("\n"))
+        .footer(EmbedFooterBuilder::new(format!(
+            "{} and {} in total",
+            confirmation::count(total_pins, "pin", "pins", locale),
+            confirmation::count(total_unpins, "unpin", "unpins", locale)
+        )))
+        .build()
+}
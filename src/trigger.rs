@@ -0,0 +1,86 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::channel::{
+    message::component::{ActionRow, Button, ButtonStyle},
+    Message,
+};
+
+use crate::{
+    confirmation, do_pin, events::PinEvent, freeze, grant, highlights, jump_link, metrics, mirror,
+    pinlog, Context,
+};
+
+/// Pin the referenced message when someone replies to it with the configured text trigger
+pub async fn handle(ctx: &Context, message: &Message) -> Result<()> {
+    let Some(ref trigger) = ctx.config.text_trigger else {
+        return Ok(());
+    };
+    if !ctx.config.enable_pin {
+        return Ok(());
+    }
+
+    if message.author.bot || message.content.trim() != trigger.as_str() {
+        return Ok(());
+    }
+
+    let (Some(guild_id), Some(target)) = (message.guild_id, message.referenced_message.as_deref())
+    else {
+        return Ok(());
+    };
+    let target_id = target.id;
+
+    if !ctx.config.is_guild_allowed(guild_id)
+        || ctx.config.is_account_too_new(message.author.id)
+        || freeze::frozen_until(ctx, message.channel_id).is_some()
+    {
+        return Ok(());
+    }
+
+    let roles = message
+        .member
+        .as_ref()
+        .map_or(&[][..], |member| &member.roles);
+    if grant::missing_role(ctx, guild_id, Some(message.author.id), true, roles).is_some() {
+        return Ok(());
+    }
+
+    let http = &ctx.http;
+    let channel_id = message.channel_id;
+    let username = &message.author.name;
+
+    // Messages don't carry the name of their channel and there is no cache to look it up
+    let channel_name = ctx.config.channel_name(None);
+    let reason = format!("{username} pinned a message in {channel_name}");
+    let result = do_pin(http, channel_id, target_id, true, &reason).await;
+
+    let request = http.create_message(channel_id).reply(message.id);
+    if let Err(e) = result {
+        metrics::observe_pin_error(&e, "create_pin");
+        log::error!("Failed to process pin due to error: {}", e);
+        request
+            .content(&ctx.config.pin_error_message(e.code()))?
+            .await?;
+    } else {
+        pinlog::record(
+            ctx,
+            PinEvent::new(true, guild_id, channel_id, target_id, message.author.id)
+                .author(target.author.id),
+        )
+        .await;
+
+        highlights::post(ctx, guild_id, target).await;
+        mirror::post(ctx, guild_id, target).await;
+
+        let content = confirmation::content(&ctx.config, &message.author, target.author.id, true);
+        let button = row!(link!("Message", jump_link(guild_id, channel_id, target_id)));
+
+        log::info!("[{}] {}", channel_id, content);
+        request
+            .allowed_mentions(Some(&confirmation::NO_PINGS))
+            .components(&button)?
+            .content(&content)?
+            .await?;
+    }
+
+    Ok(())
+}
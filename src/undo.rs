@@ -0,0 +1,188 @@
+use anyhow::Result;
+use tracing as log;
+use twilight_model::{
+    application::interaction::{message_component::MessageComponentInteractionData, Interaction},
+    channel::message::{
+        component::{ActionRow, Button, ButtonStyle},
+        MessageFlags,
+    },
+    http::interaction::{InteractionResponse, InteractionResponseData, InteractionResponseType},
+    id::{
+        marker::{ChannelMarker, GuildMarker, MessageMarker, UserMarker},
+        Id,
+    },
+};
+
+use crate::{
+    do_pin, ephemeral, events::PinEvent, gate, jump_link, lock, metrics, pinlog, time, Context,
+};
+
+/// Prefix of the undo button custom id, followed by the action, message, user and expiry
+pub const BUTTON_PREFIX: &str = "undo";
+
+/// Offer the user to reverse the pin or unpin they just did, privately.
+///
+/// The pin itself already happened, so failures are only logged.
+pub async fn offer(
+    ctx: &Context,
+    event: &Interaction,
+    message_id: Id<MessageMarker>,
+    user_id: Id<UserMarker>,
+    pin: bool,
+) {
+    if ctx.config.undo_secs == 0 {
+        return;
+    }
+
+    if let Err(e) = send(ctx, event, message_id, user_id, pin).await {
+        log::warn!("Failed to offer undo: {e}");
+    }
+}
+
+async fn send(
+    ctx: &Context,
+    event: &Interaction,
+    message_id: Id<MessageMarker>,
+    user_id: Id<UserMarker>,
+    pin: bool,
+) -> Result<()> {
+    let expires_at = time::now().saturating_add(ctx.config.undo_secs);
+    let action = if pin { "pin" } else { "unpin" };
+    let button = Button {
+        style: ButtonStyle::Secondary,
+        url: None,
+        custom_id: Some(format!(
+            "{BUTTON_PREFIX}:{action}:{message_id}:{user_id}:{expires_at}"
+        )),
+        disabled: false,
+        label: Some(format!("Undo {action}")),
+        emoji: None,
+    };
+    ctx.http
+        .interaction(event.application_id)
+        .create_followup(&event.token)
+        .flags(MessageFlags::EPHEMERAL)
+        .components(&row!(button))?
+        .content(&format!(
+            "Made a mistake? You can undo this until <t:{expires_at}:T>."
+        ))?
+        .await?;
+    Ok(())
+}
+
+/// Reverse the pin or unpin once the undo button is clicked
+pub async fn undo(
+    ctx: &Context,
+    event: &Interaction,
+    data: &MessageComponentInteractionData,
+) -> Result<()> {
+    let client = ctx.http.interaction(event.application_id);
+
+    let (Some(guild_id), Some(user), Some(channel_id)) = (
+        event.guild_id,
+        event.author(),
+        event.channel.as_ref().map(|channel| channel.id),
+    ) else {
+        return Ok(());
+    };
+    let mut parts = data.custom_id.split(':').skip(1);
+    let (Some(action), Some(message_id), Some(actor_id), Some(expires_at)) = (
+        parts.next(),
+        parts.next().and_then(|it| it.parse().ok()),
+        parts.next().and_then(|it| it.parse().ok()),
+        parts.next().and_then(|it| it.parse::<u64>().ok()),
+    ) else {
+        return Ok(());
+    };
+    // Undoing a pin means unpinning the message again
+    let pin = action != "pin";
+
+    if time::now() > expires_at {
+        let response = InteractionResponse {
+            kind: InteractionResponseType::UpdateMessage,
+            data: Some(InteractionResponseData {
+                content: Some("It's too late to undo this now.".to_owned()),
+                components: Some(Vec::new()),
+                ..Default::default()
+            }),
+        };
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(content) = rejection(ctx, event, guild_id, channel_id, message_id, actor_id, pin) {
+        client
+            .create_response(event.id, &event.token, &ephemeral(content))
+            .await?;
+        return Ok(());
+    }
+
+    let username = &user.name;
+    let channel_name = ctx.config.channel_name(event.channel.as_ref());
+    let reason = format!("{username} undid their {action} in {channel_name}");
+    if let Err(e) = do_pin(&ctx.http, channel_id, message_id, pin, &reason).await {
+        metrics::observe_pin_error(&e, if pin { "create_pin" } else { "delete_pin" });
+        log::error!("Failed to process pin due to error: {}", e);
+        let response = ephemeral(ctx.config.pin_error_message(e.code()));
+        client
+            .create_response(event.id, &event.token, &response)
+            .await?;
+        return Ok(());
+    }
+
+    if ctx.store.remove_timed_unpin(message_id).await? {
+        ctx.scheduler.wake();
+    }
+    pinlog::record(
+        ctx,
+        PinEvent::new(pin, guild_id, channel_id, message_id, user.id),
+    )
+    .await;
+
+    log::info!("[{}] {} undid their {}", channel_id, username, action);
+    let content = if pin {
+        "Undone, the message is pinned again."
+    } else {
+        "Undone, the message is no longer pinned."
+    };
+    let button = row!(link!(
+        "Message",
+        jump_link(guild_id, channel_id, message_id)
+    ));
+    let response = InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(InteractionResponseData {
+            content: Some(content.to_owned()),
+            components: Some(button.into()),
+            ..Default::default()
+        }),
+    };
+    client
+        .create_response(event.id, &event.token, &response)
+        .await?;
+    Ok(())
+}
+
+/// Why the user can't undo here, if they can't
+fn rejection(
+    ctx: &Context,
+    event: &Interaction,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    actor_id: Id<UserMarker>,
+    pin: bool,
+) -> Option<String> {
+    // Settings could have changed since the button was sent
+    if event.author().is_none_or(|user| user.id != actor_id) {
+        Some("Only the person who did this can undo it.".to_owned())
+    } else if let Some(content) = gate::rejection(ctx, event, guild_id, channel_id, pin) {
+        Some(content)
+    } else if !pin && lock::prevents_unpin(ctx, event, message_id) {
+        Some(lock::LOCKED.to_owned())
+    } else {
+        None
+    }
+}